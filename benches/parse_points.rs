@@ -0,0 +1,87 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Measures parsing a collection with 50,000 point values.
+//!
+//! Usage: cargo bench --bench parse_points
+//!
+//! Prints allocations and time per parse, and those of storing the parsed values
+//! as `PropValue`s and as Strings, the representation before short values were stored inline.
+
+extern crate sgf;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use sgf::*;
+
+const POINTS: usize = 50_000;
+const POINTS_PER_NODE: usize = 100;
+const ROUNDS: u32 = 10;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Returns a game whose nodes have AB or AW with POINTS_PER_NODE points each.
+fn generate() -> String {
+    let mut sgf = "(;FF[4]GM[1]SZ[19]".to_string();
+    for i in 0..POINTS {
+        if i % POINTS_PER_NODE == 0 {
+            sgf.push_str([";AB", ";AW"][i / POINTS_PER_NODE % 2]);
+        }
+        let x = (b'a' + (i % 19) as u8) as char;
+        let y = (b'a' + (i / 19 % 19) as u8) as char;
+        sgf.push_str(&format!("[{}{}]", x, y));
+    }
+    sgf.push(')');
+    sgf
+}
+
+/// Returns allocations and time per round of f.
+fn measure<T, F: Fn() -> T>(f: F) -> (usize, Duration) {
+    let start = ALLOCATIONS.load(Ordering::Relaxed);
+    let time = Instant::now();
+    for _ in 0..ROUNDS {
+        drop(f());
+    }
+    let elapsed = time.elapsed();
+    ((ALLOCATIONS.load(Ordering::Relaxed) - start) / ROUNDS as usize, elapsed / ROUNDS)
+}
+
+fn main() {
+    let sgf = generate();
+    let collection = SgfCollection::from_sgf(&sgf).expect("the generated game is valid");
+    // values of each property as the parser passes them to storage
+    let properties = collection[0].iter_depth_first()
+        .flat_map(|node| node.iter())
+        .map(|(_, values)| values)
+        .collect::<Vec<_>>();
+    let values = properties.iter().map(|values| values.len()).sum::<usize>();
+
+    let (allocations, elapsed) = measure(|| SgfCollection::from_sgf(&sgf).expect("the generated game is valid"));
+    println!("{} values in {} properties", values, properties.len());
+    println!("parse: {} allocations, {:?}", allocations, elapsed);
+    let (allocations, elapsed) = measure(|| properties.iter()
+        .map(|values| values.iter().map(|&v| PropValue::from(v)).collect::<Vec<_>>())
+        .collect::<Vec<_>>());
+    println!("storage as PropValues: {} allocations, {:?}", allocations, elapsed);
+    let (allocations, elapsed) = measure(|| properties.iter()
+        .map(|values| values.iter().map(|&v| v.to_string()).collect::<Vec<_>>())
+        .collect::<Vec<_>>());
+    println!("storage as Strings: {} allocations, {:?}", allocations, elapsed);
+}
@@ -0,0 +1,34 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Converts an SGF file into another charset.
+//!
+//! Usage: cargo run --example convert -- INPUT [ENCODING]
+//!
+//! INPUT is read in the charset declared by its CA, or as UTF-8 or ISO-8859-1 without CA.
+//! The collection is written to stdout in ENCODING, UTF-8 by default, with CA of every game set to it.
+
+extern crate sgf;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use sgf::*;
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() < 2 {
+        eprintln!("usage: convert INPUT [ENCODING]");
+        std::process::exit(2);
+    }
+    let encoding = args.get(2).map_or("UTF-8", |e| &e[..]);
+    let bytes = fs::read(&args[1]).expect("failed to read the input");
+    let result = SgfCollection::from_bytes(&bytes).and_then(|collection| collection.to_bytes(encoding));
+    match result {
+        Ok(output) => io::stdout().write_all(&output).expect("failed to write stdout"),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+    }
+}
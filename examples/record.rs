@@ -0,0 +1,29 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Records moves read from stdin as a game.
+//!
+//! Usage: cargo run --example record -- [SIZE] < moves.txt
+//!
+//! Each line of stdin is a point like "pd" or "pass". Colors alternate from Black.
+//! The game is written to stdout.
+
+extern crate sgf;
+
+use std::env;
+use std::io::{self, BufRead};
+use sgf::*;
+
+fn main() {
+    let size = env::args().nth(1).and_then(|s| s.parse().ok()).unwrap_or(19);
+    let stdin = io::stdin();
+    let lines = stdin.lock().lines().collect::<Result<Vec<_>, _>>().expect("failed to read stdin");
+    let points = lines.iter().map(|line| line.trim()).filter(|point| !point.is_empty());
+    match SgfNode::from_points(size, points) {
+        Ok(game) => println!("{}", SgfCollection::new(vec![game])),
+        Err(e) => {
+            eprintln!("invalid move: {:?}", e);
+            std::process::exit(1);
+        },
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Validates an SGF file, prints issues and writes the repaired collection.
+//!
+//! Usage: cargo run --example validate -- INPUT [OUTPUT]
+//!
+//! Moves in root nodes with game info are split into new nodes.
+//! The repaired collection is written to OUTPUT or stdout.
+
+extern crate sgf;
+
+use std::env;
+use std::fs;
+use sgf::*;
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() < 2 {
+        eprintln!("usage: validate INPUT [OUTPUT]");
+        std::process::exit(2);
+    }
+    let sgf = fs::read_to_string(&args[1]).expect("failed to read the input");
+    let mut collection = match SgfCollection::from_sgf(&sgf) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("parse error: {:?}", e);
+            std::process::exit(1);
+        },
+    };
+    for (i, report) in collection.validate_and_repair().iter().enumerate() {
+        for issue in report.issues.iter() {
+            println!("game {} {:?} {:?} at {:?}: {}", i + 1, issue.severity, issue.kind, issue.path, issue.message);
+        }
+        if report.root_move_split {
+            println!("game {}: split the move in the root", i + 1);
+        }
+    }
+    let output = format!("{}", collection);
+    match args.get(2) {
+        Some(path) => fs::write(path, output).expect("failed to write the output"),
+        None => println!("{}", output),
+    }
+}
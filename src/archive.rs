@@ -0,0 +1,194 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Read-only archive of SGF games parsed on demand
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use sgf_node::*;
+use extract::scan_game_tree;
+use parser::*;
+
+const CACHE_CAPACITY: usize = 16;
+
+/// A collection file indexed without building trees.
+/// Each game is parsed when it is requested and recently parsed games are cached.
+///
+/// # Example
+///
+/// ```no_run
+/// use sgf::*;
+///
+/// let archive = SgfArchive::open("games.sgf").unwrap();
+/// for i in 0..archive.len() {
+///     println!("{:?}", archive.summary(i).black_player);
+/// }
+/// let game = archive.game(0).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SgfArchive {
+    text: String,
+    ranges: Vec<Range<usize>>,
+    cache: Mutex<VecDeque<(usize, Arc<SgfNode>)>>,
+}
+
+impl SgfArchive {
+    /// Reads a file in UTF-8 and indexes byte ranges of its games.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<SgfArchive> {
+        Ok(SgfArchive::from_string(fs::read_to_string(path)?))
+    }
+
+    /// Indexes byte ranges of games in `text`.
+    /// A broken game tree extends to the end of the text and its parse fails.
+    pub fn from_string(text: String) -> SgfArchive {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = text[start..].find('(') {
+            let begin = start + offset;
+            match scan_game_tree(&text, begin) {
+                Some(end) => {
+                    ranges.push(begin..end);
+                    start = end;
+                },
+                None => {
+                    ranges.push(begin..text.len());
+                    break;
+                },
+            }
+        }
+        SgfArchive {
+            text,
+            ranges,
+            cache: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Returns the number of games.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns true if there are no games.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the source text of the index-th game.
+    /// Panics if index is out of range.
+    pub fn game_text(&self, index: usize) -> &str {
+        &self.text[self.ranges[index].clone()]
+    }
+
+    /// Returns the game info of the index-th game parsing only its root node.
+    /// A root node which fails to parse returns the default.
+    /// Panics if index is out of range.
+    pub fn summary(&self, index: usize) -> GameInfo {
+        let text = self.game_text(index);
+        let root = match (text.find(';'), root_node_end(text)) {
+            (Some(begin), Some(end)) if begin < end => format!("({})", &text[begin..end]),
+            _ => return GameInfo::default(),
+        };
+        match collection(&root) {
+            Ok(c) => c[0].game_info(),
+            Err(_) => GameInfo::default(),
+        }
+    }
+
+    /// Returns the index-th game.
+    /// The game is parsed unless it is in the cache of recently parsed games.
+    /// Offsets in a parse error are relative to the beginning of the game.
+    /// Panics if index is out of range.
+    pub fn game(&self, index: usize) -> ParseResult<Arc<SgfNode>> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(position) = cache.iter().position(|&(i, _)| i == index) {
+            let entry = cache.remove(position).unwrap();
+            let game = entry.1.clone();
+            cache.push_front(entry);
+            return Ok(game);
+        }
+        let game = Arc::new(collection(self.game_text(index))?.into_iter().next().unwrap());
+        cache.push_front((index, game.clone()));
+        cache.truncate(CACHE_CAPACITY);
+        Ok(game)
+    }
+}
+
+/// Returns the end position of the first node in a game tree text.
+fn root_node_end(text: &str) -> Option<usize> {
+    let begin = text.find(';')?;
+    let bytes = text.as_bytes();
+    let mut in_value = false;
+    let mut i = begin + 1;
+    while i < bytes.len() {
+        match (in_value, bytes[i]) {
+            (true, b'\\') => i += 1,
+            (true, b']') => in_value = false,
+            (false, b'[') => in_value = true,
+            (false, b';') | (false, b'(') | (false, b')') => return Some(i),
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::process;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// A file in the temporary directory with a name unique to the process and the time,
+    /// which is removed when dropped even if the test fails.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(prefix: &str) -> TempFile {
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+            TempFile(env::temp_dir().join(format!("{}-{}-{}.sgf", prefix, process::id(), nanos)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_archive() {
+        let mut text = String::new();
+        for i in 0..20 {
+            text.push_str(&format!("(;FF[4]PB[black {}]PW[white\\]{}]RE[B+R]C[(;a]\n;B[aa](;W[bb])(;W[cc]))\n", i, i));
+        }
+        let file = TempFile::new("rust-sgf-test-archive");
+        File::create(&file.0).unwrap().write_all(text.as_bytes()).unwrap();
+        let archive = SgfArchive::open(&file.0).unwrap();
+        let eager = SgfCollection::from_sgf(&text).unwrap();
+        assert_eq!(archive.len(), eager.len());
+        for i in 0..archive.len() {
+            assert_eq!(archive.summary(i), eager[i].game_info());
+        }
+        assert_eq!(*archive.game(5).unwrap(), eager[5]);
+        assert!(Arc::ptr_eq(&archive.game(5).unwrap(), &archive.game(5).unwrap()));
+        let path = file.0.clone();
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_archive_broken_game() {
+        let archive = SgfArchive::from_string("(;FF[4]) (;FF[4]C[oops".to_string());
+        assert_eq!(archive.len(), 2);
+        assert!(archive.game(0).is_ok());
+        assert!(archive.game(1).is_err());
+    }
+}
@@ -0,0 +1,1124 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Board reconstruction of Go
+
+use std::collections::{HashMap, HashSet};
+use sgf_node::*;
+
+/// Go features which have limits of board sizes.
+///
+/// | feature | rectangular boards | maximum size |
+/// |---------|--------------------|--------------|
+/// | Coordinates | supported | 52x52 |
+/// | Reconstruction | supported | 52x52 |
+/// | Scoring | supported | 52x52 |
+/// | Handicap | not supported | 25x25 |
+/// | Gtp | not supported | 25x25 |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardFeature {
+    /// SGF points 'a'-'z' and 'A'-'Z'
+    Coordinates,
+    /// Board reconstruction by `Board`
+    Reconstruction,
+    /// Scoring of final positions
+    Scoring,
+    /// Standard handicap placement
+    Handicap,
+    /// Vertices of Go Text Protocol, which skip 'I'
+    Gtp,
+}
+
+/// A board size validated for SGF coordinates, i.e., from 1x1 up to 52x52.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardSpec {
+    cols: u8,
+    rows: u8,
+}
+
+impl BoardSpec {
+    /// Returns a spec of cols x rows, or UnsupportedBoard for Coordinates if it is empty or larger than 52.
+    pub fn new(cols: u8, rows: u8) -> Result<BoardSpec, SgfError> {
+        let spec = BoardSpec { cols, rows };
+        spec.supports(BoardFeature::Coordinates)?;
+        Ok(spec)
+    }
+
+    /// Returns (cols, rows).
+    pub fn size(&self) -> (u8, u8) {
+        (self.cols, self.rows)
+    }
+
+    /// Returns Ok if feature supports the size, or UnsupportedBoard otherwise.
+    /// See `BoardFeature` for the limits.
+    pub fn supports(&self, feature: BoardFeature) -> Result<(), SgfError> {
+        let (square_only, max) = match feature {
+            BoardFeature::Coordinates | BoardFeature::Reconstruction | BoardFeature::Scoring => (false, 52),
+            BoardFeature::Handicap | BoardFeature::Gtp => (true, 25),
+        };
+        let supported = self.cols >= 1 && self.rows >= 1 && self.cols <= max && self.rows <= max &&
+            (!square_only || self.cols == self.rows);
+        if supported {
+            Ok(())
+        } else {
+            Err(SgfError::UnsupportedBoard { cols: self.cols, rows: self.rows, feature })
+        }
+    }
+
+    /// Converts zero-based (column, row) into a GTP vertex like "D4".
+    /// Columns are 'A'-'Z' skipping 'I' and rows are counted from the bottom.
+    /// Returns UnsupportedBoard for boards which Gtp doesn't support and OutOfRange for points off the board.
+    pub fn gtp_vertex(&self, (x, y): (u8, u8)) -> Result<String, SgfError> {
+        self.supports(BoardFeature::Gtp)?;
+        if x >= self.cols || y >= self.rows {
+            return Err(SgfError::OutOfRange(format!("{:?}", (x, y))));
+        }
+        let column = if x < 8 { b'A' + x } else { b'A' + x + 1 };
+        Ok(format!("{}{}", column as char, self.rows - y))
+    }
+}
+
+/// Returns the standard fixed handicap points of Go Text Protocol for count stones on a size x size board.
+/// Points are on the 4th lines for boards of 13 and larger and on the 3rd lines for smaller boards.
+/// Returns None if count is not 2 to 9, the board is smaller than 7x7 or larger than 25x25,
+/// or count exceeds 4 on a 7x7 board or a board of even size.
+pub fn standard_handicap_points(count: u8, size: u8) -> Option<Vec<(u8, u8)>> {
+    if !(2..=9).contains(&count) || !(7..=25).contains(&size) || (count > 4 && (size == 7 || size % 2 == 0)) {
+        return None;
+    }
+    let low = if size >= 13 { 3 } else { 2 };
+    let high = size - 1 - low;
+    let mid = size / 2;
+    let all = [(low, high), (high, low), (low, low), (high, high), (low, mid), (high, mid), (mid, high), (mid, low)];
+    let mut points = match count {
+        2 | 3 | 4 | 6 | 8 => all[..count as usize].to_vec(),
+        _ => all[..count as usize - 1].to_vec(),
+    };
+    if count % 2 == 1 && count >= 5 {
+        points.push((mid, mid));
+    }
+    Some(points)
+}
+
+/// A Go board with stones and prisoners.
+/// Coordinates are zero-based (column, row) from the top-left corner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    width: u8,
+    height: u8,
+    points: Vec<Option<SgfColor>>,
+    black_captures: usize,
+    white_captures: usize,
+    consecutive_passes: usize,
+}
+
+impl Board {
+    /// Returns an empty board of (width, height), or UnsupportedBoard if Reconstruction doesn't support the size.
+    pub fn new((width, height): (u8, u8)) -> Result<Board, SgfError> {
+        BoardSpec::new(width, height)?.supports(BoardFeature::Reconstruction)?;
+        Ok(Board {
+            width,
+            height,
+            points: vec![None; width as usize * height as usize],
+            black_captures: 0,
+            white_captures: 0,
+            consecutive_passes: 0,
+        })
+    }
+
+    /// Returns (width, height).
+    pub fn size(&self) -> (u8, u8) {
+        (self.width, self.height)
+    }
+
+    /// Returns true if (x, y) is on the board.
+    pub fn contains(&self, (x, y): (u8, u8)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    fn index(&self, (x, y): (u8, u8)) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Returns the stone at (x, y), 'B', 'W' or None for an empty point.
+    /// Points out of the board are None.
+    pub fn get(&self, p: (u8, u8)) -> Option<SgfColor> {
+        if self.contains(p) { self.points[self.index(p)] } else { None }
+    }
+
+    /// Puts or removes a stone without captures like setup properties.
+    /// Points out of the board are ignored.
+    pub fn set(&mut self, p: (u8, u8), stone: Option<SgfColor>) {
+        if self.contains(p) {
+            let i = self.index(p);
+            self.points[i] = stone;
+        }
+    }
+
+    /// Returns the number of stones captured by `color` so far.
+    pub fn captures(&self, color: SgfColor) -> usize {
+        if color == 'B' { self.black_captures } else { self.white_captures }
+    }
+
+    /// Returns the number of passes played in a row just before now.
+    pub fn consecutive_passes(&self) -> usize {
+        self.consecutive_passes
+    }
+
+    /// Returns adjacent points on the board.
+    pub fn neighbors(&self, (x, y): (u8, u8)) -> Vec<(u8, u8)> {
+        let mut result = Vec::with_capacity(4);
+        if x > 0 {
+            result.push((x - 1, y));
+        }
+        if x + 1 < self.width {
+            result.push((x + 1, y));
+        }
+        if y > 0 {
+            result.push((x, y - 1));
+        }
+        if y + 1 < self.height {
+            result.push((x, y + 1));
+        }
+        result
+    }
+
+    /// Returns the chain of stones including p and the number of its liberties.
+    /// An empty point returns an empty chain.
+    pub fn chain(&self, p: (u8, u8)) -> (Vec<(u8, u8)>, usize) {
+        let color = match self.get(p) {
+            Some(c) => c,
+            None => return (vec![], 0),
+        };
+        let mut visited = vec![false; self.points.len()];
+        let mut liberty = vec![false; self.points.len()];
+        let mut liberties = 0;
+        let mut chain = vec![p];
+        let mut stack = vec![p];
+        visited[self.index(p)] = true;
+        while let Some(q) = stack.pop() {
+            for n in self.neighbors(q) {
+                let i = self.index(n);
+                match self.points[i] {
+                    None if !liberty[i] => {
+                        liberty[i] = true;
+                        liberties += 1;
+                    },
+                    Some(c) if c == color && !visited[i] => {
+                        visited[i] = true;
+                        chain.push(n);
+                        stack.push(n);
+                    },
+                    _ => (),
+                }
+            }
+        }
+        (chain, liberties)
+    }
+
+    /// Plays a move of `color` at `point`, or a pass if `point` is None, and returns captured points.
+    /// Opponent's chains without liberties are captured, and then the own chain if it has no liberties(suicide).
+    /// Legality such as ko or an occupied point is not checked.
+    /// A point out of the board is regarded as a pass.
+    pub fn play(&mut self, color: SgfColor, point: Option<(u8, u8)>) -> Vec<(u8, u8)> {
+        let p = match point {
+            Some(p) if self.contains(p) => p,
+            _ => {
+                self.consecutive_passes += 1;
+                return vec![];
+            },
+        };
+        self.consecutive_passes = 0;
+        let opponent = if color == 'B' { 'W' } else { 'B' };
+        self.set(p, Some(color));
+        let mut captured = Vec::new();
+        for n in self.neighbors(p) {
+            if self.get(n) == Some(opponent) {
+                let (chain, liberties) = self.chain(n);
+                if liberties == 0 {
+                    for q in chain {
+                        self.set(q, None);
+                        captured.push(q);
+                    }
+                }
+            }
+        }
+        if captured.is_empty() {
+            let (chain, liberties) = self.chain(p);
+            if liberties == 0 {
+                for q in chain {
+                    self.set(q, None);
+                    captured.push(q);
+                }
+                self.add_captures(opponent, captured.len());
+                return captured;
+            }
+        }
+        self.add_captures(color, captured.len());
+        captured
+    }
+
+    fn add_captures(&mut self, color: SgfColor, n: usize) {
+        if color == 'B' {
+            self.black_captures += n;
+        } else {
+            self.white_captures += n;
+        }
+    }
+
+    /// Applies setup properties and a move of the node and returns the move if any.
+    /// Setup properties are applied in the order of AE, AB and AW before the move.
+    /// AE clears the listed points first, so stones of AB and AW win over AE at the same point,
+    /// which FF[4] doesn't allow anyway.
+    pub fn apply_node(&mut self, node: &SgfNode) -> Option<(SgfColor, Option<(u8, u8)>)> {
+        for &(id, stone) in [("AE", None), ("AB", Some('B')), ("AW", Some('W'))].iter() {
+            if let Ok(values) = node.get_property(id) {
+                for p in expand_point_list(values) {
+                    self.set(p, stone);
+                }
+            }
+        }
+        for &color in ['B', 'W'].iter() {
+            if let Ok(v) = node.get_point(&color.to_string()) {
+                let point = self.move_coord(&v);
+                self.play(color, point);
+                return Some((color, point));
+            }
+        }
+        None
+    }
+
+    /// Converts a move value into coordinates. Returns None for a pass, that is,
+    /// an empty value or "tt" on a board up to 19x19, and for invalid values.
+    pub fn move_coord(&self, value: &str) -> Option<(u8, u8)> {
+        if value == "tt" && self.width <= 19 && self.height <= 19 {
+            return None;
+        }
+        point_to_coord(value).and_then(|p| if self.contains(p) { Some(p) } else { None })
+    }
+}
+
+impl SgfNode {
+    /// Sets up a handicap game, that is, HA[count], PL[W] and AB of `standard_handicap_points`.
+    /// Returns UnsupportedBoard if size is not supported for Handicap,
+    /// or OutOfRange without modifying the node if there is no standard placement for count.
+    pub fn set_handicap(&mut self, count: u8, size: u8) -> Result<&mut Self, SgfError> {
+        BoardSpec::new(size, size)?.supports(BoardFeature::Handicap)?;
+        let points = standard_handicap_points(count, size).ok_or_else(|| SgfError::OutOfRange(count.to_string()))?;
+        self.set_number("HA", count as SgfNumber);
+        self.set_color("PL", 'W');
+        self.set_points("AB", points.into_iter().map(coord_to_point).collect());
+        Ok(self)
+    }
+
+    /// Returns the board spec of SZ of the node, 19x19 by default.
+    /// Returns ParseError if SZ is neither a number nor a pair of numbers, OutOfRange if a number is out of 0 to 255,
+    /// and UnsupportedBoard for Coordinates if SZ is out of 1 to 52.
+    pub fn board_spec(&self) -> Result<BoardSpec, SgfError> {
+        if self.get_property("SZ").is_err() {
+            return BoardSpec::new(19, 19);
+        }
+        let (cols, rows) = self.get_number_number("SZ")
+            .or_else(|_| self.get_number("SZ").map(|s| (s, s)))?;
+        let to_u8 = |n: SgfNumber| if (0..=255).contains(&n) { Ok(n as u8) } else { Err(SgfError::OutOfRange(n.to_string())) };
+        BoardSpec::new(to_u8(cols)?, to_u8(rows)?)
+    }
+
+    /// Returns the board size in SZ of the node, 19x19 by default, if feature supports it.
+    /// See `board_spec` for errors.
+    pub(crate) fn board_size(&self, feature: BoardFeature) -> Result<(u8, u8), SgfError> {
+        let spec = self.board_spec()?;
+        spec.supports(feature)?;
+        Ok(spec.size())
+    }
+
+    /// Returns the board size in SZ of the node, or infers it when SZ is missing.
+    ///
+    /// The inference is a best-effort fallback for old or broken files.
+    /// The largest coordinate of moves(B, W) and setup(AB, AE, AW) in the whole tree is rounded up
+    /// to the smallest of 9, 13, 19 and 25 which contains it, or taken as it is beyond 25.
+    /// A move "tt" is regarded as a pass since passes are written so on boards up to 19x19.
+    /// 19x19 is returned if the tree has no such points.
+    /// Returns the error of `board_spec` if SZ is present but not supported.
+    pub fn infer_board_size(&self) -> Result<(u8, u8), SgfError> {
+        if self.get_property("SZ").is_ok() {
+            return self.board_size(BoardFeature::Coordinates);
+        }
+        let max = max_coordinate(self);
+        let size = match max {
+            None => 19,
+            Some(m) => [9, 13, 19, 25].iter().cloned().find(|&s| m < s).unwrap_or(m + 1),
+        };
+        Ok((size, size))
+    }
+
+    /// Returns the board after all nodes in the main line are applied.
+    /// Returns UnsupportedBoard if Reconstruction doesn't support size.
+    pub fn final_position(&self, size: (u8, u8)) -> Result<Board, SgfError> {
+        let mut board = Board::new(size)?;
+        let mut node = self;
+        loop {
+            board.apply_node(node);
+            if node.children.is_empty() {
+                return Ok(board);
+            }
+            node = &node.children[0];
+        }
+    }
+
+    /// Returns the board after move_number moves in the main line, or None if the main line is shorter.
+    /// Setup nodes following the move are also applied, so `board_at(size, 0)` is the position set up
+    /// before the first move, which is the only position of a game without moves.
+    /// Returns UnsupportedBoard if Reconstruction doesn't support size.
+    pub fn board_at(&self, size: (u8, u8), move_number: usize) -> Result<Option<Board>, SgfError> {
+        let mut board = Board::new(size)?;
+        let mut moves = 0;
+        let mut node = self;
+        loop {
+            let has_move = node.get_property("B").is_ok() || node.get_property("W").is_ok();
+            if has_move && moves == move_number {
+                return Ok(Some(board));
+            }
+            if board.apply_node(node).is_some() {
+                moves += 1;
+            }
+            if node.children.is_empty() {
+                return Ok(if moves == move_number { Some(board) } else { None });
+            }
+            node = &node.children[0];
+        }
+    }
+
+    /// Returns moves in the main line as colors and coordinates.
+    /// A pass is None, that is, an empty value or "tt" when the board(SZ, 19x19 by default) is up to 19x19,
+    /// so it never collides with the point (0, 0).
+    /// Returns OutOfRange for a move value which is neither a pass nor a point on the board,
+    /// and the error of `board_spec` if SZ is not supported.
+    pub fn move_sequence(&self) -> Result<Vec<Move>, SgfError> {
+        let size = self.board_size(BoardFeature::Coordinates)?;
+        let mut moves = Vec::new();
+        let mut node = self;
+        loop {
+            for &color in ['B', 'W'].iter() {
+                if let Some(m) = node_move(node, color, size)? {
+                    moves.push(m);
+                }
+            }
+            if node.children.is_empty() {
+                return Ok(moves);
+            }
+            node = &node.children[0];
+        }
+    }
+
+    /// Returns a game on a board of size x size whose main line has moves alternating from Black.
+    /// Each point is a point like "pd", or "pass" or an empty string for a pass.
+    /// Returns the error of `move_sequence` if a point is not on the board or the size is not supported.
+    pub fn from_points<'a, I: IntoIterator<Item = &'a str>>(size: u8, points: I) -> Result<SgfNode, SgfError> {
+        let mut root = SgfNode::new(Vec::new());
+        root.set_number("FF", 4);
+        root.set_number("GM", 1);
+        root.set_number("SZ", SgfNumber::from(size));
+        let mut nodes = Vec::new();
+        for (i, point) in points.into_iter().enumerate() {
+            let point = if point == "pass" { "" } else { point };
+            let mut node = SgfNode::new(Vec::new());
+            node.set_point(if i % 2 == 0 { "B" } else { "W" }, point.to_string());
+            nodes.push(node);
+        }
+        // linked from the last move so that each move is appended in constant time
+        while let Some(node) = nodes.pop() {
+            match nodes.last_mut() {
+                Some(previous) => previous.children.push(node),
+                None => root.children.push(node),
+            }
+        }
+        root.move_sequence()?;
+        Ok(root)
+    }
+
+    /// Returns the color of each move in the main line with the time left for the player after the move,
+    /// BL for Black and WL for White in the node of the move, in seconds.
+    /// The time is None when the node doesn't have it or it is not a real number.
+    /// Both moves of a node with B and W are returned in that order.
+    pub fn move_timing(&self) -> Vec<(SgfColor, Option<SgfReal>)> {
+        let mut timing = Vec::new();
+        for node in self.main_line() {
+            for &(color, time) in [('B', "BL"), ('W', "WL")].iter() {
+                if node.get_property(&color.to_string()).is_ok() {
+                    timing.push((color, node.get_real(time).ok()));
+                }
+            }
+        }
+        timing
+    }
+
+    /// Returns points with a move or a setup stone(AB, AW) in any node of the main line on a board of size.
+    /// Passes and points out of the board are excluded.
+    /// Returns UnsupportedBoard if Reconstruction doesn't support size.
+    pub fn touched_points(&self, size: (u8, u8)) -> Result<HashSet<(u8, u8)>, SgfError> {
+        let board = Board::new(size)?;
+        let mut points = HashSet::new();
+        let mut node = self;
+        loop {
+            for &id in ["AB", "AW"].iter() {
+                if let Ok(values) = node.get_property(id) {
+                    points.extend(expand_point_list(values).into_iter().filter(|&p| board.contains(p)));
+                }
+            }
+            for &id in ["B", "W"].iter() {
+                if let Some(p) = node.get_point(id).ok().and_then(|v| board.move_coord(&v)) {
+                    points.insert(p);
+                }
+            }
+            if node.children.is_empty() {
+                return Ok(points);
+            }
+            node = &node.children[0];
+        }
+    }
+
+    /// Returns, for each move in the main line, the running difference of prisoners,
+    /// that is, stones captured by Black minus stones captured by White so far.
+    /// Returns UnsupportedBoard if Reconstruction doesn't support size.
+    pub fn capture_timeline(&self, size: (u8, u8)) -> Result<Vec<i32>, SgfError> {
+        let mut board = Board::new(size)?;
+        let mut timeline = Vec::new();
+        let mut node = self;
+        loop {
+            if board.apply_node(node).is_some() {
+                timeline.push(board.captures('B') as i32 - board.captures('W') as i32);
+            }
+            if node.children.is_empty() {
+                return Ok(timeline);
+            }
+            node = &node.children[0];
+        }
+    }
+}
+
+/// Returns the largest coordinate of moves and setup points of the node and its descendants.
+fn max_coordinate(node: &SgfNode) -> Option<u8> {
+    let mut max = None;
+    for &id in ["B", "W", "AB", "AE", "AW"].iter() {
+        if let Ok(values) = node.get_property(id) {
+            let values = values.iter().filter(|v| id.len() == 2 || v.as_str() != "tt").cloned().collect::<Vec<_>>();
+            for (x, y) in expand_point_list(&values) {
+                max = max.max(Some(x.max(y)));
+            }
+        }
+    }
+    node.children.iter().map(max_coordinate).fold(max, |a, b| a.max(b))
+}
+
+/// A move as a color and coordinates, which are None for a pass.
+pub type Move = (SgfColor, Option<(u8, u8)>);
+
+/// Returns the move of color in node on a board of size as `move_sequence` reads it, or None if there is none.
+pub(crate) fn node_move(node: &SgfNode, color: SgfColor, (width, height): (u8, u8)) -> Result<Option<Move>, SgfError> {
+    let v = match node.get_point(&color.to_string()) {
+        Ok(v) => v,
+        Err(_) => return Ok(None),
+    };
+    let point = if v.is_empty() || (v == "tt" && width <= 19 && height <= 19) {
+        None
+    } else {
+        match point_to_coord(&v) {
+            Some((x, y)) if x < width && y < height => Some((x, y)),
+            _ => return Err(SgfError::OutOfRange(v)),
+        }
+    };
+    Ok(Some((color, point)))
+}
+
+/// How a game ended, derived from the main line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEndState {
+    /// No end is recorded.
+    Ongoing,
+    /// Two consecutive passes. `at_move` is the move number of the second pass.
+    TwoPasses { at_move: usize },
+    /// RE with "+R" or "+Resign". `at_move` is the number of the last move.
+    Resignation { at_move: usize },
+    /// RE with "+T" or "+Time".
+    TimeLoss,
+    /// Other results such as a score or a forfeit without two passes. `at_move` is the number of the last move.
+    OtherResult { at_move: usize },
+}
+
+/// A row of `SgfNode::to_move_table`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveRow {
+    /// 1-origin number of the move in the main line
+    pub move_number: usize,
+    pub color: SgfColor,
+    /// None for a pass
+    pub point: Option<(u8, u8)>,
+    /// Number of stones captured by the move
+    pub captured: usize,
+    /// True for the move which ended the game. Moves after it should not be processed.
+    pub game_over: bool,
+}
+
+impl SgfNode {
+    /// Returns how the game ended.
+    /// The first two consecutive passes in the main line take precedence over RE.
+    /// The board size is taken from SZ of the node, 19x19 by default.
+    /// Returns the error of `board_spec` or UnsupportedBoard for Reconstruction if SZ is not supported.
+    pub fn game_end_state(&self) -> Result<GameEndState, SgfError> {
+        let mut board = Board::new(self.board_size(BoardFeature::Reconstruction)?)?;
+        let mut moves = 0;
+        let mut node = self;
+        loop {
+            if board.apply_node(node).is_some() {
+                moves += 1;
+                if board.consecutive_passes() == 2 {
+                    return Ok(GameEndState::TwoPasses { at_move: moves });
+                }
+            }
+            if node.children.is_empty() {
+                break;
+            }
+            node = &node.children[0];
+        }
+        Ok(match self.get_simple_text("RE") {
+            Ok(ref re) if re.ends_with("+R") || re.ends_with("+Resign") => GameEndState::Resignation { at_move: moves },
+            Ok(ref re) if re.ends_with("+T") || re.ends_with("+Time") => GameEndState::TimeLoss,
+            Ok(ref re) if re.contains('+') || re.starts_with('0') || re.starts_with("Draw") => GameEndState::OtherResult { at_move: moves },
+            _ => GameEndState::Ongoing,
+        })
+    }
+
+    /// Returns a table of moves in the main line.
+    /// The row at which the game ended according to `game_end_state` is marked `game_over`.
+    /// For TimeLoss, the last move is marked.
+    /// Returns UnsupportedBoard if Reconstruction doesn't support size, or the error of `game_end_state`.
+    pub fn to_move_table(&self, size: (u8, u8)) -> Result<Vec<MoveRow>, SgfError> {
+        let mut board = Board::new(size)?;
+        let mut rows: Vec<MoveRow> = Vec::new();
+        let mut node = self;
+        loop {
+            let before = board.captures('B') + board.captures('W');
+            if let Some((color, point)) = board.apply_node(node) {
+                rows.push(MoveRow {
+                    move_number: rows.len() + 1,
+                    color,
+                    point,
+                    captured: board.captures('B') + board.captures('W') - before,
+                    game_over: false,
+                });
+            }
+            if node.children.is_empty() {
+                break;
+            }
+            node = &node.children[0];
+        }
+        let end = match self.game_end_state()? {
+            GameEndState::Ongoing => None,
+            GameEndState::TwoPasses { at_move } |
+            GameEndState::Resignation { at_move } |
+            GameEndState::OtherResult { at_move } => Some(at_move),
+            GameEndState::TimeLoss => Some(rows.len()),
+        };
+        if let Some(n) = end {
+            if n > 0 {
+                rows[n - 1].game_over = true;
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Returns the moves of the main line in a compact notation for articles and databases, like
+    /// "1. B Q16 W D4 2. B Q4 pass".
+    ///
+    /// * Each odd-numbered move is preceded by its full-move number, "1.", "2." and so on,
+    ///   so a pair of moves usually follows a number.
+    /// * A move is its color, 'B' or 'W', a space and the point as a GTP vertex("D4", columns skipping 'I',
+    ///   rows from the bottom), or "pass". On boards which GTP doesn't support, points are SGF points like "pd".
+    /// * If annotate_captures, a move capturing stones is followed by 'x' and the number of them, like "D4x2".
+    /// * Tokens are separated by single spaces. The notation is empty for a game without moves.
+    ///
+    /// Returns the errors of `to_move_table`.
+    pub fn to_move_notation(&self, size: (u8, u8), annotate_captures: bool) -> Result<String, SgfError> {
+        let spec = BoardSpec::new(size.0, size.1)?;
+        let mut tokens = Vec::new();
+        for row in self.to_move_table(size)? {
+            if row.move_number % 2 == 1 {
+                tokens.push(format!("{}.", row.move_number / 2 + 1));
+            }
+            let point = match row.point {
+                Some(p) => spec.gtp_vertex(p).unwrap_or_else(|_| coord_to_point(p)),
+                None => "pass".to_string(),
+            };
+            let captures = if annotate_captures && row.captured > 0 { format!("x{}", row.captured) } else { String::new() };
+            tokens.push(format!("{} {}{}", row.color, point, captures));
+        }
+        Ok(tokens.join(" "))
+    }
+}
+
+impl SgfNode {
+    /// Returns a clone in which each move node of the main line labels its own point with its move number by LB.
+    /// Move numbers are those of `main_line_numbered`.
+    /// Passes are not labeled. A label which the node already has at the point is replaced.
+    /// Each node has only the label of its own move, so a move at a point where an earlier move was captured
+    /// doesn't conflict with the earlier label.
+    /// Returns UnsupportedBoard if Reconstruction doesn't support size.
+    pub fn with_move_number_labels(&self, size: (u8, u8)) -> Result<SgfNode, SgfError> {
+        let board = Board::new(size)?;
+        let labels = self.main_line_numbered().map(|(n, node)| {
+            ['B', 'W'].iter()
+                .filter_map(|&c| node.get_point(&c.to_string()).ok())
+                .next()
+                .and_then(|v| board.move_coord(&v))
+                .map(|p| (coord_to_point(p), n))
+        }).collect::<Vec<_>>();
+        let mut result = self.clone();
+        {
+            let mut node = &mut result;
+            for label in labels {
+                if let Some((point, n)) = label {
+                    let mut values = node.get_property("LB").cloned().unwrap_or_default();
+                    values.retain(|v| !v.starts_with(&format!("{}:", point)));
+                    values.push(format!("{}:{}", point, n).into());
+                    node.set_property("LB", values);
+                }
+                if node.children.is_empty() {
+                    break;
+                }
+                node = &mut { node }.children[0];
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl SgfNode {
+    /// Returns the subtree at path of child indices from the node as a standalone game.
+    ///
+    /// The new root has FF[4], GM[1] and SZ of the node.
+    /// If include_position is true, it also has AB and AW of the position just before the node at path
+    /// and PL of the color of the move at path if any.
+    /// The subtree is the only child of the new root.
+    /// Returns InvalidPath if path doesn't lead to a node, and the error of `board_spec`
+    /// or UnsupportedBoard for Reconstruction if SZ is not supported.
+    pub fn subtree_as_game(&self, path: &[usize], include_position: bool) -> Result<SgfNode, SgfError> {
+        let size = self.board_size(BoardFeature::Reconstruction)?;
+        let mut board = Board::new(size)?;
+        let mut node = self;
+        for &i in path {
+            board.apply_node(node);
+            node = node.children.get(i).ok_or_else(|| SgfError::InvalidPath(path.to_vec()))?;
+        }
+        let mut root = SgfNode::new(HashMap::new());
+        root.set_number("FF", 4);
+        root.set_number("GM", 1);
+        if size.0 == size.1 {
+            root.set_number("SZ", size.0 as SgfNumber);
+        } else {
+            root.set_number_number("SZ", (size.0 as SgfNumber, size.1 as SgfNumber));
+        }
+        if include_position {
+            for &(id, color) in [("AB", 'B'), ("AW", 'W')].iter() {
+                let mut points = Vec::new();
+                for y in 0..size.1 {
+                    for x in 0..size.0 {
+                        if board.get((x, y)) == Some(color) {
+                            points.push(coord_to_point((x, y)));
+                        }
+                    }
+                }
+                if !points.is_empty() {
+                    root.set_points(id, points);
+                }
+            }
+            if let Some(&color) = ['B', 'W'].iter().find(|c| node.get_property(&c.to_string()).is_ok()) {
+                root.set_color("PL", color);
+            }
+        }
+        root.children.push(node.clone());
+        Ok(root)
+    }
+}
+
+/// Tunable thresholds of `phase_boundaries_with`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseThresholds {
+    /// An empty point is settled when it has a stone within this Manhattan distance
+    /// and all stones within the distance have the same color. 2 by default.
+    pub settled_distance: u8,
+    /// The endgame begins when at least this ratio of the points are stones or settled empty points.
+    /// 0.7 by default.
+    pub settled_ratio: SgfReal,
+}
+
+impl Default for PhaseThresholds {
+    fn default() -> Self {
+        PhaseThresholds { settled_distance: 2, settled_ratio: 0.7 }
+    }
+}
+
+impl SgfNode {
+    /// Returns the move numbers where the opening ends and where the endgame begins in the main line
+    /// with the default thresholds. See `phase_boundaries_with`.
+    pub fn phase_boundaries(&self, size: (u8, u8)) -> Result<(usize, usize), SgfError> {
+        self.phase_boundaries_with(size, &PhaseThresholds::default())
+    }
+
+    /// Returns the move numbers where the opening ends and where the endgame begins in the main line,
+    /// estimated heuristically.
+    ///
+    /// The board is divided into 3x3 regions. The opening ends at the first move after which
+    /// all 8 regions of the corners and the sides have stones.
+    /// The endgame begins at the first move, not before the end of the opening,
+    /// after which the ratio of settled points reaches `thresholds.settled_ratio`.
+    /// Either boundary is the number of moves if it is not reached.
+    /// Returns UnsupportedBoard if Reconstruction doesn't support size.
+    pub fn phase_boundaries_with(&self, size: (u8, u8), thresholds: &PhaseThresholds) -> Result<(usize, usize), SgfError> {
+        let mut board = Board::new(size)?;
+        let mut moves = 0;
+        let mut opening_end = None;
+        let mut endgame_start = None;
+        let mut node = self;
+        loop {
+            if board.apply_node(node).is_some() {
+                moves += 1;
+                if opening_end.is_none() && outer_regions_occupied(&board) {
+                    opening_end = Some(moves);
+                }
+                if opening_end.is_some() && endgame_start.is_none() &&
+                    settled_ratio(&board, thresholds.settled_distance) >= thresholds.settled_ratio {
+                    endgame_start = Some(moves);
+                }
+            }
+            if node.children.is_empty() {
+                return Ok((opening_end.unwrap_or(moves), endgame_start.unwrap_or(moves)));
+            }
+            node = &node.children[0];
+        }
+    }
+}
+
+/// Returns true if all regions of 3x3 except the center have stones.
+fn outer_regions_occupied(board: &Board) -> bool {
+    let (width, height) = board.size();
+    let mut occupied = [[false; 3]; 3];
+    for y in 0..height {
+        for x in 0..width {
+            if board.get((x, y)).is_some() {
+                occupied[y as usize * 3 / height as usize][x as usize * 3 / width as usize] = true;
+            }
+        }
+    }
+    (0..3).all(|i| (0..3).all(|j| (i == 1 && j == 1) || occupied[i][j]))
+}
+
+/// Returns the ratio of stones and settled empty points to all points.
+fn settled_ratio(board: &Board, distance: u8) -> SgfReal {
+    let (width, height) = board.size();
+    let d = distance as i32;
+    let mut settled = 0;
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            if board.get((x as u8, y as u8)).is_some() {
+                settled += 1;
+                continue;
+            }
+            let mut colors = (-d..=d).flat_map(|dy| (-d..=d).map(move |dx| (x + dx, y + dy)))
+                .filter(|&(nx, ny)| (nx - x).abs() + (ny - y).abs() <= d &&
+                        nx >= 0 && ny >= 0 && nx < width as i32 && ny < height as i32)
+                .filter_map(|(nx, ny)| board.get((nx as u8, ny as u8)));
+            if let Some(first) = colors.next() {
+                if colors.all(|c| c == first) {
+                    settled += 1;
+                }
+            }
+        }
+    }
+    settled as SgfReal / (width as usize * height as usize) as SgfReal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_play_capture() {
+        let mut board = Board::new((9, 9)).unwrap();
+        board.play('B', Some((1, 0)));
+        board.play('W', Some((0, 0)));
+        assert_eq!(board.play('B', Some((0, 1))), vec![(0, 0)]);
+        assert_eq!(board.get((0, 0)), None);
+        assert_eq!(board.captures('B'), 1);
+    }
+
+    #[test]
+    fn test_play_suicide() {
+        let mut board = Board::new((9, 9)).unwrap();
+        board.set((1, 0), Some('B'));
+        board.set((0, 1), Some('B'));
+        assert_eq!(board.play('W', Some((0, 0))), vec![(0, 0)]);
+        assert_eq!(board.captures('B'), 1);
+    }
+
+    #[test]
+    fn test_capture_timeline() {
+        // Black captures a1 corner stone, then White captures a black stone at the other corner.
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9];B[ba];W[aa];B[ab];W[hi];B[ii];W[ih];B[cc])").unwrap()[0];
+        assert_eq!(node.capture_timeline((9, 9)).unwrap(), vec![0, 0, 1, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_game_end_state_two_passes() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9]RE[B+0.5];B[aa];W[bb];B[];W[];B[cc])").unwrap()[0];
+        assert_eq!(node.game_end_state().unwrap(), GameEndState::TwoPasses { at_move: 4 });
+        let table = node.to_move_table((9, 9)).unwrap();
+        assert_eq!(table.len(), 5);
+        assert!(table[3].game_over);
+        assert_eq!(table.iter().filter(|r| r.game_over).count(), 1);
+    }
+
+    #[test]
+    fn test_game_end_state_resignation() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9]RE[W+R];B[aa];W[bb];B[cc])").unwrap()[0];
+        assert_eq!(node.game_end_state().unwrap(), GameEndState::Resignation { at_move: 3 });
+        assert!(node.to_move_table((9, 9)).unwrap()[2].game_over);
+    }
+
+    #[test]
+    fn test_game_end_state_ongoing() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9];B[aa];W[];B[cc])").unwrap()[0];
+        assert_eq!(node.game_end_state().unwrap(), GameEndState::Ongoing);
+        assert!(node.to_move_table((9, 9)).unwrap().iter().all(|r| !r.game_over));
+    }
+
+    #[test]
+    fn test_with_move_number_labels() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9];B[aa];W[bb]LB[bb:A][cc:B];B[tt];W[aa](;B[cc])(;B[dd]))").unwrap()[0];
+        let labeled = node.with_move_number_labels((9, 9)).unwrap();
+        let lb = |n: &SgfNode| n.get_points("LB").ok();
+        assert_eq!(lb(&labeled), None);
+        let first = &labeled.children[0];
+        assert_eq!(lb(first), Some(vec!["aa:1".to_string()]));
+        assert_eq!(lb(&first.children[0]), Some(vec!["cc:B".to_string(), "bb:2".to_string()]));
+        assert_eq!(lb(&first.children[0].children[0]), None);
+        let fourth = &first.children[0].children[0].children[0];
+        assert_eq!(lb(fourth), Some(vec!["aa:4".to_string()]));
+        assert_eq!(lb(&fourth.children[0]), Some(vec!["cc:5".to_string()]));
+        assert_eq!(lb(&fourth.children[1]), None);
+        assert!(node.children[0].get_property("LB").is_err());
+    }
+
+    #[test]
+    fn test_subtree_as_game() {
+        let c = SgfCollection::from_sgf("(;FF[4]SZ[9]AB[cc];B[ba];W[aa];B[ab](;W[ee];B[ff])(;W[gg]C[variation];B[hh]))").unwrap();
+        let path = [0, 0, 0, 1];
+        let game = c[0].subtree_as_game(&path, true).unwrap();
+        let exported = SgfCollection::from_sgf(&SgfCollection::new(vec![game]).to_string()).unwrap();
+        let root = &exported[0];
+        assert_eq!(root.get_color("PL").unwrap(), 'W');
+        assert_eq!(root.children[0].get_text("C").unwrap(), "variation");
+        let mut original = Board::new((9, 9)).unwrap();
+        let mut node = &c[0];
+        for &i in path.iter() {
+            original.apply_node(node);
+            node = &node.children[i];
+        }
+        let mut board = Board::new((9, 9)).unwrap();
+        board.apply_node(root);
+        for y in 0..9 {
+            for x in 0..9 {
+                assert_eq!(board.get((x, y)), original.get((x, y)));
+            }
+        }
+        assert_eq!(board.get((0, 0)), None);
+        assert_eq!(board.get((2, 2)), Some('B'));
+        let minimal = c[0].subtree_as_game(&path, false).unwrap();
+        assert!(minimal.get_property("AB").is_err());
+        assert_eq!(minimal.get_number("SZ").unwrap(), 9);
+        match c[0].subtree_as_game(&[0, 3], true) {
+            Err(SgfError::InvalidPath(ref p)) if p == &[0, 3] => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_board_spec() {
+        let supports = |cols, rows, feature| BoardSpec::new(cols, rows).unwrap().supports(feature).is_ok();
+        for &(cols, rows) in [(31, 31), (9, 13)].iter() {
+            assert!(supports(cols, rows, BoardFeature::Reconstruction));
+            assert!(supports(cols, rows, BoardFeature::Scoring));
+            assert!(!supports(cols, rows, BoardFeature::Handicap));
+            assert!(!supports(cols, rows, BoardFeature::Gtp));
+        }
+        assert!(supports(19, 19, BoardFeature::Handicap));
+        assert!(supports(25, 25, BoardFeature::Gtp));
+        assert!(BoardSpec::new(53, 53).is_err());
+        assert!(BoardSpec::new(0, 9).is_err());
+        match BoardSpec::new(31, 31).unwrap().gtp_vertex((0, 0)) {
+            Err(SgfError::UnsupportedBoard { cols: 31, rows: 31, feature: BoardFeature::Gtp }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        let spec = BoardSpec::new(19, 19).unwrap();
+        assert_eq!(spec.gtp_vertex((3, 15)).unwrap(), "D4");
+        assert_eq!(spec.gtp_vertex((8, 0)).unwrap(), "J19");
+        assert!(spec.gtp_vertex((19, 0)).is_err());
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9:13])").unwrap()[0];
+        assert_eq!(node.board_spec().unwrap().size(), (9, 13));
+    }
+
+    #[test]
+    fn test_unsupported_board_size() {
+        let unsupported = |e: Result<_, SgfError>, feature| match e {
+            Err(SgfError::UnsupportedBoard { cols: 60, rows: 60, feature: f }) => f == feature,
+            _ => false,
+        };
+        let game = &SgfCollection::from_sgf("(;FF[4]SZ[60];B[aa];W[bb])").unwrap()[0];
+        assert!(unsupported(game.board_spec().map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(game.move_sequence().map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(game.game_end_state().map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(game.subtree_as_game(&[0], true).map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(game.infer_board_size().map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(Board::new((60, 60)).map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(game.capture_timeline((60, 60)).map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(game.to_move_table((60, 60)).map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(game.with_move_number_labels((60, 60)).map(|_| ()), BoardFeature::Coordinates));
+        assert!(unsupported(game.phase_boundaries((60, 60)).map(|_| ()), BoardFeature::Coordinates));
+        // a size not fitting in u8 is not truncated
+        for sz in ["275", "-1", "19:300"].iter() {
+            let game = &SgfCollection::from_sgf(&format!("(;FF[4]SZ[{}];B[aa])", sz)).unwrap()[0];
+            assert!(matches!(game.board_spec(), Err(SgfError::OutOfRange(_))));
+            assert!(matches!(game.move_sequence(), Err(SgfError::OutOfRange(_))));
+        }
+        let game = &SgfCollection::from_sgf("(;FF[4]SZ[x];B[aa])").unwrap()[0];
+        assert!(matches!(game.board_spec(), Err(SgfError::ParseError { .. })));
+        // GTP is limited to 25x25 but notation falls back to SGF points
+        let game = &SgfCollection::from_sgf("(;FF[4]SZ[52];B[ZZ])").unwrap()[0];
+        assert_eq!(game.to_move_notation((52, 52), false).unwrap(), "1. B ZZ");
+    }
+
+    #[test]
+    fn test_set_handicap() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]SZ[19])").unwrap();
+        c[0].set_handicap(5, 19).unwrap();
+        assert_eq!(c[0].get_number("HA").unwrap(), 5);
+        assert_eq!(c[0].get_color("PL").unwrap(), 'W');
+        assert_eq!(c[0].get_points("AB").unwrap(), vec!["dp", "pd", "dd", "pp", "jj"]);
+        c[0].set_handicap(9, 9).unwrap();
+        assert_eq!(c[0].get_points("AB").unwrap(), vec!["cg", "gc", "cc", "gg", "ce", "ge", "eg", "ec", "ee"]);
+        assert!(c[0].set_handicap(6, 7).is_err());
+        assert!(c[0].set_handicap(1, 19).is_err());
+        assert!(c[0].set_handicap(2, 31).is_err());
+        assert_eq!(c[0].get_number("HA").unwrap(), 9);
+        assert_eq!(standard_handicap_points(6, 19).unwrap(), vec![(3, 15), (15, 3), (3, 3), (15, 15), (3, 9), (15, 9)]);
+        assert_eq!(standard_handicap_points(4, 10).unwrap().len(), 4);
+        assert!(standard_handicap_points(5, 10).is_none());
+    }
+
+    #[test]
+    fn test_apply_node_add_empty() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9]AB[aa:cc]AW[dd];AE[aa:bb][dd]AW[ab])").unwrap()[0];
+        let board = node.final_position((9, 9)).unwrap();
+        assert_eq!(board.get((0, 0)), None);
+        assert_eq!(board.get((1, 1)), None);
+        assert_eq!(board.get((0, 1)), Some('W'));
+        assert_eq!(board.get((2, 2)), Some('B'));
+        assert_eq!(board.get((3, 3)), None);
+    }
+
+    #[test]
+    fn test_to_move_notation() {
+        let game = &SgfCollection::from_sgf("(;FF[4]SZ[19];B[pd];W[dp];B[ba];W[aa];B[ab];W[];B[tt])").unwrap()[0];
+        assert_eq!(game.to_move_notation((19, 19), false).unwrap(), "1. B Q16 W D4 2. B B19 W A19 3. B A18 W pass 4. B pass");
+        assert_eq!(game.to_move_notation((19, 19), true).unwrap(), "1. B Q16 W D4 2. B B19 W A19 3. B A18x1 W pass 4. B pass");
+        let large = &SgfCollection::from_sgf("(;FF[4]SZ[30];B[CD])").unwrap()[0];
+        assert_eq!(large.to_move_notation((30, 30), false).unwrap(), "1. B CD");
+        assert_eq!(SgfCollection::from_sgf("(;FF[4])").unwrap()[0].to_move_notation((19, 19), true).unwrap(), "");
+    }
+
+    #[test]
+    fn test_move_timing() {
+        let game = &SgfCollection::from_sgf("(;FF[4]TM[600];B[pd]BL[595.5];W[dd]WL[590];B[pp];W[]WL[x];B[dp]BL[580]OB[3])").unwrap()[0];
+        assert_eq!(game.move_timing(), vec![
+            ('B', Some(595.5)), ('W', Some(590.0)), ('B', None), ('W', None), ('B', Some(580.0))
+        ]);
+        assert!(SgfCollection::from_sgf("(;FF[4]BL[600])").unwrap()[0].move_timing().is_empty());
+    }
+
+    #[test]
+    fn test_move_sequence_passes() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[19];B[aa];W[];B[tt];W[ss])").unwrap()[0];
+        assert_eq!(node.move_sequence().unwrap(), vec![('B', Some((0, 0))), ('W', None), ('B', None), ('W', Some((18, 18)))]);
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9];B[aa];W[tt];B[ii])").unwrap()[0];
+        assert_eq!(node.move_sequence().unwrap(), vec![('B', Some((0, 0))), ('W', None), ('B', Some((8, 8)))]);
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9];B[aa];W[jj])").unwrap()[0];
+        assert!(node.move_sequence().is_err());
+    }
+
+    #[test]
+    fn test_infer_board_size() {
+        let size = |sgf: &str| SgfCollection::from_sgf(sgf).unwrap()[0].infer_board_size().unwrap();
+        assert_eq!(size("(;FF[4]AB[cc];B[ee];W[tt](;B[hh])(;B[gi]))"), (9, 9));
+        assert_eq!(size("(;FF[4];B[dd];W[jj])"), (13, 13));
+        assert_eq!(size("(;FF[4];B[pd];W[dp])"), (19, 19));
+        assert_eq!(size("(;FF[4]AW[aa:tt])"), (25, 25));
+        assert_eq!(size("(;FF[4];B[Aa])"), (27, 27));
+        assert_eq!(size("(;FF[4]C[empty])"), (19, 19));
+        assert_eq!(size("(;FF[4]SZ[7:5];B[aa])"), (7, 5));
+    }
+
+    #[test]
+    fn test_touched_points() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9]AB[aa:ab]AW[zz];B[cc];W[tt];B[aa]AE[cc];W[](;B[dd])(;B[ee]))").unwrap()[0];
+        let points = node.touched_points((9, 9)).unwrap();
+        let mut points = points.into_iter().collect::<Vec<_>>();
+        points.sort();
+        assert_eq!(points, vec![(0, 0), (0, 1), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn test_phase_boundaries() {
+        let game = &SgfCollection::from_sgf("(;SZ[9];B[aa];W[ea];B[ia];W[ae];B[ee];W[ie];B[ai];W[ei];B[ii];W[dd])").unwrap()[0];
+        // 49 of 81 points are settled after the 9th move, fewer than 70%, and the endgame is never reached
+        assert_eq!(game.phase_boundaries((9, 9)).unwrap(), (9, 10));
+        let exact = PhaseThresholds { settled_ratio: 49.0 / 81.0, ..Default::default() };
+        assert_eq!(game.phase_boundaries_with((9, 9), &exact).unwrap(), (9, 9));
+        let eager = PhaseThresholds { settled_ratio: 0.0, ..Default::default() };
+        assert_eq!(game.phase_boundaries_with((9, 9), &eager).unwrap(), (9, 9));
+        let strict = PhaseThresholds { settled_ratio: 1.0, ..Default::default() };
+        assert_eq!(game.phase_boundaries_with((9, 9), &strict).unwrap(), (9, 10));
+        let short = &SgfCollection::from_sgf("(;SZ[9];B[ee];W[cc])").unwrap()[0];
+        assert_eq!(short.phase_boundaries((9, 9)).unwrap(), (2, 2));
+    }
+
+    #[test]
+    fn test_board_at() {
+        let game = &SgfCollection::from_sgf("(;SZ[9]AB[aa];AW[bb];B[cc];AE[aa];W[dd])").unwrap()[0];
+        let board = game.board_at((9, 9), 0).unwrap().unwrap();
+        assert_eq!((board.get((0, 0)), board.get((1, 1)), board.get((2, 2))), (Some('B'), Some('W'), None));
+        let board = game.board_at((9, 9), 1).unwrap().unwrap();
+        assert_eq!((board.get((0, 0)), board.get((2, 2)), board.get((3, 3))), (None, Some('B'), None));
+        assert_eq!(game.board_at((9, 9), 2).unwrap().unwrap().get((3, 3)), Some('W'));
+        assert!(game.board_at((9, 9), 3).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_metadata_only_game() {
+        use validate::{IssueKind, Severity};
+        let game = &SgfCollection::from_sgf("(;FF[4]SZ[9]PB[foo]PW[bar]RE[B+R]AB[ee])").unwrap()[0];
+        assert!(!game.has_moves());
+        assert_eq!(game.main_line().count(), 1);
+        assert_eq!(game.main_line_numbered().map(|(n, _)| n).collect::<Vec<_>>(), vec![0]);
+        assert!(game.move_sequence().unwrap().is_empty());
+        assert_eq!(game.board_at((9, 9), 0).unwrap().unwrap().get((4, 4)), Some('B'));
+        assert!(game.board_at((9, 9), 1).unwrap().is_none());
+        assert!(game.to_move_table((9, 9)).unwrap().is_empty());
+        assert!(game.capture_timeline((9, 9)).unwrap().is_empty());
+        assert_eq!(game.game_end_state().unwrap(), GameEndState::Resignation { at_move: 0 });
+        assert_eq!(game.phase_boundaries((9, 9)).unwrap(), (0, 0));
+        assert_eq!(game.game_info().black_player, Some("foo".to_string()));
+        let issues = game.validate();
+        assert_eq!(issues.len(), 1);
+        assert_eq!((issues[0].kind, issues[0].severity), (IssueKind::NoMoves, Severity::Info));
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Conversion between SGF and legacy charsets
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use encoding::{DecoderTrap, EncoderTrap, Encoding};
+use encoding::all::ISO_8859_1;
+use encoding::label::encoding_from_whatwg_label;
+use sgf_node::*;
+
+impl SgfCollection {
+    /// Parses SGF bytes in the charset declared by CA, e.g. "SJIS", "GB2312" or "ISO-8859-1".
+    ///
+    /// CA in the root of the first game is read before decoding, which is safe since the structure characters
+    /// of SGF are ASCII in the supported charsets. It is applied to the whole collection.
+    /// CA in comments, other nodes and later games is not looked at.
+    /// Without CA, the bytes are read as UTF-8 if they are valid, otherwise as ISO-8859-1, the default of FF[4].
+    /// Bytes invalid in the charset are decoded as U+FFFD.
+    /// CA of every root is set to "UTF-8" since the collection is no longer in the original charset.
+    ///
+    /// Returns `SgfError::UnknownEncoding` if CA is not a known charset label
+    /// and `SgfError::InvalidSgf` if the decoded string is not SGF.
+    pub fn from_bytes(bytes: &[u8]) -> Result<SgfCollection, SgfError> {
+        let sgf = match find_charset(bytes) {
+            Some(label) => {
+                let charset = encoding_from_whatwg_label(&label).ok_or(SgfError::UnknownEncoding(label))?;
+                charset.decode(bytes, DecoderTrap::Replace).expect("replacement never fails")
+            },
+            None => match String::from_utf8(bytes.to_vec()) {
+                Ok(s) => s,
+                Err(_) => ISO_8859_1.decode(bytes, DecoderTrap::Replace).expect("replacement never fails"),
+            },
+        };
+        let mut c = SgfCollection::from_sgf(&sgf).map_err(|e| SgfError::InvalidSgf { line: e.line, column: e.column })?;
+        for game in c.iter_mut() {
+            game.set_simple_text("CA", "UTF-8".to_string());
+        }
+        Ok(c)
+    }
+
+    /// Serializes the collection in the charset `encoding`, e.g. "EUC-KR" or "Shift_JIS".
+    /// CA of every root is set to `encoding` before transcoding.
+    ///
+    /// Returns `SgfError::UnknownEncoding` if `encoding` is not a known charset label,
+    /// `SgfError::InvalidIdentifier` if a property identifier can't be written
+    /// and `SgfError::Unencodable` with the first character which the charset can't represent
+    /// and the path to the node containing it.
+    /// The first element of the path is the index of the game, the rest are child indices from its root.
+    pub fn to_bytes(&self, encoding: &str) -> Result<Vec<u8>, SgfError> {
+        let charset = encoding_from_whatwg_label(encoding).ok_or_else(|| SgfError::UnknownEncoding(encoding.to_string()))?;
+        let mut c = self.clone();
+        for game in c.iter_mut() {
+            game.set_simple_text("CA", encoding.to_string());
+        }
+        let sgf = c.to_sgf()?;
+        charset.encode(&sgf, EncoderTrap::Strict).map_err(|_| {
+            for (i, game) in c.iter().enumerate() {
+                if let Some(e) = find_unencodable(game, &mut vec![i], &|c: char| {
+                    charset.encode(&c.to_string(), EncoderTrap::Strict).is_err()
+                }) {
+                    return e;
+                }
+            }
+            SgfError::UnknownEncoding(encoding.to_string()) // unreachable unless the charset fails on structure characters
+        })
+    }
+
+    /// Writes the collection to the file at `path` in the charset `encoding`.
+    /// See `to_bytes` for details.
+    pub fn to_file_with_encoding<P: AsRef<Path>>(&self, path: P, encoding: &str) -> Result<(), SgfError> {
+        let bytes = self.to_bytes(encoding)?;
+        File::create(path).and_then(|mut f| f.write_all(&bytes)).map_err(SgfError::Io)
+    }
+}
+
+impl SgfCollection {
+    /// Returns CA of each game, None for a game without it.
+    pub fn declared_charsets(&self) -> Vec<Option<String>> {
+        self.iter().map(|game| game.get_simple_text("CA").ok().map(|ca| ca.trim().to_string())).collect()
+    }
+
+    /// Returns indices of games whose CA names a charset other than `actual`, the charset
+    /// the string was actually decoded from, so that callers can re-decode them.
+    /// Labels are compared by the charsets they name, so "SJIS" and "Shift_JIS" agree.
+    /// A CA which is not a known charset label is a mismatch and games without CA are never mismatches.
+    ///
+    /// Returns `SgfError::UnknownEncoding` if `actual` is not a known charset label.
+    pub fn charset_mismatches(&self, actual: &str) -> Result<Vec<usize>, SgfError> {
+        let actual = encoding_from_whatwg_label(actual).ok_or_else(|| SgfError::UnknownEncoding(actual.to_string()))?;
+        Ok(self.declared_charsets().into_iter().enumerate().filter(|(_, ca)| match *ca {
+            Some(ref label) => encoding_from_whatwg_label(label).map_or(true, |declared| declared.name() != actual.name()),
+            None => false,
+        }).map(|(i, _)| i).collect())
+    }
+
+    /// Sets CA of every game to `charset`, typically "UTF-8" after the collection is converted.
+    pub fn set_charset_all(&mut self, charset: &str) -> &mut Self {
+        for game in self.iter_mut() {
+            game.set_simple_text("CA", charset.to_string());
+        }
+        self
+    }
+}
+
+/// Returns the value of CA in the root of the first game in bytes,
+/// or None if the root has no CA or the bytes don't start with a game tree.
+/// A '\\' after a non-ASCII byte is taken as the second byte of a character, such as "ソ" in Shift_JIS,
+/// rather than an escape.
+fn find_charset(bytes: &[u8]) -> Option<String> {
+    let skip_whitespaces = |i: usize| i + bytes[i..].iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let mut i = skip_whitespaces(0);
+    if bytes.get(i) != Some(&b'(') {
+        return None;
+    }
+    i = skip_whitespaces(i + 1);
+    if bytes.get(i) != Some(&b';') {
+        return None;
+    }
+    i = skip_whitespaces(i + 1);
+    loop {
+        let len = bytes[i..].iter().take_while(|b| b.is_ascii_uppercase()).count();
+        if len == 0 {
+            return None;
+        }
+        let id = &bytes[i..i + len];
+        i = skip_whitespaces(i + len);
+        let mut first = None;
+        while bytes.get(i) == Some(&b'[') {
+            let start = i + 1;
+            i = start;
+            while *bytes.get(i)? != b']' {
+                i += if bytes[i] == b'\\' && bytes[i - 1].is_ascii() { 2 } else { 1 };
+            }
+            first = first.or(Some(&bytes[start..i]));
+            i = skip_whitespaces(i + 1);
+        }
+        let value = first?;
+        if id == b"CA" {
+            return Some(String::from_utf8_lossy(value).trim().to_string());
+        }
+    }
+}
+
+fn find_unencodable<F: Fn(char) -> bool>(node: &SgfNode, path: &mut Vec<usize>, unencodable: &F) -> Option<SgfError> {
+    for (id, values) in node.iter_values() {
+        for c in id.chars().chain(values.iter().flat_map(|v| v.chars())) {
+            if unencodable(c) {
+                return Some(SgfError::Unencodable { character: c, path: path.clone() });
+            }
+        }
+    }
+    for (i, child) in node.children.iter().enumerate() {
+        path.push(i);
+        let result = find_unencodable(child, path, unencodable);
+        path.pop();
+        if result.is_some() {
+            return result;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charset_mismatches() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]CA[UTF-8])(;FF[4]CA[SJIS])(;FF[4])(;FF[4]CA[utf8])(;FF[4]CA[unknown])").unwrap();
+        assert_eq!(c.declared_charsets(), vec![
+            Some("UTF-8".to_string()), Some("SJIS".to_string()), None, Some("utf8".to_string()), Some("unknown".to_string())
+        ]);
+        assert_eq!(c.charset_mismatches("UTF-8").unwrap(), vec![1, 4]);
+        assert_eq!(c.charset_mismatches("Shift_JIS").unwrap(), vec![0, 3, 4]);
+        assert!(c.charset_mismatches("unknown").is_err());
+        c.set_charset_all("UTF-8");
+        assert!(c.declared_charsets().iter().all(|ca| ca.as_ref().map(|s| &s[..]) == Some("UTF-8")));
+        assert!(c.charset_mismatches("UTF-8").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_to_bytes_euc_kr() {
+        let c = SgfCollection::from_sgf("(;FF[4]CA[UTF-8]PB[이창호];C[좋은 수])").unwrap();
+        let bytes = c.to_bytes("EUC-KR").unwrap();
+        let decoded = encoding_from_whatwg_label("EUC-KR").unwrap().decode(&bytes, DecoderTrap::Strict).unwrap();
+        let c = SgfCollection::from_sgf(&decoded).unwrap();
+        assert_eq!(c[0].get_simple_text("CA").unwrap(), "EUC-KR");
+        assert_eq!(c[0].get_simple_text("PB").unwrap(), "이창호");
+        assert_eq!(c[0].children[0].get_text("C").unwrap(), "좋은 수");
+    }
+
+    #[test]
+    fn test_to_bytes_unencodable() {
+        let c = SgfCollection::from_sgf("(;FF[4])(;FF[4];B[aa];C[nice \u{1F44D}])").unwrap();
+        match c.to_bytes("EUC-KR") {
+            Err(SgfError::Unencodable { character, path }) => {
+                assert_eq!(character, '\u{1F44D}');
+                assert_eq!(path, vec![1, 0, 0]);
+            },
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_to_bytes_invalid_identifier() {
+        let mut c = SgfCollection::from_sgf("(;FF[4];B[aa])").unwrap();
+        c[0].children[0].set_point("b", "bb".to_string());
+        match c.to_bytes("UTF-8") {
+            Err(SgfError::InvalidIdentifier(ref id)) if id == "b" => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_to_bytes_unknown_encoding() {
+        let c = SgfCollection::from_sgf("(;FF[4])").unwrap();
+        match c.to_bytes("NO-SUCH-CHARSET") {
+            Err(SgfError::UnknownEncoding(ref e)) if e == "NO-SUCH-CHARSET" => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_shift_jis() {
+        // PB[本因坊秀策]PW[井山裕太]C[ソ], whose last byte of "ソ" is '\\'
+        let bytes = b"(;FF[4]CA[SJIS]PB[\x96\x7b\x88\xf6\x96\x56\x8f\x47\x8d\xf4]PW[\x88\xe4\x8e\x52\x97\x54\x91\xbe];B[pd]C[\x83\x5c])";
+        let c = SgfCollection::from_bytes(bytes).unwrap();
+        assert_eq!(c[0].get_simple_text("PB").unwrap(), "本因坊秀策");
+        assert_eq!(c[0].get_simple_text("PW").unwrap(), "井山裕太");
+        assert_eq!(c[0].children[0].get_text("C").unwrap(), "ソ");
+        assert_eq!(c[0].get_simple_text("CA").unwrap(), "UTF-8");
+    }
+
+    #[test]
+    fn test_from_bytes_without_ca() {
+        let c = SgfCollection::from_bytes("(;FF[4]PB[이창호])".as_bytes()).unwrap();
+        assert_eq!(c[0].get_simple_text("PB").unwrap(), "이창호");
+        let c = SgfCollection::from_bytes(b"(;FF[4]PB[Jos\xe9])").unwrap();
+        assert_eq!(c[0].get_simple_text("PB").unwrap(), "José");
+    }
+
+    #[test]
+    fn test_from_bytes_unknown_encoding() {
+        match SgfCollection::from_bytes(b"(;FF[4]CA[X-UNKNOWN])") {
+            Err(SgfError::UnknownEncoding(ref label)) if label == "X-UNKNOWN" => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        assert!(matches!(SgfCollection::from_bytes(b"(;FF[4]CA[UTF-8]"), Err(SgfError::InvalidSgf { .. })));
+        assert!(matches!(SgfCollection::from_bytes(b"(;FF[4]\n;B[pd](C[x])"), Err(SgfError::InvalidSgf { line: 2, .. })));
+    }
+
+    #[test]
+    fn test_from_bytes_root_ca_only() {
+        assert_eq!(find_charset(b" (\n;FF[4]AB[aa][bb] CA [SJIS];B[pd])"), Some("SJIS".to_string()));
+        // the second byte of "ソ" followed by ']' ends the value
+        assert_eq!(find_charset(b"(;PB[\x83\x5c]CA[SJIS])"), Some("SJIS".to_string()));
+        assert_eq!(find_charset(b"(;C[a\\]CA[SJIS]CA[UTF-8])"), Some("UTF-8".to_string()));
+        for &bytes in [&b"(;FF[4]C[CA[SJIS]];CA[SJIS])"[..], b"(;FF[4];CA[SJIS])", b"(;FF[4])(;CA[SJIS])", b"(;FF[4]CA"].iter() {
+            assert_eq!(find_charset(bytes), None);
+        }
+        let c = SgfCollection::from_bytes("(;FF[4]C[CA[SJIS\\]]PB[이창호])".as_bytes()).unwrap();
+        assert_eq!(c[0].get_simple_text("PB").unwrap(), "이창호");
+    }
+}
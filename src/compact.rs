@@ -0,0 +1,76 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Compact binary representation of trees (feature "compact")
+
+use bincode::{self, Options};
+use sgf_node::*;
+
+/// Leading bytes of the compact format. The last byte is the version of the format.
+const MAGIC: &[u8] = b"SGFC\x01";
+
+#[derive(Serialize, Deserialize)]
+struct CompactNode {
+    properties: Vec<(String, Vec<String>)>,
+    children: Vec<CompactNode>,
+}
+
+impl<'a> From<&'a SgfNode> for CompactNode {
+    fn from(node: &'a SgfNode) -> CompactNode {
+        CompactNode {
+            properties: node.iter_values().map(|(id, values)| (id.clone(), values.iter().map(|v| v.to_string()).collect())).collect(),
+            children: node.children.iter().map(CompactNode::from).collect(),
+        }
+    }
+}
+
+impl From<CompactNode> for SgfNode {
+    fn from(node: CompactNode) -> SgfNode {
+        let mut result = SgfNode::new(node.properties);
+        result.children = node.children.into_iter().map(SgfNode::from).collect();
+        result
+    }
+}
+
+impl SgfNode {
+    /// Serializes the tree into a compact binary form for caching.
+    ///
+    /// The format is bincode with variable length integers of the tree prefixed with a magic number and a format version.
+    /// It is not guaranteed to be stable across versions of this crate except that
+    /// `from_bytes_compact` rejects data of another format version.
+    pub fn to_bytes_compact(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(bincode::DefaultOptions::new().serialize(&CompactNode::from(self)).expect("serialization into memory never fails"));
+        bytes
+    }
+
+    /// Deserializes a tree serialized by `to_bytes_compact`.
+    /// Returns CompactFormat error for data of another format version or broken data.
+    pub fn from_bytes_compact(bytes: &[u8]) -> Result<SgfNode, SgfError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(SgfError::CompactFormat("unknown magic number or format version".to_string()));
+        }
+        bincode::DefaultOptions::new().deserialize::<CompactNode>(&bytes[MAGIC.len()..])
+            .map(SgfNode::from)
+            .map_err(|e| SgfError::CompactFormat(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_round_trip() {
+        let c = SgfCollection::from_sgf("(;FF[4]C[root\\]](;B[aa]AB[bb][cc];W[dd])(;B[ee]C[日本語]))").unwrap();
+        let bytes = c[0].to_bytes_compact();
+        let node = SgfNode::from_bytes_compact(&bytes).unwrap();
+        assert_eq!(node, c[0]);
+        // the order of properties is kept
+        assert_eq!(node.to_string(), c[0].to_string());
+        let mut other_version = bytes.clone();
+        other_version[4] = 0;
+        assert!(SgfNode::from_bytes_compact(&other_version).is_err());
+        assert!(SgfNode::from_bytes_compact(&bytes[..bytes.len() - 3]).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Extraction of SGF embedded in other texts
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use sgf_node::*;
+use parser::*;
+
+/// Finds SGF game trees embedded in a text such as a HTML page or a chat log.
+///
+/// Candidates are balanced `(` ... `)` spans starting with `(;` and containing at least one property value.
+/// Brackets and parentheses inside property values are skipped with escapes taken into account,
+/// and a `[` without its `]` is an ordinary character.
+/// Each candidate is parsed strictly, except that candidates inside a successful one are hidden by it
+/// and candidates containing the position where an enclosing candidate failed are not parsed since they fail there too.
+/// Returns byte ranges in `text` and parse results, longest first.
+/// Returned ranges never overlap each other and a failed candidate never hides a successful one.
+/// It takes time linear in the length of the text.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let found = extract_sgf("review this (;FF[4];B[pd]) please");
+/// assert_eq!(found[0].0, 12..26);
+/// assert!(found[0].1.is_ok());
+/// ```
+pub fn extract_sgf(text: &str) -> Vec<(Range<usize>, ParseResult<SgfCollection>)> {
+    let mut spans = candidate_spans(text);
+    // enclosing candidates first
+    spans.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+    let mut candidates = Vec::new();
+    let mut hidden_until = 0;
+    // failed candidates enclosing the current one with the positions where they failed
+    let mut failed: Vec<(usize, usize)> = Vec::new();
+    for span in spans {
+        if span.start < hidden_until {
+            continue;
+        }
+        while failed.last().map_or(false, |&(end, _)| end <= span.start) {
+            failed.pop();
+        }
+        if failed.last().map_or(false, |&(_, error)| span.start <= error && error < span.end) {
+            continue;
+        }
+        let result = collection(&text[span.clone()]);
+        match result {
+            Ok(_) => hidden_until = span.end,
+            Err(ref e) => failed.push((span.end, span.start + e.offset)),
+        }
+        candidates.push((span, result));
+    }
+    candidates.sort_by(|a, b| {
+        b.1.is_ok().cmp(&a.1.is_ok())
+            .then((b.0.end - b.0.start).cmp(&(a.0.end - a.0.start)))
+            .then(a.0.start.cmp(&b.0.start))
+    });
+    // candidates are nested or disjoint, so one overlaps a selected one if either contains the other
+    let mut selected_ranges = BTreeMap::new();
+    let mut selected: Vec<(Range<usize>, ParseResult<SgfCollection>)> = Vec::new();
+    for (range, result) in candidates {
+        let inside = selected_ranges.range(..=range.start).next_back().map_or(false, |(_, &end)| range.start < end);
+        let outside = selected_ranges.range(range.start..range.end).next().is_some();
+        if !inside && !outside {
+            selected_ranges.insert(range.start, range.end);
+            selected.push((range, result));
+        }
+    }
+    selected.sort_by(|a, b| (b.0.end - b.0.start).cmp(&(a.0.end - a.0.start)).then(a.0.start.cmp(&b.0.start)));
+    selected
+}
+
+/// An open `(` in `candidate_spans`.
+struct OpenParen {
+    begin: usize,
+    /// whether it is followed by `;`
+    candidate: bool,
+    has_value: bool,
+}
+
+/// Returns the spans of the candidates of `extract_sgf` in one pass over text.
+fn candidate_spans(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    // value_end[i] is the position of ']' ending a value whose content starts at i
+    let mut value_end = vec![None; bytes.len() + 1];
+    for i in (0..bytes.len()).rev() {
+        value_end[i] = match bytes[i] {
+            b'\\' => value_end.get(i + 2).cloned().unwrap_or(None),
+            b']' => Some(i),
+            _ => value_end[i + 1],
+        };
+    }
+    let mut spans = Vec::new();
+    let mut stack: Vec<OpenParen> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => {
+                let next = bytes[i + 1..].iter().position(|b| !(*b as char).is_whitespace()).map(|k| i + 1 + k);
+                let candidate = next.map_or(false, |k| bytes[k] == b';');
+                if candidate || !stack.is_empty() {
+                    stack.push(OpenParen { begin: i, candidate, has_value: false });
+                }
+            },
+            b'[' if !stack.is_empty() => if let Some(end) = value_end[i + 1] {
+                stack.last_mut().expect("not empty").has_value = true;
+                i = end;
+            },
+            b')' => if let Some(open) = stack.pop() {
+                if open.candidate && open.has_value {
+                    spans.push(open.begin..i + 1);
+                }
+                if let Some(parent) = stack.last_mut() {
+                    parent.has_value |= open.has_value;
+                }
+            },
+            _ => (),
+        }
+        i += 1;
+    }
+    spans
+}
+
+/// Returns the end position of a balanced game tree candidate beginning at `begin`.
+pub(crate) fn scan_game_tree(text: &str, begin: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = begin + 1;
+    while i < bytes.len() && (bytes[i] as char).is_whitespace() {
+        i += 1;
+    }
+    if i >= bytes.len() || bytes[i] != b';' {
+        return None;
+    }
+    let mut depth = 1;
+    let mut in_value = false;
+    let mut has_value = false;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if in_value {
+            match c {
+                b'\\' => i += 1,
+                b']' => in_value = false,
+                _ => (),
+            }
+        } else {
+            match c {
+                b'[' => {
+                    in_value = true;
+                    has_value = true;
+                },
+                b'(' => depth += 1,
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return if has_value { Some(i + 1) } else { None };
+                    }
+                },
+                _ => (),
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_sgf_from_html() {
+        let sgf = "(;FF[4]C[a (tricky\\] comment)]SZ[9](;B[ee])(;B[cc]))";
+        let html = format!("<html><body><p>Game:</p><pre>{}</pre></body></html>", sgf);
+        let found = extract_sgf(&html);
+        assert_eq!(found.len(), 1);
+        assert_eq!(&html[found[0].0.clone()], sgf);
+        assert_eq!(found[0].1.as_ref().unwrap()[0].children.len(), 2);
+    }
+
+    #[test]
+    fn test_extract_sgf_two_games() {
+        let text = "first (;FF[4];B[aa]) and the longer second (;FF[4];B[bb];W[cc]) :)";
+        let found = extract_sgf(text);
+        assert_eq!(found.len(), 2);
+        assert_eq!(&text[found[0].0.clone()], "(;FF[4];B[bb];W[cc])");
+        assert_eq!(&text[found[1].0.clone()], "(;FF[4];B[aa])");
+        assert!(found.iter().all(|f| f.1.is_ok()));
+    }
+
+    #[test]
+    fn test_extract_sgf_emoticon() {
+        assert!(extract_sgf("nice move (;-) see you").is_empty());
+    }
+
+    #[test]
+    fn test_extract_sgf_nested_and_unclosed() {
+        // an unclosed bracket in the text doesn't swallow the game
+        let text = "see [link (;FF[4];B[aa]) and (;C[unclosed";
+        let found = extract_sgf(text);
+        assert_eq!(found.len(), 1);
+        assert_eq!(&text[found[0].0.clone()], "(;FF[4];B[aa])");
+        // a valid variation of a broken game is found, and the broken game too
+        let text = "(;FF[4]?(;B[aa]))";
+        let found = extract_sgf(text);
+        assert_eq!(found.iter().map(|f| (&text[f.0.clone()], f.1.is_ok())).collect::<Vec<_>>(), vec![("(;B[aa])", true)]);
+        let text = "(;FF[4](;B[aa])?) (;B[bb]";
+        let found = extract_sgf(text);
+        assert_eq!(found.iter().map(|f| (&text[f.0.clone()], f.1.is_ok())).collect::<Vec<_>>(), vec![("(;B[aa])", true)]);
+        let text = "(;FF[4]?)(;FF[4]";
+        let found = extract_sgf(text);
+        assert_eq!(found.iter().map(|f| (&text[f.0.clone()], f.1.is_ok())).collect::<Vec<_>>(), vec![("(;FF[4]?)", false)]);
+    }
+
+    #[test]
+    fn test_extract_sgf_in_linear_time() {
+        let n = 40_000;
+        let start = ::std::time::Instant::now();
+        assert!(extract_sgf(&"(;".repeat(n)).is_empty());
+        assert!(extract_sgf(&"(;C[x]".repeat(n)).is_empty());
+        let found = extract_sgf(&format!("{}{}", "(;C[x]".repeat(n), "(;B[aa])"));
+        assert_eq!(found.len(), 1);
+        assert!(found[0].1.is_ok());
+        assert_eq!(extract_sgf(&"(;C[x]?)".repeat(n)).len(), n);
+        // a quadratic scan takes minutes
+        assert!(start.elapsed().as_secs() < 10);
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Parsed values of RE and DT
+
+use sgf_node::*;
+
+/// How a game was won.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WinBy {
+    /// Score difference like "B+3.5"
+    Score(SgfReal),
+    /// "+R" or "+Resign"
+    Resignation,
+    /// "+T" or "+Time"
+    Time,
+    /// "+F" or "+Forfeit"
+    Forfeit,
+    /// "+" without a reason
+    Unspecified,
+}
+
+/// Value of RE.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    Win { winner: SgfColor, by: WinBy },
+    /// "0" or "Draw"
+    Draw,
+    /// "Void", no result or suspended play
+    Void,
+    /// "?", unknown result
+    Unknown,
+}
+
+impl GameResult {
+    /// Parses a value of RE. Returns None if the value doesn't follow the specification.
+    pub fn parse(re: &str) -> Option<GameResult> {
+        match re {
+            "0" | "Draw" => return Some(GameResult::Draw),
+            "Void" => return Some(GameResult::Void),
+            "?" => return Some(GameResult::Unknown),
+            _ => (),
+        }
+        let winner = match re.chars().next() {
+            Some(c) if c == 'B' || c == 'W' => c,
+            _ => return None,
+        };
+        if !re[1..].starts_with('+') {
+            return None;
+        }
+        let by = match &re[2..] {
+            "" => WinBy::Unspecified,
+            "R" | "Resign" => WinBy::Resignation,
+            "T" | "Time" => WinBy::Time,
+            "F" | "Forfeit" => WinBy::Forfeit,
+            score => WinBy::Score(score.parse().ok()?),
+        };
+        Some(GameResult::Win { winner, by })
+    }
+}
+
+/// A date of DT, which may be partial.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SgfDate {
+    pub year: u16,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+}
+
+impl SgfDate {
+    /// Parses a value of DT into dates.
+    /// The shortcuts of the specification like "1996-05-06,07,08" and "1996-12-27,28,1997-01-03" are expanded.
+    /// Returns None if the value doesn't follow the specification.
+    pub fn parse_dt(dt: &str) -> Option<Vec<SgfDate>> {
+        let mut dates: Vec<SgfDate> = Vec::new();
+        for element in dt.split(',') {
+            let parts = element.trim().split('-').collect::<Vec<_>>();
+            if parts.iter().any(|p| p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit())) {
+                return None;
+            }
+            let previous = dates.last().cloned();
+            let date = match (parts.len(), parts[0].len(), previous) {
+                (_, 4, _) => SgfDate {
+                    year: parts[0].parse().ok()?,
+                    month: if parts.len() > 1 { Some(parse_two_digits(parts[1])?) } else { None },
+                    day: if parts.len() > 2 { Some(parse_two_digits(parts[2])?) } else { None },
+                },
+                (1, _, Some(SgfDate { day: Some(_), .. })) => SgfDate { day: Some(parse_two_digits(parts[0])?), ..previous? },
+                (1, _, Some(SgfDate { month: Some(_), .. })) => SgfDate { month: Some(parse_two_digits(parts[0])?), ..previous? },
+                (2, _, Some(SgfDate { day: Some(_), .. })) => SgfDate {
+                    month: Some(parse_two_digits(parts[0])?),
+                    day: Some(parse_two_digits(parts[1])?),
+                    ..previous?
+                },
+                _ => return None,
+            };
+            if parts.len() > 3 || date.month.map_or(false, |m| !(1..=12).contains(&m)) || date.day.map_or(false, |d| !(1..=31).contains(&d)) {
+                return None;
+            }
+            dates.push(date);
+        }
+        Some(dates)
+    }
+}
+
+fn parse_two_digits(s: &str) -> Option<u8> {
+    if s.len() == 2 { s.parse().ok() } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_result_parse() {
+        assert_eq!(GameResult::parse("B+R"), Some(GameResult::Win { winner: 'B', by: WinBy::Resignation }));
+        assert_eq!(GameResult::parse("W+3.5"), Some(GameResult::Win { winner: 'W', by: WinBy::Score(3.5) }));
+        assert_eq!(GameResult::parse("W+"), Some(GameResult::Win { winner: 'W', by: WinBy::Unspecified }));
+        assert_eq!(GameResult::parse("Draw"), Some(GameResult::Draw));
+        assert_eq!(GameResult::parse("B+X"), None);
+        assert_eq!(GameResult::parse("Jigo"), None);
+    }
+
+    #[test]
+    fn test_sgf_date_parse_dt() {
+        let date = |year, month, day| SgfDate { year, month, day };
+        assert_eq!(SgfDate::parse_dt("1996-05-06,07,08").unwrap(),
+                   vec![date(1996, Some(5), Some(6)), date(1996, Some(5), Some(7)), date(1996, Some(5), Some(8))]);
+        assert_eq!(SgfDate::parse_dt("1996,1997").unwrap(), vec![date(1996, None, None), date(1997, None, None)]);
+        assert_eq!(SgfDate::parse_dt("1996-05,06").unwrap(), vec![date(1996, Some(5), None), date(1996, Some(6), None)]);
+        assert_eq!(SgfDate::parse_dt("1996-05-06,07,08-03,10").unwrap()[3], date(1996, Some(8), Some(10)));
+        assert_eq!(SgfDate::parse_dt("1996-12-27,28,1997-01-03,04").unwrap()[3], date(1997, Some(1), Some(4)));
+        assert_eq!(SgfDate::parse_dt("1996-13-01"), None);
+        assert_eq!(SgfDate::parse_dt("06,07"), None);
+        assert_eq!(SgfDate::parse_dt("May 6, 1996"), None);
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Helpers for displaying decoded text on the web
+
+/// Escapes `&`, `<`, `>`, `"` and `'` of text as HTML character references
+/// so that a decoded value, such as `get_text("C")`, can be put in HTML elements and attribute values.
+///
+/// This is a display helper unrelated to SGF escaping. Pass decoded text, not raw values.
+/// Line breaks are kept as they are, so render the result with `white-space: pre-wrap` or replace them with `<br>`.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let c = SgfCollection::from_sgf("(;C[<b>B+R</b> & \"resign\"])").unwrap();
+/// assert_eq!(escape_for_html(&c[0].get_text("C").unwrap()), "&lt;b&gt;B+R&lt;/b&gt; &amp; &quot;resign&quot;");
+/// ```
+pub fn escape_for_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sgf_node::*;
+
+    #[test]
+    fn test_escape_for_html() {
+        let c = SgfCollection::from_sgf("(;C[a<script>alert('x')</script>\\]\nline])").unwrap();
+        assert_eq!(escape_for_html(&c[0].get_text("C").unwrap()),
+                   "a&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;]\nline");
+        assert_eq!(escape_for_html("&amp;"), "&amp;amp;");
+        assert_eq!(escape_for_html("黒番"), "黒番");
+    }
+}
@@ -0,0 +1,222 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Localized display strings of game results and dates (feature "i18n")
+
+use game_result::*;
+
+/// Language of display strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    English,
+    Japanese,
+    Korean,
+    Chinese,
+}
+
+/// Display strings of a language.
+/// "{color}", "{points}", "{n}", "{year}", "{month}" and "{day}" in templates are replaced.
+struct Table {
+    black: &'static str,
+    white: &'static str,
+    resignation: &'static str,
+    time: &'static str,
+    forfeit: &'static str,
+    score: &'static str,
+    unspecified: &'static str,
+    /// Points with an integer n
+    points: &'static str,
+    /// Points with n and a half
+    half_points: &'static str,
+    /// A half point
+    half_point: &'static str,
+    draw: &'static str,
+    void: &'static str,
+    unknown: &'static str,
+    months: [&'static str; 12],
+    year_month_day: &'static str,
+    year_month: &'static str,
+    year: &'static str,
+}
+
+const ENGLISH: Table = Table {
+    black: "Black",
+    white: "White",
+    resignation: "{color} wins by resignation",
+    time: "{color} wins on time",
+    forfeit: "{color} wins by forfeit",
+    score: "{color} wins by {points}",
+    unspecified: "{color} wins",
+    points: "{n} points",
+    half_points: "{n}.5 points",
+    half_point: "0.5 points",
+    draw: "Draw",
+    void: "No result",
+    unknown: "Unknown",
+    months: ["January", "February", "March", "April", "May", "June",
+             "July", "August", "September", "October", "November", "December"],
+    year_month_day: "{month} {day}, {year}",
+    year_month: "{month} {year}",
+    year: "{year}",
+};
+
+const JAPANESE: Table = Table {
+    black: "黒",
+    white: "白",
+    resignation: "{color}中押し勝ち",
+    time: "{color}時間切れ勝ち",
+    forfeit: "{color}反則勝ち",
+    score: "{color}{points}勝ち",
+    unspecified: "{color}勝ち",
+    points: "{n}目",
+    half_points: "{n}目半",
+    half_point: "半目",
+    draw: "持碁",
+    void: "無勝負",
+    unknown: "不明",
+    months: ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月"],
+    year_month_day: "{year}年{month}{day}日",
+    year_month: "{year}年{month}",
+    year: "{year}年",
+};
+
+const KOREAN: Table = Table {
+    black: "흑",
+    white: "백",
+    resignation: "{color} 불계승",
+    time: "{color} 시간승",
+    forfeit: "{color} 반칙승",
+    score: "{color} {points}승",
+    unspecified: "{color} 승",
+    points: "{n}집",
+    half_points: "{n}집 반",
+    half_point: "반집",
+    draw: "무승부",
+    void: "무효",
+    unknown: "미상",
+    months: ["1월", "2월", "3월", "4월", "5월", "6월", "7월", "8월", "9월", "10월", "11월", "12월"],
+    year_month_day: "{year}년 {month} {day}일",
+    year_month: "{year}년 {month}",
+    year: "{year}년",
+};
+
+const CHINESE: Table = Table {
+    black: "黑",
+    white: "白",
+    resignation: "{color}中盘胜",
+    time: "{color}超时胜",
+    forfeit: "{color}判胜",
+    score: "{color}胜{points}",
+    unspecified: "{color}胜",
+    points: "{n}目",
+    half_points: "{n}目半",
+    half_point: "半目",
+    draw: "和棋",
+    void: "无胜负",
+    unknown: "未知",
+    months: ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月"],
+    year_month_day: "{year}年{month}{day}日",
+    year_month: "{year}年{month}",
+    year: "{year}年",
+};
+
+impl Lang {
+    /// Guesses the language from a value of CA. English is the default.
+    pub fn from_ca_or_default(ca: Option<&str>) -> Lang {
+        match ca.map(|ca| ca.to_ascii_uppercase()).as_ref().map(|ca| &ca[..]) {
+            Some("SHIFT_JIS") | Some("SJIS") | Some("EUC-JP") | Some("ISO-2022-JP") => Lang::Japanese,
+            Some("EUC-KR") | Some("KS_C_5601-1987") | Some("CP949") => Lang::Korean,
+            Some("GB2312") | Some("GBK") | Some("GB18030") | Some("BIG5") => Lang::Chinese,
+            _ => Lang::English,
+        }
+    }
+
+    fn table(self) -> &'static Table {
+        match self {
+            Lang::English => &ENGLISH,
+            Lang::Japanese => &JAPANESE,
+            Lang::Korean => &KOREAN,
+            Lang::Chinese => &CHINESE,
+        }
+    }
+}
+
+impl GameResult {
+    /// Returns a display string of the result in lang.
+    pub fn to_display(&self, lang: Lang) -> String {
+        let table = lang.table();
+        match *self {
+            GameResult::Win { winner, by } => {
+                let template = match by {
+                    WinBy::Score(_) => table.score,
+                    WinBy::Resignation => table.resignation,
+                    WinBy::Time => table.time,
+                    WinBy::Forfeit => table.forfeit,
+                    WinBy::Unspecified => table.unspecified,
+                };
+                let points = match by {
+                    WinBy::Score(0.5) => table.half_point.to_string(),
+                    WinBy::Score(score) if score.fract() == 0.5 => table.half_points.replace("{n}", &score.trunc().to_string()),
+                    WinBy::Score(score) => table.points.replace("{n}", &score.to_string()),
+                    _ => String::new(),
+                };
+                template.replace("{color}", if winner == 'B' { table.black } else { table.white })
+                    .replace("{points}", &points)
+            },
+            GameResult::Draw => table.draw.to_string(),
+            GameResult::Void => table.void.to_string(),
+            GameResult::Unknown => table.unknown.to_string(),
+        }
+    }
+}
+
+impl SgfDate {
+    /// Returns a display string of the date in lang.
+    pub fn to_display(&self, lang: Lang) -> String {
+        let table = lang.table();
+        let template = match (self.month, self.day) {
+            (Some(_), Some(_)) => table.year_month_day,
+            (Some(_), None) => table.year_month,
+            _ => table.year,
+        };
+        let month = self.month.map(|m| table.months[(m as usize).saturating_sub(1) % 12]).unwrap_or("");
+        template.replace("{year}", &self.year.to_string())
+            .replace("{month}", month)
+            .replace("{day}", &self.day.map(|d| d.to_string()).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_result_to_display() {
+        let display = |re, lang| GameResult::parse(re).unwrap().to_display(lang);
+        assert_eq!(display("B+R", Lang::English), "Black wins by resignation");
+        assert_eq!(display("W+3.5", Lang::English), "White wins by 3.5 points");
+        assert_eq!(display("0", Lang::English), "Draw");
+        assert_eq!(display("B+R", Lang::Japanese), "黒中押し勝ち");
+        assert_eq!(display("W+3.5", Lang::Japanese), "白3目半勝ち");
+        assert_eq!(display("B+0.5", Lang::Japanese), "黒半目勝ち");
+        assert_eq!(display("W+T", Lang::Japanese), "白時間切れ勝ち");
+        assert_eq!(display("Draw", Lang::Japanese), "持碁");
+    }
+
+    #[test]
+    fn test_sgf_date_to_display() {
+        let date = SgfDate { year: 1996, month: Some(12), day: None };
+        assert_eq!(date.to_display(Lang::English), "December 1996");
+        assert_eq!(date.to_display(Lang::Japanese), "1996年12月");
+        let date = SgfDate { year: 1996, month: Some(5), day: Some(6) };
+        assert_eq!(date.to_display(Lang::English), "May 6, 1996");
+        assert_eq!(date.to_display(Lang::Japanese), "1996年5月6日");
+    }
+
+    #[test]
+    fn test_lang_from_ca_or_default() {
+        assert_eq!(Lang::from_ca_or_default(Some("Shift_JIS")), Lang::Japanese);
+        assert_eq!(Lang::from_ca_or_default(Some("UTF-8")), Lang::English);
+        assert_eq!(Lang::from_ca_or_default(None), Lang::English);
+    }
+}
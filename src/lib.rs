@@ -4,8 +4,72 @@
 //! SGF(Smart Game Format) parser
 
 extern crate regex;
+extern crate encoding;
+#[cfg(feature = "compact")]
+extern crate bincode;
+#[cfg(feature = "compact")]
+#[macro_use]
+extern crate serde_derive;
 
 pub mod sgf_node;
+pub mod prop_value;
+pub mod properties;
+pub mod writer;
+pub mod extract;
+pub mod options;
+mod charset;
+pub mod symmetry;
+pub mod strict;
+pub mod stream;
+pub mod merge;
+pub mod validate;
+pub mod board;
+pub mod scoring;
+pub mod archive;
+mod problems;
+pub mod tidy;
+pub mod view;
+pub mod stats;
+#[cfg(feature = "compact")]
+mod compact;
+pub mod game_result;
+pub mod print;
+pub mod stamp;
+pub mod pattern;
+pub mod sgf_options;
+pub mod html;
+#[cfg(feature = "persistent")]
+pub mod persistent;
+#[cfg(feature = "track_changes")]
+pub mod tracked;
+#[cfg(feature = "i18n")]
+pub mod i18n;
 mod parser;
 
 pub use sgf_node::*;
+pub use prop_value::*;
+pub use writer::*;
+pub use extract::*;
+pub use options::*;
+pub use symmetry::*;
+pub use strict::*;
+pub use stream::*;
+pub use merge::*;
+pub use validate::*;
+pub use board::*;
+pub use scoring::*;
+pub use archive::*;
+pub use view::*;
+pub use tidy::*;
+pub use stats::*;
+pub use game_result::*;
+pub use print::*;
+pub use stamp::*;
+pub use sgf_options::*;
+pub use html::*;
+#[cfg(feature = "persistent")]
+pub use persistent::*;
+#[cfg(feature = "track_changes")]
+pub use tracked::*;
+#[cfg(feature = "i18n")]
+pub use i18n::*;
@@ -0,0 +1,690 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Merging games and collections
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use sgf_node::*;
+use board::{node_move, BoardFeature, Move};
+use symmetry::Symmetry;
+use properties::{property_spec, PropertyType};
+
+/// How to treat duplicated games in `SgfCollection::merge_from`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Appends all games.
+    AppendAll,
+    /// Skips games equal to a game already in the collection.
+    SkipExactDuplicates,
+    /// Skips games with the same Dyer signature as a game already in the collection.
+    SkipSameSignature,
+    /// When a game has the same Dyer signature as a game already in the collection,
+    /// keeps the one with more properties in total. The existing one is kept on ties.
+    PreferRicher,
+}
+
+/// Counts of outcomes of `SgfCollection::merge_from`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergeSummary {
+    pub appended: usize,
+    pub skipped_exact: usize,
+    pub skipped_signature: usize,
+    /// Existing games replaced by richer ones under PreferRicher
+    pub replaced: usize,
+}
+
+impl SgfNode {
+    /// Returns the Dyer signature of the game, which is the concatenation of the points of moves
+    /// 20, 40, 60, 31, 51 and 71 in the main line.
+    /// Passes and missing moves are represented as "--".
+    /// Returns None if the main line has fewer than 20 moves since the signature identifies nothing then.
+    pub fn dyer_signature(&self) -> Option<String> {
+        let moves = main_line_points(self);
+        if moves.len() < 20 {
+            return None;
+        }
+        Some([20, 40, 60, 31, 51, 71].iter().map(|&n| match moves.get(n - 1) {
+            Some(p) if p.len() == 2 => p.clone(),
+            _ => "--".to_string(),
+        }).collect())
+    }
+}
+
+impl SgfCollection {
+    /// Appends games of `other` to the collection according to `policy`.
+    /// Games in `other` are also compared with games appended from `other` before them.
+    pub fn merge_from(&mut self, other: SgfCollection, policy: MergePolicy) -> MergeSummary {
+        let mut summary = MergeSummary::default();
+        // the index of the first game with each signature, computed once per game
+        let mut signatures = HashMap::new();
+        if policy == MergePolicy::SkipSameSignature || policy == MergePolicy::PreferRicher {
+            for (i, game) in self.iter().enumerate() {
+                if let Some(s) = game.dyer_signature() {
+                    signatures.entry(s).or_insert(i);
+                }
+            }
+        }
+        for game in other {
+            match policy {
+                MergePolicy::AppendAll => (),
+                MergePolicy::SkipExactDuplicates => if self.contains(&game) {
+                    summary.skipped_exact += 1;
+                    continue;
+                },
+                MergePolicy::SkipSameSignature | MergePolicy::PreferRicher => {
+                    let signature = game.dyer_signature();
+                    if let Some(&i) = signature.as_ref().and_then(|s| signatures.get(s)) {
+                        if policy == MergePolicy::PreferRicher && property_count(&game) > property_count(&self[i]) {
+                            self[i] = game;
+                            summary.replaced += 1;
+                        } else {
+                            summary.skipped_signature += 1;
+                        }
+                        continue;
+                    }
+                    if let Some(s) = signature {
+                        signatures.insert(s, self.len());
+                    }
+                },
+            }
+            self.push(game);
+            summary.appended += 1;
+        }
+        summary
+    }
+}
+
+/// Tolerance of `SgfCollection::find_matching`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchTolerance {
+    /// Maximum number of moves after the common prefix in each of the two main lines for a PrefixMatch
+    pub max_trailing_moves: usize,
+    /// Minimum number of moves of the common prefix for a PrefixMatch
+    pub min_common_moves: usize,
+    /// Whether games are also compared under the symmetries. Only square boards are transformed.
+    pub symmetry: bool,
+}
+
+/// How a game matched in `SgfCollection::find_matching`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchQuality {
+    /// The main lines have the same moves.
+    Exact,
+    /// The main lines have the same moves after the symmetry is applied to the searched game.
+    SymmetryEquivalent(Symmetry),
+    /// The main lines share the first n moves, possibly under a symmetry.
+    PrefixMatch(usize),
+}
+
+impl MatchQuality {
+    fn rank(&self) -> (u8, usize) {
+        match *self {
+            MatchQuality::Exact => (2, 0),
+            MatchQuality::SymmetryEquivalent(_) => (1, 0),
+            MatchQuality::PrefixMatch(n) => (0, n),
+        }
+    }
+}
+
+impl SgfCollection {
+    /// Finds copies of `game` in the collection, which may have different properties other than moves.
+    ///
+    /// Main lines are compared by moves of the board size of `game`, so games of other sizes never match,
+    /// and nothing matches if SZ of `game` is not supported.
+    /// Dyer signatures of games in the collection filter out games which can't match before main lines are compared.
+    /// Returns indices of matched games with the best quality of each, in the order of the collection.
+    pub fn find_matching(&self, game: &SgfNode, tolerance: MatchTolerance) -> Vec<(usize, MatchQuality)> {
+        let size = match game.board_size(BoardFeature::Coordinates) {
+            Ok(size) => size,
+            Err(_) => return Vec::new(),
+        };
+        let moves = match game.move_sequence() {
+            Ok(moves) => moves,
+            Err(_) => return Vec::new(),
+        };
+        let symmetries = if tolerance.symmetry && size.0 == size.1 { Symmetry::all().to_vec() } else { vec![Symmetry::Identity] };
+        let queries = symmetries.into_iter().map(|sym| {
+            (sym, moves.iter().map(|&(c, p)| (c, p.map(|p| sym.apply(p, size.0)))).collect::<Vec<_>>())
+        }).collect::<Vec<_>>();
+        let mut result = Vec::new();
+        for (i, candidate) in self.iter().enumerate() {
+            if candidate.board_size(BoardFeature::Coordinates).ok() != Some(size) {
+                continue;
+            }
+            let signature = candidate.dyer_signature();
+            let queries = queries.iter()
+                .filter(|(_, query)| signature.as_ref().map_or(true, |s| !signature_conflicts(s, query, size, tolerance.max_trailing_moves)))
+                .collect::<Vec<_>>();
+            if queries.is_empty() {
+                continue;
+            }
+            let other = match candidate.move_sequence() {
+                Ok(moves) => moves,
+                Err(_) => continue,
+            };
+            let mut best: Option<MatchQuality> = None;
+            for &&(sym, ref query) in queries.iter() {
+                let n = query.iter().zip(other.iter()).take_while(|&(a, b)| a == b).count();
+                let quality = if n == query.len() && n == other.len() {
+                    if sym == Symmetry::Identity { MatchQuality::Exact } else { MatchQuality::SymmetryEquivalent(sym) }
+                } else if n >= tolerance.min_common_moves && query.len() - n <= tolerance.max_trailing_moves &&
+                        other.len() - n <= tolerance.max_trailing_moves {
+                    MatchQuality::PrefixMatch(n)
+                } else {
+                    continue;
+                };
+                if best.map_or(true, |b| quality.rank() > b.rank()) {
+                    best = Some(quality);
+                }
+            }
+            if let Some(quality) = best {
+                result.push((i, quality));
+            }
+        }
+        result
+    }
+}
+
+/// Returns true if a move in a Dyer signature differs from the move of `moves`
+/// at a move number which must be in the common prefix to match with the tolerance.
+fn signature_conflicts(signature: &str, moves: &[Move], (width, height): (u8, u8), tolerance: usize) -> bool {
+    [20, 40, 60, 31, 51, 71].iter().enumerate().any(|(k, &n)| {
+        let slot = &signature[2 * k..2 * k + 2];
+        let point = point_to_coord(slot).filter(|&(x, y)| x < width && y < height);
+        n + tolerance <= moves.len() && slot != "--" && point != moves[n - 1].1
+    })
+}
+
+/// Property of nodes of `SgfCollection::to_opening_tree` with the number of games which reached the node.
+/// It is a private property not defined in FF[4].
+pub const OPENING_COUNT_PROPERTY: &str = "VC";
+
+impl SgfNode {
+    /// Merges `line`, a sequence of nodes, as a line of descendants of the node.
+    /// Each node is compared with children by its B and W only and the matching child is followed.
+    /// The rest of the line is appended as a new variation at the first node without a match.
+    /// Children of nodes in `line` are discarded.
+    /// Returns the path to the node corresponding to the last node of `line`.
+    pub fn merge_variation(&mut self, line: Vec<SgfNode>) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut node = self;
+        for mut n in line {
+            n.children.clear();
+            let key = move_key(&n);
+            let i = match node.children.iter().position(|c| move_key(c) == key) {
+                Some(i) => i,
+                None => {
+                    node.children.push(n);
+                    node.children.len() - 1
+                },
+            };
+            path.push(i);
+            node = &mut node.children[i];
+        }
+        path
+    }
+}
+
+impl SgfCollection {
+    /// Merges main lines of all games into one tree sharing common prefixes for opening study.
+    ///
+    /// The root is a new node and each following node has only a move, B or W, of the games.
+    /// Every node including the root has OPENING_COUNT_PROPERTY(VC) with the number of games reaching it.
+    /// Games are assumed to be on the same board size. Moves are compared by their values as they are,
+    /// so a pass written as "tt" and as an empty value are different moves.
+    pub fn to_opening_tree(&self) -> SgfNode {
+        let mut root = SgfNode::new(HashMap::new());
+        root.set_number("FF", 4);
+        root.set_number("GM", 1);
+        root.set_number(OPENING_COUNT_PROPERTY, self.len() as SgfNumber);
+        for game in self.iter() {
+            let mut line = Vec::new();
+            let mut node = game;
+            loop {
+                for color in ["B", "W"].iter() {
+                    if let Ok(p) = node.get_point(color) {
+                        let mut n = SgfNode::new(HashMap::new());
+                        n.set_point(color, p);
+                        line.push(n);
+                    }
+                }
+                if node.children.is_empty() {
+                    break;
+                }
+                node = &node.children[0];
+            }
+            let path = root.merge_variation(line);
+            let mut node = &mut root;
+            for i in path {
+                node = &mut node.children[i];
+                let count = node.get_number(OPENING_COUNT_PROPERTY).unwrap_or(0);
+                node.set_number(OPENING_COUNT_PROPERTY, count + 1);
+            }
+        }
+        root
+    }
+}
+
+impl SgfNode {
+    /// Expands the tree into linear games, one for each path from the node to a leaf, in depth-first order.
+    /// Every node on a path is copied with all its properties, so each game has the properties of the root.
+    ///
+    /// The number of games is the number of leaves, and the total size of the result is
+    /// the sum of the path lengths, which can be much larger than the tree when it branches near the root.
+    /// Check `iter_depth_first().filter(|n| n.children.is_empty()).count()` in advance for large trees.
+    pub fn expand_variations(&self) -> Vec<SgfNode> {
+        let head = self.without_children();
+        if self.children.is_empty() {
+            return vec![head];
+        }
+        self.children.iter().flat_map(|child| child.expand_variations()).map(|line| {
+            let mut game = head.clone();
+            game.children.push(line);
+            game
+        }).collect()
+    }
+}
+
+/// How `SgfNode::continue_with` treats root properties of the next part differing from the game's.
+/// Root properties only the next part has are always added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataPolicy {
+    /// Keeps the game's values.
+    KeepExisting,
+    /// Replaces the game's values with the next part's.
+    PreferNext,
+    /// Fails with MetadataConflict.
+    Reject,
+}
+
+/// Options of `SgfNode::continue_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContinuationOptions {
+    /// Maximum number of moves the next part may repeat from the end of the game
+    pub max_overlap: usize,
+    pub metadata: MetadataPolicy,
+}
+
+/// Outcome of `SgfNode::continue_with`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ContinuationReport {
+    /// Number of moves the next part repeated from the end of the game
+    pub overlap: usize,
+    /// Number of moves appended to the main line
+    pub appended: usize,
+    /// Identifiers of root properties whose values differed
+    pub conflicts: Vec<String>,
+}
+
+/// Errors of `SgfNode::continue_with`.
+#[derive(Debug)]
+pub enum MergeError {
+    /// A move of either part is not a point on the board nor a pass, or SZ of the game is not supported.
+    InvalidMove(SgfError),
+    /// The next part doesn't continue the game. The value is the move number in the game
+    /// at which the next part has a different move, one more than the game's moves if it isn't a legal next move.
+    Diverged(usize),
+    /// The root property differs under MetadataPolicy::Reject, or SZ differs under any policy.
+    MetadataConflict(String),
+    /// The position at the end of the main line of the game can't be reconstructed.
+    UnreplayableMainLine,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MergeError::InvalidMove(ref e) => write!(f, "invalid move: {}", e),
+            MergeError::Diverged(n) => write!(f, "the next part differs from the game at move {}", n),
+            MergeError::MetadataConflict(ref id) => write!(f, "root property {} conflicts", id),
+            MergeError::UnreplayableMainLine => write!(f, "the main line of the game can't be replayed"),
+        }
+    }
+}
+
+impl Error for MergeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            MergeError::InvalidMove(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl SgfNode {
+    /// Splices `next`, the next part of a game split into several files, onto the main line of the game.
+    ///
+    /// Moves are counted by node as `board_at` counts them, so a node with both B and W is a move of B.
+    /// The main line of `next` either starts with the move following the last move of the game,
+    /// which is the opposite color on an empty point, or repeats the last moves of the game
+    /// up to `options.max_overlap` moves, the longest overlap preferred.
+    /// Nodes of `next` after the repeated moves, or after its root when nothing is repeated, become
+    /// children of the last node of the main line, variations included, and moves in the root of `next`
+    /// are split off by `split_root_move` first.
+    /// Setup properties in the root of `next`, such as AB and AW, are put in a new node inserted before
+    /// the appended nodes, so they apply to the position where `next` continues the game.
+    /// The other root properties are merged by `options.metadata`. Nothing is changed on errors.
+    pub fn continue_with(&mut self, next: &SgfNode, options: ContinuationOptions) -> Result<ContinuationReport, MergeError> {
+        let mut next = next.clone();
+        next.split_root_move();
+        let size = self.board_size(BoardFeature::Reconstruction).map_err(MergeError::InvalidMove)?;
+        if next.board_size(BoardFeature::Reconstruction).ok() != Some(size) {
+            return Err(MergeError::MetadataConflict("SZ".to_string()));
+        }
+        let moves = main_line_moves(self).map_err(MergeError::InvalidMove)?;
+        let next_moves = main_line_moves(&next).map_err(MergeError::InvalidMove)?;
+        let n = moves.len();
+        let max = options.max_overlap.min(n).min(next_moves.len());
+        let overlap = match (1..=max).rev().find(|&k| moves[n - k..] == next_moves[..k]) {
+            Some(k) => k,
+            None => {
+                let mut best: Option<(usize, usize)> = None;
+                for k in 1..=max {
+                    let matched = moves[n - k..].iter().zip(next_moves.iter()).take_while(|&(a, b)| a == b).count();
+                    if matched > 0 && best.map_or(true, |(m, _)| matched > m) {
+                        best = Some((matched, n - k + matched + 1));
+                    }
+                }
+                if let Some((_, move_number)) = best {
+                    return Err(MergeError::Diverged(move_number));
+                }
+                if let Some(&(color, point)) = next_moves.first() {
+                    let board = self.board_at(size, n).map_err(MergeError::InvalidMove)?.ok_or(MergeError::UnreplayableMainLine)?;
+                    let wrong_color = moves.last().map_or(false, |&(c, _)| c == color);
+                    if wrong_color || point.map_or(false, |p| board.get(p).is_some()) {
+                        return Err(MergeError::Diverged(n + 1));
+                    }
+                }
+                0
+            },
+        };
+        let is_setup = |id: &str| property_spec(id).map(|spec| spec.property_type) == Some(PropertyType::Setup);
+        let mut conflicts = Vec::new();
+        for (id, values) in next.iter_values().filter(|p| !is_setup(p.0)) {
+            match self.get_property(id) {
+                Ok(v) if v != values => {
+                    if options.metadata == MetadataPolicy::Reject {
+                        return Err(MergeError::MetadataConflict(id.clone()));
+                    }
+                    conflicts.push(id.clone());
+                },
+                _ => (),
+            }
+        }
+        for (id, values) in next.iter().filter(|p| !is_setup(p.0)) {
+            if self.get_property(id).is_err() || (options.metadata == MetadataPolicy::PreferNext && conflicts.contains(id)) {
+                self.set_property(id, values.clone());
+            }
+        }
+        let mut setup = SgfNode::new(Vec::new());
+        for (id, values) in next.iter().filter(|p| is_setup(p.0)) {
+            setup.set_property(id, values.clone());
+        }
+        let mut rest = &mut next;
+        let mut count = 0;
+        while count < overlap {
+            rest = &mut rest.children[0];
+            if rest.get_property("B").is_ok() || rest.get_property("W").is_ok() {
+                count += 1;
+            }
+        }
+        let mut leaf = self;
+        while !leaf.children.is_empty() {
+            leaf = &mut leaf.children[0];
+        }
+        if setup.iter().next().is_none() {
+            leaf.children.append(&mut rest.children);
+        } else {
+            setup.children.append(&mut rest.children);
+            leaf.children.push(setup);
+        }
+        Ok(ContinuationReport { overlap, appended: next_moves.len() - overlap, conflicts })
+    }
+}
+
+fn move_key(node: &SgfNode) -> (Option<SgfPoint>, Option<SgfPoint>) {
+    (node.get_point("B").ok(), node.get_point("W").ok())
+}
+
+/// Returns the moves in the main line, one per node as `Board::apply_node` plays them.
+fn main_line_moves(node: &SgfNode) -> Result<Vec<Move>, SgfError> {
+    let size = node.board_size(BoardFeature::Coordinates)?;
+    let mut moves = Vec::new();
+    let mut node = node;
+    loop {
+        match node_move(node, 'B', size)? {
+            Some(m) => moves.push(m),
+            None => moves.extend(node_move(node, 'W', size)?),
+        }
+        if node.children.is_empty() {
+            return Ok(moves);
+        }
+        node = &node.children[0];
+    }
+}
+
+fn main_line_points(node: &SgfNode) -> Vec<SgfPoint> {
+    let mut moves = Vec::new();
+    let mut node = node;
+    loop {
+        if let Ok(p) = node.get_point("B").or_else(|_| node.get_point("W")) {
+            moves.push(p);
+        }
+        if node.children.is_empty() {
+            return moves;
+        }
+        node = &node.children[0];
+    }
+}
+
+fn property_count(node: &SgfNode) -> usize {
+    node.iter().count() + node.children.iter().map(property_count).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns nodes of moves of numbers in range, which alternate colors from B on points in row order.
+    fn moves(range: ::std::ops::Range<usize>) -> String {
+        range.map(|i| {
+            let p = format!("{}{}", (b'a' + (i % 19) as u8) as char, (b'a' + (i / 19) as u8) as char);
+            format!(";{}[{}]", if i % 2 == 0 { "B" } else { "W" }, p)
+        }).collect()
+    }
+
+    fn game(prefix: &str, n: usize, suffix: &str) -> String {
+        format!("(;FF[4]{}{}{})", prefix, moves(0..n), suffix)
+    }
+
+    fn fixtures() -> (SgfCollection, SgfCollection) {
+        let base = SgfCollection::from_sgf(&(game("PB[a]", 80, "") + &game("PB[b]", 30, ""))).unwrap();
+        let other = SgfCollection::from_sgf(&(
+            game("PB[a]", 80, "") +                  // exact duplicate of base[0]
+            &game("PB[b]GC[annotated]", 30, "C[resign]") + // same moves as base[1] with more properties
+            &game("PB[c]", 10, "")                   // new short game
+        )).unwrap();
+        (base, other)
+    }
+
+    fn part(root: &str, range: ::std::ops::Range<usize>) -> SgfNode {
+        let s = format!("(;FF[4]SZ[19]{}{})", root, moves(range));
+        SgfCollection::from_sgf(&s).unwrap().into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_continue_with() {
+        let options = ContinuationOptions { max_overlap: 10, metadata: MetadataPolicy::KeepExisting };
+        let whole = part("PB[a]", 0..30).move_sequence().unwrap();
+        // clean continuation
+        let mut game = part("PB[a]", 0..20);
+        let report = game.continue_with(&part("PB[a]PW[b]RE[B+R]", 20..30), options).unwrap();
+        assert_eq!(report, ContinuationReport { overlap: 0, appended: 10, conflicts: vec![] });
+        assert_eq!(game.move_sequence().unwrap(), whole);
+        assert_eq!(game.get_simple_text("PW").unwrap(), "b");
+        // 5-move overlap with a conflicting root property
+        let mut game = part("PB[a]", 0..20);
+        let next = part("PB[a (9d)]", 15..30);
+        let report = game.continue_with(&next, options).unwrap();
+        assert_eq!(report, ContinuationReport { overlap: 5, appended: 10, conflicts: vec!["PB".to_string()] });
+        assert_eq!(game.move_sequence().unwrap(), whole);
+        assert_eq!(game.get_simple_text("PB").unwrap(), "a");
+        let mut preferred = part("PB[a]", 0..20);
+        preferred.continue_with(&next, ContinuationOptions { metadata: MetadataPolicy::PreferNext, ..options }).unwrap();
+        assert_eq!(preferred.get_simple_text("PB").unwrap(), "a (9d)");
+        let mut rejected = part("PB[a]", 0..20);
+        assert!(matches!(rejected.continue_with(&next, ContinuationOptions { metadata: MetadataPolicy::Reject, ..options }),
+                         Err(MergeError::MetadataConflict(ref id)) if id == "PB"));
+        assert_eq!(rejected, part("PB[a]", 0..20));
+        // overlap longer than the limit
+        let mut game = part("PB[a]", 0..20);
+        assert!(game.continue_with(&next, ContinuationOptions { max_overlap: 3, ..options }).is_err());
+        // setup properties of the next part apply where it continues the game
+        let mut game = part("", 0..20);
+        game.continue_with(&part("PB[a]AB[ss]AW[rs]", 20..30), options).unwrap();
+        assert_eq!(game.move_sequence().unwrap(), whole);
+        assert_eq!(game.get_simple_text("PB").unwrap(), "a");
+        assert!(game.get_points("AB").is_err());
+        let setup = game.node_at(&[0; 20]).unwrap().children[0].clone();
+        assert_eq!(setup.get_points("AB").unwrap(), vec!["ss"]);
+        assert_eq!(setup.get_points("AW").unwrap(), vec!["rs"]);
+        assert_eq!(setup.children[0].get_point("B").unwrap(), "bb");
+    }
+
+    #[test]
+    fn test_continue_with_mismatch() {
+        let options = ContinuationOptions { max_overlap: 10, metadata: MetadataPolicy::KeepExisting };
+        // repeats moves 16 to 18 and differs at move 19
+        let mut game = part("", 0..20);
+        let mut next = part("", 15..18);
+        next.leaf_mut().children.push(part("", 30..32).children.remove(0));
+        assert!(matches!(game.continue_with(&next, options), Err(MergeError::Diverged(19))));
+        assert_eq!(game, part("", 0..20));
+        // the first move is on an occupied point
+        let next = part("", 19..22);
+        assert!(matches!(game.continue_with(&next, ContinuationOptions { max_overlap: 0, ..options }), Err(MergeError::Diverged(21))));
+        // SZ which doesn't fit in u8 is not truncated to 19
+        let mut large = part("", 0..20);
+        large.set_number("SZ", 275);
+        let mut next = part("", 20..22);
+        next.set_number("SZ", 275);
+        let error = large.continue_with(&next, options).unwrap_err();
+        assert!(matches!(error, MergeError::InvalidMove(SgfError::OutOfRange(_))));
+        assert_eq!(error.to_string(), "invalid move: '275' is out of range");
+        assert!(error.source().is_some());
+        assert_eq!(MergeError::Diverged(19).to_string(), "the next part differs from the game at move 19");
+        assert_eq!(MergeError::MetadataConflict("PB".to_string()).to_string(), "root property PB conflicts");
+    }
+
+    #[test]
+    fn test_continue_with_both_colors_in_a_node() {
+        let options = ContinuationOptions { max_overlap: 10, metadata: MetadataPolicy::KeepExisting };
+        let part = |sgf: &str| SgfCollection::from_sgf(sgf).unwrap()[0].clone();
+        // the node with B and W is a move of B as on the board
+        let mut game = part("(;FF[4];B[aa]W[bb])");
+        assert!(matches!(game.continue_with(&part("(;FF[4];B[cc])"), options), Err(MergeError::Diverged(2))));
+        let report = game.continue_with(&part("(;FF[4];W[cc])"), options).unwrap();
+        assert_eq!(report, ContinuationReport { overlap: 0, appended: 1, conflicts: vec![] });
+        assert_eq!(game.children[0].children[0].get_point("W").unwrap(), "cc");
+        let mut game = part("(;FF[4];B[aa]W[bb])");
+        let report = game.continue_with(&part("(;FF[4];B[aa]W[bb];W[cc])"), options).unwrap();
+        assert_eq!(report, ContinuationReport { overlap: 1, appended: 1, conflicts: vec![] });
+    }
+
+    #[test]
+    fn test_dyer_signature() {
+        let c = SgfCollection::from_sgf(&game("", 80, "")).unwrap();
+        assert_eq!(c[0].dyer_signature().unwrap(), "abbccdlbmcnd");
+        let c = SgfCollection::from_sgf(&game("", 45, "")).unwrap();
+        assert_eq!(c[0].dyer_signature().unwrap(), "abbc--lb----");
+        let c = SgfCollection::from_sgf(&game("", 10, "")).unwrap();
+        assert_eq!(c[0].dyer_signature(), None);
+    }
+
+    #[test]
+    fn test_merge_from() {
+        let (mut base, other) = fixtures();
+        let summary = base.merge_from(other, MergePolicy::AppendAll);
+        assert_eq!((base.len(), summary.appended), (5, 3));
+
+        let (mut base, other) = fixtures();
+        let summary = base.merge_from(other, MergePolicy::SkipExactDuplicates);
+        assert_eq!((base.len(), summary.appended, summary.skipped_exact), (4, 2, 1));
+
+        let (mut base, other) = fixtures();
+        let summary = base.merge_from(other, MergePolicy::SkipSameSignature);
+        assert_eq!((base.len(), summary.appended, summary.skipped_signature), (3, 1, 2));
+        assert!(base[1].get_text("GC").is_err());
+
+        let (mut base, other) = fixtures();
+        let summary = base.merge_from(other, MergePolicy::PreferRicher);
+        assert_eq!((base.len(), summary.appended, summary.skipped_signature, summary.replaced), (3, 1, 1, 1));
+        assert_eq!(base[1].get_text("GC").unwrap(), "annotated");
+    }
+
+    #[test]
+    fn test_expand_variations() {
+        let node = &SgfCollection::from_sgf("(;FF[4]C[root](;C[a];C[b](;C[c])
+            (;C[d];C[e]))
+            (;C[f](;C[g];C[h];C[i])
+            (;C[j])))").unwrap()[0];
+        let games = node.expand_variations();
+        assert_eq!(games.len(), 4);
+        let comments = |game: &SgfNode| game.main_line().map(|n| n.get_text("C").unwrap()).collect::<Vec<_>>().join("");
+        assert_eq!(games.iter().map(comments).collect::<Vec<_>>(), vec!["rootabc", "rootabde", "rootfghi", "rootfj"]);
+        assert!(games.iter().all(|g| g.get_number("FF").ok() == Some(4) && g.iter_depth_first().all(|n| n.children.len() <= 1)));
+        let leaf = node.node_at(&[1, 1]).unwrap();
+        assert_eq!(leaf.expand_variations(), vec![leaf.clone()]);
+    }
+
+    #[test]
+    fn test_to_opening_tree() {
+        let c = SgfCollection::from_sgf("(;FF[4];B[pd];W[dd];B[pq])(;FF[4]C[x];B[pd];W[dd];B[dp])(;FF[4];B[qd](;W[dd])(;W[dp]))").unwrap();
+        let tree = c.to_opening_tree();
+        let count = |path: &[usize]| {
+            let mut node = &tree;
+            for &i in path {
+                node = &node.children[i];
+            }
+            (node.get_point("B").or_else(|_| node.get_point("W")).unwrap_or_default(), node.get_number(OPENING_COUNT_PROPERTY).unwrap())
+        };
+        assert_eq!(count(&[]), ("".to_string(), 3));
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(count(&[0]), ("pd".to_string(), 2));
+        assert_eq!(count(&[0, 0]), ("dd".to_string(), 2));
+        assert_eq!(count(&[0, 0, 0]), ("pq".to_string(), 1));
+        assert_eq!(count(&[0, 0, 1]), ("dp".to_string(), 1));
+        assert_eq!(count(&[1]), ("qd".to_string(), 1));
+        assert_eq!(count(&[1, 0]), ("dd".to_string(), 1));
+        assert_eq!(tree.children[1].children.len(), 1);
+        assert!(tree.children[0].get_text("C").is_err());
+    }
+
+    #[test]
+    fn test_find_matching() {
+        let original = SgfCollection::from_sgf(&game("PB[a]SZ[19]", 80, "")).unwrap();
+        let mut rotated = original[0].clone();
+        rotated.transform(Symmetry::Rotate90, 19).unwrap();
+        rotated.set_simple_text("PB", "rotated".to_string());
+        let truncated = SgfCollection::from_sgf(&game("PB[b]SZ[19]", 77, "")).unwrap();
+        let mut different = SgfCollection::from_sgf(&game("PB[c]SZ[19]", 80, "")).unwrap();
+        different[0].children[0].set_point("B", "ss".to_string());
+        let mut c = SgfCollection::new(vec![different[0].clone(), rotated, truncated[0].clone(), original[0].clone()]);
+        c.push(SgfCollection::from_sgf(&game("SZ[9]", 8, "")).unwrap()[0].clone());
+        let tolerance = MatchTolerance { max_trailing_moves: 5, min_common_moves: 20, symmetry: true };
+        assert_eq!(c.find_matching(&original[0], tolerance), vec![
+            (1, MatchQuality::SymmetryEquivalent(Symmetry::Rotate90)),
+            (2, MatchQuality::PrefixMatch(77)),
+            (3, MatchQuality::Exact),
+        ]);
+        let strict = MatchTolerance { max_trailing_moves: 0, min_common_moves: 0, symmetry: false };
+        assert_eq!(c.find_matching(&original[0], strict), vec![(3, MatchQuality::Exact)]);
+        assert!(c.find_matching(&different[0], strict).len() == 1);
+        let mut unsupported = original[0].clone();
+        unsupported.set_number("SZ", 275);
+        assert!(c.find_matching(&unsupported, tolerance).is_empty());
+        c.push(unsupported.clone());
+        assert_eq!(c.find_matching(&original[0], strict), vec![(3, MatchQuality::Exact)]);
+    }
+}
@@ -0,0 +1,745 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Parse options
+
+use std::collections::{HashMap, HashSet};
+use sgf_node::*;
+use parser::*;
+use properties::{property_spec, ValueType, PROPERTIES};
+
+/// Default of `ParseOptions::max_value_length`, 1MiB.
+pub const DEFAULT_MAX_VALUE_LENGTH: usize = 1 << 20;
+
+/// Options for `SgfCollection::from_sgf_with_options`.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Trims leading and trailing whitespaces of property values whose types are not Text nor SimpleText.
+    /// RE and DT are SimpleText in the specification but they are normalized too since they have formats.
+    /// Nonstandard properties are never normalized.
+    pub normalize_values: bool,
+    /// Maximum length in bytes of a single raw property value.
+    /// A longer value is a parse error whose offset points at the identifier of the property,
+    /// so `&sgf_str[error.offset..]` begins with its name, and whose `expected` names the identifier
+    /// if the property is a standard one, like "C value within max_value_length".
+    /// The input is checked before any tree is built.
+    pub max_value_length: usize,
+    /// Repairs some malformed inputs instead of failing and reports them in `ParseReport::warnings`.
+    ///
+    /// * A value ending with a backslash right before `]`, like `C[C:\]`, escapes its terminator
+    ///   and runs past it. Such a backslash is taken as a literal one if the `]` can be followed by the rest
+    ///   while the escaped value continues with `[` or up to the end of the input.
+    ///   If the input can't be parsed as it is, all such backslashes are taken so in one pass over the input,
+    ///   and they are kept only if the input is parsed then.
+    /// * Lowercase letters in property identifiers, like `CoPyright` of FF[3], are removed as FF[4] requires
+    ///   for compatibility. A property whose identifier has no uppercase letters is removed.
+    /// * Values of a property appearing again in the same node are appended to the values of the first one.
+    /// * An empty variation `()` is removed.
+    /// * Text after the last game tree is ignored unless it contains `(`.
+    pub lenient: bool,
+    /// Maximum nesting of game trees, where a game is 1 and each level of variations adds 1.
+    /// Deeper input is a parse error at the exceeding '(' and the check runs before any tree is built.
+    /// None is unlimited.
+    pub max_nesting: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            normalize_values: false,
+            max_value_length: DEFAULT_MAX_VALUE_LENGTH,
+            lenient: false,
+            max_nesting: None,
+        }
+    }
+}
+
+/// A record of a value modified during parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalization {
+    /// Index of the game in the collection
+    pub game: usize,
+    /// Child indices from the root of the game to the node
+    pub path: Vec<usize>,
+    pub id: String,
+    pub original: String,
+    pub normalized: String,
+}
+
+/// A problem of the input repaired in lenient mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    /// Byte offset of the problem in the input
+    pub offset: usize,
+    /// 1-based line number of the offset
+    pub line: usize,
+    /// 1-based column number in characters of the offset
+    pub column: usize,
+    pub message: String,
+}
+
+/// Report of modifications made during parsing.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParseReport {
+    pub normalizations: Vec<Normalization>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// Statistics of a parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseStats {
+    /// Number of games(top-level game trees)
+    pub games: usize,
+    /// Number of nodes in all games
+    pub nodes: usize,
+    /// Number of nodes on the longest path from the root of a game to a leaf among all games
+    pub max_depth: usize,
+}
+
+impl SgfCollection {
+    /// Parses a SGF string with options and returns a SgfCollection with a report.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sgf::*;
+    ///
+    /// let options = ParseOptions { normalize_values: true, ..Default::default() };
+    /// let (c, report) = SgfCollection::from_sgf_with_options("(;FF[4]KM[ 6.5])", &options).unwrap();
+    /// assert_eq!(c[0].get_real("KM").unwrap(), 6.5);
+    /// assert_eq!(report.normalizations.len(), 1);
+    /// ```
+    pub fn from_sgf_with_options(sgf_str: &str, options: &ParseOptions) -> ParseResult<(SgfCollection, ParseReport)> {
+        let mut report = ParseReport::default();
+        check_value_lengths(sgf_str, options.max_value_length)?;
+        if let Some(max) = options.max_nesting {
+            check_nesting(sgf_str, max)?;
+        }
+        let mut c = if options.lenient {
+            parse_lenient(sgf_str, &mut report.warnings)?
+        } else {
+            collection(sgf_str).map_err(|e| locate_runaway_value(sgf_str, e))?
+        };
+        if options.normalize_values {
+            for (i, game) in c.iter_mut().enumerate() {
+                normalize_values(game, i, &mut vec![], &mut report);
+            }
+        }
+        Ok((c, report))
+    }
+
+    /// Parses a SGF string and returns a SgfCollection with statistics of it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sgf::*;
+    ///
+    /// let (_, stats) = SgfCollection::from_sgf_with_stats("(;FF[4];B[aa](;W[bb])(;W[cc];B[dd]))(;FF[4])").unwrap();
+    /// assert_eq!((stats.games, stats.nodes, stats.max_depth), (2, 6, 4));
+    /// ```
+    pub fn from_sgf_with_stats(sgf_str: &str) -> ParseResult<(SgfCollection, ParseStats)> {
+        let c = collection(sgf_str)?;
+        let mut stats = ParseStats { games: c.len(), ..Default::default() };
+        for game in c.iter() {
+            let profile = game.width_profile();
+            stats.nodes += profile.iter().sum::<usize>();
+            stats.max_depth = stats.max_depth.max(profile.len());
+        }
+        Ok((c, stats))
+    }
+}
+
+/// Returns an error naming the identifier and max if a property value is longer than max bytes.
+fn check_value_lengths(sgf_str: &str, max: usize) -> ParseResult<()> {
+    let bytes = sgf_str.as_bytes();
+    let mut id_start = 0;
+    let mut value_start = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        match value_start {
+            Some(start) => {
+                match c {
+                    b'\\' => i += 1,
+                    b']' => value_start = None,
+                    _ => (),
+                }
+                if value_start.is_some() && i - start >= max {
+                    let id_end = sgf_str[id_start..].find(|c: char| !c.is_ascii_alphabetic()).map_or(sgf_str.len(), |k| id_start + k);
+                    return Err(error_at(sgf_str, id_start, value_length_message(&sgf_str[id_start..id_end])));
+                }
+            },
+            None => match c {
+                b'[' => value_start = Some(i + 1),
+                b'A'..=b'Z' | b'a'..=b'z' if i == 0 || !(bytes[i - 1] as char).is_ascii_alphabetic() => id_start = i,
+                _ => (),
+            },
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Returns an error at the '(' which nests game trees deeper than max.
+fn check_nesting(sgf_str: &str, max: usize) -> ParseResult<()> {
+    let bytes = sgf_str.as_bytes();
+    let mut depth = 0;
+    let mut in_value = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match (in_value, bytes[i]) {
+            (true, b'\\') => i += 1,
+            (true, b']') => in_value = false,
+            (false, b'[') => in_value = true,
+            (false, b'(') => {
+                depth += 1;
+                if depth > max {
+                    return Err(error_at(sgf_str, i, "game trees nested within the limit"));
+                }
+            },
+            (false, b')') => depth -= 1,
+            _ => (),
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+macro_rules! value_length_messages {
+    ($($id:expr),*) => { &[$(concat!($id, " value within max_value_length")),*] };
+}
+
+/// `ParseError::expected` of a too long value of each standard property, in the order of `PROPERTIES`.
+/// Messages are static so that errors of hostile inputs allocate nothing which outlives them.
+static VALUE_LENGTH_MESSAGES: &[&str] = value_length_messages!(
+    "AP", "CA", "FF", "GM", "ST", "SZ",
+    "AN", "BR", "BT", "CP", "DT", "EV", "GC", "GN", "HA", "KM", "ON", "OT", "PB", "PC", "PW", "RE", "RO", "RU", "SO",
+    "TM", "US", "WR", "WT",
+    "AB", "AE", "AW", "PL",
+    "B", "KO", "MN", "W",
+    "BM", "DO", "IT", "TE",
+    "C", "DM", "GB", "GW", "HO", "N", "UC", "V",
+    "AR", "CR", "DD", "LB", "LN", "MA", "SL", "SQ", "TR",
+    "BL", "OB", "OW", "WL",
+    "FG", "PM", "VW",
+    "TB", "TW"
+);
+
+/// Returns `ParseError::expected` of a value of property id longer than `ParseOptions::max_value_length`.
+fn value_length_message(id: &str) -> &'static str {
+    PROPERTIES.iter().position(|p| p.id == id).map_or("property value within max_value_length", |i| VALUE_LENGTH_MESSAGES[i])
+}
+
+fn error_at(sgf_str: &str, offset: usize, expected: &'static str) -> ParseError {
+    let (line, column) = line_column(sgf_str, offset);
+    ParseError {
+        line,
+        column,
+        offset,
+        expected: [expected].iter().cloned().collect::<HashSet<_>>(),
+    }
+}
+
+/// Returns a warning at offset, whose line and column are set by `set_line_columns` later.
+fn warning_at(offset: usize, message: &str) -> ParseWarning {
+    ParseWarning { offset, line: 0, column: 0, message: message.to_string() }
+}
+
+/// Sets the lines and the columns of warnings sorted by offset in one pass over sgf_str.
+fn set_line_columns(sgf_str: &str, warnings: &mut [ParseWarning]) {
+    let (mut last, mut line, mut column) = (0, 1, 1);
+    for w in warnings.iter_mut() {
+        for c in sgf_str[last..w.offset].chars() {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        last = w.offset;
+        w.line = line;
+        w.column = column;
+    }
+}
+
+fn line_column(sgf_str: &str, offset: usize) -> (usize, usize) {
+    let before = &sgf_str[..offset];
+    (before.matches('\n').count() + 1, before.chars().rev().take_while(|&c| c != '\n').count() + 1)
+}
+
+/// Returns (offset of '[', offset of '\\') of backslashes which seem to escape the ']' of a value by mistake,
+/// that is, the ']' followed by the rest could terminate the value
+/// while the value escaped by them continues with '[' or up to the end of the input.
+///
+/// The input is scanned once, taking each of the backslashes as a literal one as soon as it is found,
+/// so the value ends at its ']' and the scan goes on after it.
+fn runaway_backslashes(sgf_str: &str) -> Vec<(usize, usize)> {
+    let bytes = sgf_str.as_bytes();
+    // runs_away[i] is true if the rest of a value from i contains '[' or has no ']'
+    let mut runs_away = vec![true; bytes.len() + 1];
+    for i in (0..bytes.len()).rev() {
+        runs_away[i] = match bytes[i] {
+            b'\\' => runs_away[(i + 2).min(bytes.len())],
+            b']' => false,
+            b'[' => true,
+            _ => runs_away[i + 1],
+        };
+    }
+    let mut result = Vec::new();
+    let mut value_start = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match (value_start, bytes[i]) {
+            (Some(start), b'\\') => {
+                if bytes.get(i + 1) == Some(&b']') && runs_away[i + 2] && can_follow_value(&sgf_str[i + 2..]) {
+                    result.push((start, i));
+                    value_start = None;
+                }
+                i += 1;
+            },
+            (Some(_), b']') => value_start = None,
+            (None, b'[') => value_start = Some(i),
+            _ => (),
+        }
+        i += 1;
+    }
+    result
+}
+
+/// Returns true if rest begins with what can follow a property value except another value.
+fn can_follow_value(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    match rest.chars().next() {
+        None | Some(';') | Some('(') | Some(')') => true,
+        Some(c) if c.is_ascii_alphabetic() => rest.trim_start_matches(|c: char| c.is_ascii_alphabetic()).trim_start().starts_with('['),
+        _ => false,
+    }
+}
+
+/// Returns sgf_str with each of backslashes escaped so that it is a literal one.
+fn escape_backslashes(sgf_str: &str, backslashes: &[(usize, usize)]) -> String {
+    let mut result = String::with_capacity(sgf_str.len() + backslashes.len());
+    let mut last = 0;
+    for &(_, i) in backslashes {
+        result.push_str(&sgf_str[last..i]);
+        result.push('\\');
+        last = i;
+    }
+    result.push_str(&sgf_str[last..]);
+    result
+}
+
+/// Returns the offset in sgf_str of offset in sgf_str with backslashes escaped by `escape_backslashes`.
+fn offset_before_escaping(offset: usize, backslashes: &[(usize, usize)]) -> usize {
+    // the k-th backslash is at i + k in the escaped one, which increases with k
+    let (mut low, mut high) = (0, backslashes.len());
+    while low < high {
+        let k = (low + high) / 2;
+        if backslashes[k].1 + k < offset {
+            low = k + 1;
+        } else {
+            high = k;
+        }
+    }
+    offset - low
+}
+
+/// Moves error, that of parsing sgf_str, to the start of the first runaway value
+/// if the parse with backslashes escaped goes further, that is, it succeeds or fails at an offset in sgf_str
+/// after error.
+fn relocate_error(sgf_str: &str, error: ParseError, backslashes: &[(usize, usize)], escaped_error: Option<usize>) -> ParseError {
+    let further = escaped_error.map_or(true, |offset| offset > error.offset);
+    match backslashes.first() {
+        Some(&(start, i)) if further && i < error.offset => error_at(sgf_str, start, "] not escaped by a trailing backslash"),
+        _ => error,
+    }
+}
+
+/// Moves an error to the start of the first value before it which runs away past its ']'
+/// because of a trailing backslash, that is, taking the backslash as a literal one lets the parse go further.
+/// It takes a scan and a parse of sgf_str.
+pub(crate) fn locate_runaway_value(sgf_str: &str, error: ParseError) -> ParseError {
+    let backslashes = runaway_backslashes(sgf_str);
+    if backslashes.is_empty() {
+        return error;
+    }
+    let escaped_error = collection(&escape_backslashes(sgf_str, &backslashes)).err()
+        .map(|e| offset_before_escaping(e.offset, &backslashes));
+    relocate_error(sgf_str, error, &backslashes, escaped_error)
+}
+
+/// A replacement of `start..end` of the input with `text`.
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// A property found by `repair_structure`.
+struct ScannedProperty {
+    ident_start: usize,
+    ident_end: usize,
+    values_start: usize,
+    values_end: usize,
+}
+
+/// Parses sgf_str repairing its structure by `repair_structure`.
+/// If it fails, parses it once more with the backslashes of `runaway_backslashes` taken as literal ones
+/// before repairing its structure, since the values running away hide the structure.
+fn parse_lenient(sgf_str: &str, warnings: &mut Vec<ParseWarning>) -> ParseResult<SgfCollection> {
+    let (repaired, origins) = repair_structure(sgf_str, warnings);
+    let error = match collection(&repaired) {
+        Ok(c) => {
+            warnings.sort_by_key(|w| w.offset);
+            set_line_columns(sgf_str, warnings);
+            return Ok(c);
+        },
+        Err(e) => {
+            let offset = origins.get(e.offset).cloned().unwrap_or(sgf_str.len());
+            let (line, column) = line_column(sgf_str, offset);
+            ParseError { line, column, offset, expected: e.expected }
+        },
+    };
+    let backslashes = runaway_backslashes(sgf_str);
+    if backslashes.is_empty() {
+        return Err(error);
+    }
+    let escaped = escape_backslashes(sgf_str, &backslashes);
+    let mut escaped_warnings = Vec::new();
+    let (repaired, origins) = repair_structure(&escaped, &mut escaped_warnings);
+    // the offset in sgf_str of an offset in repaired
+    let origin = |offset: usize| offset_before_escaping(origins.get(offset).cloned().unwrap_or(escaped.len()), &backslashes);
+    match collection(&repaired) {
+        Ok(c) => {
+            warnings.clear();
+            warnings.extend(escaped_warnings.into_iter().map(|w| warning_at(offset_before_escaping(w.offset, &backslashes), &w.message)));
+            for &(_, i) in backslashes.iter() {
+                warnings.push(warning_at(i, "trailing backslash of a value taken as a literal"));
+            }
+            warnings.sort_by_key(|w| w.offset);
+            set_line_columns(sgf_str, warnings);
+            Ok(c)
+        },
+        Err(e) => Err(relocate_error(sgf_str, error, &backslashes, Some(origin(e.offset)))),
+    }
+}
+
+/// Repairs lowercase identifiers, duplicated properties, empty variations and trailing text.
+/// Returns the repaired string and the offset in sgf_str of each byte of it.
+fn repair_structure(sgf_str: &str, warnings: &mut Vec<ParseWarning>) -> (String, Vec<usize>) {
+    let bytes = sgf_str.as_bytes();
+    let mut edits = Vec::new();
+    let mut properties: Vec<ScannedProperty> = Vec::new();
+    let mut depth = 0;
+    let mut last_close = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'[' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b']' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                if let Some(p) = properties.last_mut() {
+                    if p.values_end == p.ident_end {
+                        p.values_start = start;
+                    }
+                    p.values_end = (i + 1).min(bytes.len());
+                }
+            },
+            c if c.is_ascii_alphabetic() => {
+                let start = i;
+                while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                    i += 1;
+                }
+                properties.push(ScannedProperty { ident_start: start, ident_end: i, values_start: i, values_end: i });
+                continue;
+            },
+            c @ b';' | c @ b'(' | c @ b')' => {
+                repair_properties(sgf_str, &properties, &mut edits, warnings);
+                properties.clear();
+                match c {
+                    b'(' => {
+                        let next = i + 1 + sgf_str[i + 1..].len() - sgf_str[i + 1..].trim_start().len();
+                        if bytes.get(next) == Some(&b')') {
+                            edits.push(Edit { start: i, end: next + 1, text: String::new() });
+                            warnings.push(warning_at(i, "empty variation removed"));
+                            i = next;
+                        } else {
+                            depth += 1;
+                        }
+                    },
+                    b')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            last_close = Some(i);
+                        }
+                    },
+                    _ => (),
+                }
+            },
+            _ => (),
+        }
+        i += 1;
+    }
+    repair_properties(sgf_str, &properties, &mut edits, warnings);
+    if let Some(close) = last_close {
+        let rest = &sgf_str[close + 1..];
+        if !rest.trim().is_empty() && !rest.contains('(') {
+            edits.retain(|e| e.start <= close);
+            edits.push(Edit { start: close + 1, end: sgf_str.len(), text: String::new() });
+            warnings.push(warning_at(close + 1, "text after the last game tree ignored"));
+        }
+    }
+    // stable, so insertions at the same offset keep their order and precede a removal starting there
+    edits.sort_by_key(|e| (e.start, e.end));
+    let mut repaired = String::with_capacity(sgf_str.len());
+    let mut origins = Vec::with_capacity(sgf_str.len());
+    let mut position = 0;
+    for e in edits {
+        repaired.push_str(&sgf_str[position..e.start]);
+        origins.extend(position..e.start);
+        repaired.push_str(&e.text);
+        origins.extend(::std::iter::repeat(e.start).take(e.text.len()));
+        position = e.end;
+    }
+    repaired.push_str(&sgf_str[position..]);
+    origins.extend(position..sgf_str.len());
+    (repaired, origins)
+}
+
+/// Adds edits for lowercase identifiers and duplicated properties of a node.
+fn repair_properties(sgf_str: &str, properties: &[ScannedProperty], edits: &mut Vec<Edit>, warnings: &mut Vec<ParseWarning>) {
+    // the end of the values of each kept identifier
+    let mut kept: HashMap<String, usize> = HashMap::new();
+    for p in properties {
+        if p.values_end == p.ident_end {
+            continue; // not a property, left to the parser
+        }
+        let ident = &sgf_str[p.ident_start..p.ident_end];
+        let uppercase = ident.chars().filter(|c| c.is_ascii_uppercase()).collect::<String>();
+        if uppercase.is_empty() {
+            edits.push(Edit { start: p.ident_start, end: p.values_end, text: String::new() });
+            warnings.push(warning_at(p.ident_start, "property without uppercase letters removed"));
+        } else if let Some(&end) = kept.get(&uppercase) {
+            edits.push(Edit { start: end, end, text: sgf_str[p.values_start..p.values_end].to_string() });
+            edits.push(Edit { start: p.ident_start, end: p.values_end, text: String::new() });
+            warnings.push(warning_at(p.ident_start, "values of a duplicated property merged"));
+        } else {
+            if uppercase != ident {
+                edits.push(Edit { start: p.ident_start, end: p.ident_end, text: uppercase.clone() });
+                warnings.push(warning_at(p.ident_start, "lowercase letters of an identifier removed"));
+            }
+            kept.insert(uppercase, p.values_end);
+        }
+    }
+}
+
+fn normalize_values(node: &mut SgfNode, game: usize, path: &mut Vec<usize>, report: &mut ParseReport) {
+    for (id, values) in node.iter_values_mut() {
+        let normalizable = match property_spec(id) {
+            Some(spec) => match spec.value_type {
+                ValueType::Text | ValueType::SimpleTextSimpleText | ValueType::ListOfPointSimpleText |
+                ValueType::NoneOrNumberSimpleText => false,
+                ValueType::SimpleText => id == "RE" || id == "DT",
+                _ => true,
+            },
+            None => false,
+        };
+        if !normalizable {
+            continue;
+        }
+        for v in values.iter_mut() {
+            let trimmed = v.trim().to_string();
+            if trimmed != *v {
+                report.normalizations.push(Normalization {
+                    game,
+                    path: path.clone(),
+                    id: id.clone(),
+                    original: v.to_string(),
+                    normalized: trimmed.clone(),
+                });
+                *v = trimmed.into();
+            }
+        }
+    }
+    for (i, child) in node.children.iter_mut().enumerate() {
+        path.push(i);
+        normalize_values(child, game, path, report);
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_values() {
+        let options = ParseOptions { normalize_values: true, ..Default::default() };
+        let (c, report) = SgfCollection::from_sgf_with_options("(;FF[4]SZ[ 19 ]C[  comment ];RE[B+R\n])", &options).unwrap();
+        assert_eq!(c[0].get_number("SZ").unwrap(), 19);
+        assert_eq!(c[0].get_text("C").unwrap(), "  comment ");
+        assert_eq!(c[0].children[0].get_simple_text("RE").unwrap(), "B+R");
+        assert_eq!(report.normalizations, vec![
+            Normalization { game: 0, path: vec![], id: "SZ".to_string(), original: " 19 ".to_string(), normalized: "19".to_string() },
+            Normalization { game: 0, path: vec![0], id: "RE".to_string(), original: "B+R\n".to_string(), normalized: "B+R".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_without_normalize_values() {
+        let (c, report) = SgfCollection::from_sgf_with_options("(;FF[4]SZ[ 19 ])", &ParseOptions::default()).unwrap();
+        assert!(c[0].get_number("SZ").is_err());
+        assert!(report.normalizations.is_empty());
+    }
+
+    #[test]
+    fn test_max_value_length() {
+        let options = ParseOptions { max_value_length: 10, ..Default::default() };
+        let sgf = "(;FF[4]\nPB[0123456789];C[0123456789\\]0123456789])";
+        match SgfCollection::from_sgf_with_options(sgf, &options) {
+            Err(e) => {
+                assert!(sgf[e.offset..].starts_with("C["));
+                assert_eq!((e.line, e.column), (2, 16));
+            },
+            Ok(_) => panic!("oversized value was accepted"),
+        }
+        assert!(SgfCollection::from_sgf_with_options(sgf, &ParseOptions::default()).is_ok());
+        // an unterminated value is stopped at the limit
+        let options = ParseOptions { max_value_length: 5, ..Default::default() };
+        assert_eq!(SgfCollection::from_sgf_with_options("(;C[0123456789", &options).unwrap_err().offset, 2);
+        // the limit is inclusive and the error names the identifier and the limit
+        assert!(SgfCollection::from_sgf_with_options("(;C[01234])", &options).is_ok());
+        assert!(SgfCollection::from_sgf_with_options("(;AB[aa][bb]C[0\\]23])", &options).is_ok());
+        let e = SgfCollection::from_sgf_with_options("(;AB[aa][bb]C[012345])", &options).unwrap_err();
+        assert_eq!(e.offset, 12);
+        assert_eq!(e.expected.iter().cloned().collect::<Vec<_>>(), vec!["C value within max_value_length"]);
+        let e = SgfCollection::from_sgf_with_options("(;XY[012345])", &options).unwrap_err();
+        assert_eq!(e.expected.iter().cloned().collect::<Vec<_>>(), vec!["property value within max_value_length"]);
+        for (spec, message) in PROPERTIES.iter().zip(VALUE_LENGTH_MESSAGES) {
+            assert_eq!(message.split(' ').next(), Some(spec.id));
+        }
+        assert_eq!(PROPERTIES.len(), VALUE_LENGTH_MESSAGES.len());
+        let e = SgfCollection::from_sgf_with_options("(;AB[aa][bb]C[0123\\]])", &options).unwrap_err();
+        assert_eq!(e.offset, 12);
+    }
+
+    #[test]
+    fn test_from_sgf_with_stats() {
+        let (c, stats) = SgfCollection::from_sgf_with_stats("(;FF[4];B[aa];W[bb])(;FF[4](;B[aa])(;B[bb];W[cc];B[dd]))").unwrap();
+        assert_eq!(c.len(), 2);
+        assert_eq!(stats, ParseStats { games: 2, nodes: 8, max_depth: 4 });
+        assert!(SgfCollection::from_sgf_with_stats("(;FF[4]").is_err());
+    }
+
+    #[test]
+    fn test_trailing_backslash() {
+        let sgf = "(;FF[4]PB[foo];B[aa]C[path C:\\])";
+        let options = ParseOptions { lenient: true, ..Default::default() };
+        let (c, report) = SgfCollection::from_sgf_with_options(sgf, &options).unwrap();
+        assert_eq!(c[0].get_simple_text("PB").unwrap(), "foo");
+        assert_eq!(c[0].children[0].get_point("B").unwrap(), "aa");
+        assert_eq!(c[0].children[0].get_text("C").unwrap(), "path C:\\");
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(&sgf[report.warnings[0].offset..], "\\])");
+        let sgf = "(;FF[4]C[path C:\\])";
+        let (c, _) = SgfCollection::from_sgf_with_options(sgf, &options).unwrap();
+        assert_eq!(c[0].get_text("C").unwrap(), "path C:\\");
+        let e = SgfCollection::from_sgf(sgf).unwrap_err();
+        assert!(sgf[e.offset..].starts_with("[path"));
+        // an escaped backslash ends the value
+        assert_eq!(SgfCollection::from_sgf("(;C[C:\\\\])").unwrap()[0].get_text("C").unwrap(), "C:\\");
+        // an escaped bracket in a valid input is kept
+        let (c, report) = SgfCollection::from_sgf_with_options("(;C[a\\]b]PB[x])", &options).unwrap();
+        assert_eq!(c[0].get_text("C").unwrap(), "a]b");
+        assert!(report.warnings.is_empty());
+        let (c, report) = SgfCollection::from_sgf_with_options("(;C[x\\]B[aa]PB[y])", &options).unwrap();
+        assert_eq!(c[0].get_text("C").unwrap(), "x]B[aa");
+        assert_eq!(c[0].get_simple_text("PB").unwrap(), "y");
+        assert!(report.warnings.is_empty());
+        // an error unrelated to a backslash stays where it is
+        let sgf = "(;C[a\\]PB[x];B[aa]?)";
+        assert!(sgf[SgfCollection::from_sgf(sgf).unwrap_err().offset..].starts_with("?"));
+    }
+
+    #[test]
+    fn test_runaway_values_in_linear_time() {
+        let options = ParseOptions { lenient: true, ..Default::default() };
+        let n = 20_000;
+        let start = ::std::time::Instant::now();
+        let unterminated = format!("(;FF[4]C[{}", "a\\];".repeat(n));
+        assert!(SgfCollection::from_sgf(&unterminated).is_err());
+        assert!(SgfCollection::from_sgf_with_options(&unterminated, &options).is_err());
+        let games = "(;FF[4]C[C:\\])".repeat(n);
+        let e = SgfCollection::from_sgf(&games).unwrap_err();
+        assert_eq!(e.offset, 8);
+        let (c, report) = SgfCollection::from_sgf_with_options(&games, &options).unwrap();
+        assert_eq!(c.len(), n);
+        assert_eq!(c[n - 1].get_text("C").unwrap(), "C:\\");
+        assert_eq!(report.warnings.len(), n);
+        assert_eq!(report.warnings[n - 1].column, games.len() - 2);
+        // a quadratic scan takes minutes
+        assert!(start.elapsed().as_secs() < 10);
+    }
+
+    #[test]
+    fn test_lenient_structure() {
+        let sgf = "(;FF[3]CoPyright[me]White[foo]\nAB[aa]AB[bb][cc]size[19]()(;B[dd]C[a]C[b])()\n)\nDownloaded from example.com";
+        let options = ParseOptions { lenient: true, ..Default::default() };
+        let (c, report) = SgfCollection::from_sgf_with_options(sgf, &options).unwrap();
+        assert_eq!(c.len(), 1);
+        assert_eq!(c[0].get_simple_text("CP").unwrap(), "me");
+        assert_eq!(c[0].get_simple_text("W").unwrap(), "foo");
+        assert_eq!(c[0].get_points("AB").unwrap(), vec!["aa", "bb", "cc"]);
+        assert!(c[0].iter().all(|(id, _)| !id.is_empty() && id != "size"));
+        assert_eq!(c[0].children.len(), 1);
+        assert_eq!(c[0].children[0].values_iter("C").collect::<Vec<_>>(), vec!["a", "b"]);
+        let messages = report.warnings.iter().map(|w| (w.line, &w.message[..])).collect::<Vec<_>>();
+        assert_eq!(messages, vec![
+            (1, "lowercase letters of an identifier removed"),
+            (1, "lowercase letters of an identifier removed"),
+            (2, "values of a duplicated property merged"),
+            (2, "property without uppercase letters removed"),
+            (2, "empty variation removed"),
+            (2, "values of a duplicated property merged"),
+            (2, "empty variation removed"),
+            (3, "text after the last game tree ignored"),
+        ]);
+        assert!(sgf[report.warnings[2].offset..].starts_with("AB[bb]"));
+        assert_eq!(report.warnings[7].column, 2);
+        assert!(SgfCollection::from_sgf(sgf).is_err());
+        // strict mode is unchanged and a valid input has no warnings
+        let (c, report) = SgfCollection::from_sgf_with_options("(;FF[4]C[a];B[aa])", &options).unwrap();
+        assert_eq!(c, SgfCollection::from_sgf("(;FF[4]C[a];B[aa])").unwrap());
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_lenient_identifiers_and_duplicates() {
+        let options = ParseOptions { lenient: true, ..Default::default() };
+        let (c, _) = SgfCollection::from_sgf_with_options("(;CoPyright[x]FF[3])", &options).unwrap();
+        assert_eq!(c[0].iter().map(|(id, _)| &id[..]).collect::<Vec<_>>(), vec!["CP", "FF"]);
+        assert_eq!(c[0].get_simple_text("CP").unwrap(), "x");
+        let (c, report) = SgfCollection::from_sgf_with_options("(;FF[4];AB[aa]AB[bb])", &options).unwrap();
+        assert_eq!(c[0].children[0].get_points("AB").unwrap(), vec!["aa".to_string(), "bb".to_string()]);
+        assert_eq!(report.warnings.len(), 1);
+        assert!(SgfCollection::from_sgf("(;CoPyright[x]FF[3])").is_err());
+        assert!(SgfCollection::from_sgf("(;FF[4];AB[aa]AB[bb])").is_err());
+        let (c, report) = SgfCollection::from_sgf_with_options("(;C[a]C[b]C[c]PB[x])", &options).unwrap();
+        assert_eq!(c[0].values_iter("C").collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(c[0].get_simple_text("PB").unwrap(), "x");
+        assert_eq!(report.warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_lenient_error_offset() {
+        let options = ParseOptions { lenient: true, ..Default::default() };
+        let sgf = "(;FF[4]CoPyright[me];B[aa]?)";
+        let e = SgfCollection::from_sgf_with_options(sgf, &options).unwrap_err();
+        assert!(sgf[e.offset..].starts_with("?"));
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Search of local stone patterns in games
+
+use std::collections::HashSet;
+use sgf_node::*;
+use board::{Board, BoardFeature};
+use symmetry::Symmetry;
+
+/// A point of a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cell {
+    /// 'X'
+    Black,
+    /// 'O'
+    White,
+    /// '.'
+    Empty,
+    /// '?', any of the above
+    Any,
+}
+
+/// A rectangular stone pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    width: u8,
+    height: u8,
+    /// Cells in row-major order
+    cells: Vec<Cell>,
+}
+
+impl Pattern {
+    /// Parses a pattern from an ASCII grid of 'X'(black), 'O'(white), '.'(empty) and '?'(any).
+    /// Rows are separated by newlines and spaces in a row are ignored, so "X O .\n. X ?" is a 3x2 pattern.
+    /// Blank lines are ignored.
+    /// Returns InvalidPattern for other characters, rows of different lengths or an empty grid.
+    pub fn parse(s: &str) -> Result<Pattern, SgfError> {
+        let mut rows = Vec::new();
+        for line in s.lines() {
+            let row = line.chars().filter(|c| !c.is_whitespace()).map(|c| match c {
+                'X' => Ok(Cell::Black),
+                'O' => Ok(Cell::White),
+                '.' => Ok(Cell::Empty),
+                '?' => Ok(Cell::Any),
+                _ => Err(SgfError::InvalidPattern(format!("unknown character '{}'", c))),
+            }).collect::<Result<Vec<_>, _>>()?;
+            if !row.is_empty() {
+                rows.push(row);
+            }
+        }
+        let width = rows.first().map(|r| r.len()).unwrap_or(0);
+        if width == 0 {
+            return Err(SgfError::InvalidPattern("empty".to_string()));
+        }
+        if width > 52 || rows.len() > 52 {
+            return Err(SgfError::InvalidPattern("larger than 52x52".to_string()));
+        }
+        if rows.iter().any(|r| r.len() != width) {
+            return Err(SgfError::InvalidPattern("rows of different lengths".to_string()));
+        }
+        Ok(Pattern { width: width as u8, height: rows.len() as u8, cells: rows.concat() })
+    }
+
+    /// Returns (width, height).
+    pub fn size(&self) -> (u8, u8) {
+        (self.width, self.height)
+    }
+
+    /// Returns the cell at zero-based (column, row).
+    pub fn get(&self, (x, y): (u8, u8)) -> Cell {
+        self.cells[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Returns the pattern transformed by sym, with colors swapped if swap_colors.
+    fn transformed(&self, sym: Symmetry, swap_colors: bool) -> Pattern {
+        let (w, h) = (self.width, self.height);
+        let (tw, th) = match sym {
+            Symmetry::Rotate90 | Symmetry::Rotate270 | Symmetry::Transpose | Symmetry::AntiTranspose => (h, w),
+            _ => (w, h),
+        };
+        let mut cells = vec![Cell::Any; self.cells.len()];
+        for y in 0..h {
+            for x in 0..w {
+                let (tx, ty) = match sym {
+                    Symmetry::Identity => (x, y),
+                    Symmetry::Rotate90 => (h - 1 - y, x),
+                    Symmetry::Rotate180 => (w - 1 - x, h - 1 - y),
+                    Symmetry::Rotate270 => (y, w - 1 - x),
+                    Symmetry::FlipHorizontal => (w - 1 - x, y),
+                    Symmetry::FlipVertical => (x, h - 1 - y),
+                    Symmetry::Transpose => (y, x),
+                    Symmetry::AntiTranspose => (h - 1 - y, w - 1 - x),
+                };
+                cells[ty as usize * tw as usize + tx as usize] = match (self.get((x, y)), swap_colors) {
+                    (Cell::Black, true) => Cell::White,
+                    (Cell::White, true) => Cell::Black,
+                    (cell, _) => cell,
+                };
+            }
+        }
+        Pattern { width: tw, height: th, cells }
+    }
+
+    fn matches(&self, board: &Board, (ax, ay): (u8, u8)) -> bool {
+        (0..self.height).all(|y| (0..self.width).all(|x| {
+            matches!((self.get((x, y)), board.get((ax + x, ay + y))),
+                     (Cell::Any, _) | (Cell::Empty, None) | (Cell::Black, Some('B')) | (Cell::White, Some('W')))
+        }))
+    }
+}
+
+/// An occurrence of a pattern found by `search`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternHit {
+    /// Number of moves played when the pattern appeared. 0 for the position set up before the first move.
+    pub move_number: usize,
+    /// Zero-based (column, row) of the top-left corner of the area matching the transformed pattern
+    pub anchor: (u8, u8),
+    /// Symmetry applied to the pattern
+    pub symmetry: Symmetry,
+    /// True if black and white of the pattern are swapped
+    pub colors_swapped: bool,
+}
+
+/// Searches the pattern in the positions along the main line of the game
+/// under the 8 symmetries and both colorings, and returns hits in the order of their appearance.
+///
+/// A hit is reported when the pattern appears, so an occurrence lasting for several moves is reported once.
+/// Transformations giving the same pattern as an earlier one in the order of `Symmetry::all`,
+/// identity colors first, are skipped. The pattern must be entirely on the board, whose size is SZ of the game.
+/// The board is updated incrementally and only the areas around changed points are checked after each node.
+/// Returns the error of `SgfNode::board_spec` or UnsupportedBoard for Reconstruction if SZ is not supported.
+pub fn search(game: &SgfNode, pat: &Pattern) -> Result<Vec<PatternHit>, SgfError> {
+    let mut variants: Vec<(Symmetry, bool, Pattern)> = Vec::new();
+    for &swap in [false, true].iter() {
+        for &sym in Symmetry::all().iter() {
+            let p = pat.transformed(sym, swap);
+            if variants.iter().all(|v| v.2 != p) {
+                variants.push((sym, swap, p));
+            }
+        }
+    }
+    let size = game.board_size(BoardFeature::Reconstruction)?;
+    let mut board = Board::new(size)?;
+    let mut current: HashSet<(usize, (u8, u8))> = HashSet::new();
+    let mut hits = Vec::new();
+    let mut move_number = 0;
+    let mut node = game;
+    loop {
+        let before = board.clone();
+        if board.apply_node(node).is_some() {
+            move_number += 1;
+        }
+        let changed = (0..size.1).flat_map(|y| (0..size.0).map(move |x| (x, y)))
+            .filter(|&p| board.get(p) != before.get(p))
+            .collect::<Vec<_>>();
+        for (i, &(symmetry, colors_swapped, ref p)) in variants.iter().enumerate() {
+            if p.width > size.0 || p.height > size.1 {
+                continue;
+            }
+            let mut anchors = changed.iter().flat_map(|&(cx, cy)| {
+                let xs = cx.saturating_sub(p.width - 1)..=cx.min(size.0 - p.width);
+                let ys = cy.saturating_sub(p.height - 1)..=cy.min(size.1 - p.height);
+                ys.flat_map(move |y| xs.clone().map(move |x| (x, y)))
+            }).collect::<Vec<_>>();
+            anchors.sort();
+            anchors.dedup();
+            for anchor in anchors {
+                if p.matches(&board, anchor) {
+                    if current.insert((i, anchor)) {
+                        hits.push(PatternHit { move_number, anchor, symmetry, colors_swapped });
+                    }
+                } else {
+                    current.remove(&(i, anchor));
+                }
+            }
+        }
+        if node.children.is_empty() {
+            return Ok(hits);
+        }
+        node = &node.children[0];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_parse() {
+        let p = Pattern::parse("X O .\n. X ?\n").unwrap();
+        assert_eq!(p.size(), (3, 2));
+        assert_eq!(p.get((1, 0)), Cell::White);
+        assert_eq!(p.get((2, 1)), Cell::Any);
+        assert!(Pattern::parse("X O\nX").is_err());
+        assert!(Pattern::parse("X B").is_err());
+        assert!(Pattern::parse("").is_err());
+    }
+
+    #[test]
+    fn test_search_rotated() {
+        let pat = Pattern::parse("X O .\n. X ?\n. . .").unwrap();
+        // the pattern rotated by 90 degrees is ". . X / . X O / . ? ." at (4, 4)
+        let game = &SgfCollection::from_sgf("(;FF[4]SZ[9];B[ge];W[aa];B[cc];W[gf];B[ff];W[ba];B[ab])").unwrap()[0];
+        let expected = PatternHit { move_number: 5, anchor: (4, 4), symmetry: Symmetry::Rotate90, colors_swapped: false };
+        let hits = search(game, &pat).unwrap();
+        assert!(hits.contains(&expected));
+        assert!(hits.iter().all(|h| h.move_number == 5 && !h.colors_swapped));
+        let swapped = Pattern::parse("O X .\n. O ?\n. . .").unwrap();
+        let hits = search(game, &swapped).unwrap();
+        assert!(hits.contains(&PatternHit { colors_swapped: true, ..expected }));
+        assert!(search(game, &Pattern::parse("X X X").unwrap()).unwrap().is_empty());
+        let large = &SgfCollection::from_sgf("(;FF[4]SZ[300];B[aa])").unwrap()[0];
+        assert!(matches!(search(large, &pat), Err(SgfError::OutOfRange(_))));
+    }
+}
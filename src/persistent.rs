@@ -0,0 +1,126 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Persistent trees with copy-on-write for concurrent readers (feature "persistent")
+
+use std::mem;
+use std::sync::Arc;
+use sgf_node::*;
+
+/// A node of a persistent tree. Children are shared between trees by Arc.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersistentNode {
+    /// properties of the node, whose children are always empty
+    node: SgfNode,
+    pub children: Vec<Arc<PersistentNode>>,
+}
+
+impl PersistentNode {
+    fn from_node(mut node: SgfNode) -> PersistentNode {
+        let children = mem::take(&mut node.children).into_iter().map(|c| Arc::new(PersistentNode::from_node(c))).collect();
+        PersistentNode { node, children }
+    }
+
+    /// Returns the properties of the node as an SgfNode without children. Use `children` for its children.
+    pub fn node(&self) -> &SgfNode {
+        &self.node
+    }
+
+    /// Edits the properties of the node by f.
+    /// f gets the node without children, and children which f adds are appended as new variations.
+    pub fn edit_properties<F: FnOnce(&mut SgfNode)>(&mut self, f: F) {
+        f(&mut self.node);
+        let added = mem::take(&mut self.node.children);
+        self.children.extend(added.into_iter().map(|c| Arc::new(PersistentNode::from_node(c))));
+    }
+
+    /// Returns the descendant at path.
+    pub fn descendant(&self, path: &[usize]) -> Option<&PersistentNode> {
+        let mut node = self;
+        for &i in path {
+            node = node.children.get(i)?;
+        }
+        Some(node)
+    }
+
+    /// Converts the tree into SgfNode, cloning all nodes.
+    pub fn to_sgf_node(&self) -> SgfNode {
+        let mut node = self.node.clone();
+        node.children = self.children.iter().map(|c| c.to_sgf_node()).collect();
+        node
+    }
+}
+
+/// An immutable snapshot of a tree, which can be sent to other threads.
+#[derive(Debug, Clone)]
+pub struct SgfSnapshot {
+    root: Arc<PersistentNode>,
+}
+
+impl SgfSnapshot {
+    pub fn root(&self) -> &PersistentNode {
+        &self.root
+    }
+}
+
+/// A tree which takes snapshots in constant time.
+///
+/// Editing through `node_mut` copies only the nodes on the path which are shared with snapshots,
+/// so snapshots keep the tree at the time they were taken and untouched subtrees stay shared.
+/// SgfNode owns its children and can't share them, so convert a game into a PersistentTree once by `new`
+/// and edit it through the tree to take snapshots of it.
+#[derive(Debug, Clone)]
+pub struct PersistentTree {
+    root: Arc<PersistentNode>,
+}
+
+impl PersistentTree {
+    pub fn new(root: SgfNode) -> PersistentTree {
+        PersistentTree { root: Arc::new(PersistentNode::from_node(root)) }
+    }
+
+    pub fn root(&self) -> &PersistentNode {
+        &self.root
+    }
+
+    /// Returns a snapshot of the current tree.
+    pub fn snapshot(&self) -> SgfSnapshot {
+        SgfSnapshot { root: self.root.clone() }
+    }
+
+    /// Returns the node at path for editing after copying nodes on the path shared with snapshots.
+    /// Returns None if path doesn't exist.
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut PersistentNode> {
+        self.root.descendant(path)?;
+        let mut node = Arc::make_mut(&mut self.root);
+        for &i in path {
+            node = Arc::make_mut(&mut node.children[i]);
+        }
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_copy_on_write() {
+        let c = SgfCollection::from_sgf("(;FF[4]C[old](;B[aa];W[bb])(;B[cc]C[untouched]))").unwrap();
+        let mut tree = PersistentTree::new(c[0].clone());
+        let snapshot = tree.snapshot();
+        tree.node_mut(&[]).unwrap().edit_properties(|node| { node.set_text("C", "new".to_string()); });
+        tree.node_mut(&[0, 0]).unwrap().edit_properties(|node| { node.set_point("W", "dd".to_string()); });
+        assert_eq!(snapshot.root().node().get_text("C").unwrap(), "old");
+        assert_eq!(snapshot.root().to_sgf_node(), c[0]);
+        assert_eq!(tree.root().node().get_text("C").unwrap(), "new");
+        assert_eq!(tree.root().descendant(&[0, 0]).unwrap().node().get_point("W").unwrap(), "dd");
+        assert_eq!(Arc::strong_count(&tree.root().children[1]), 2);
+        assert!(Arc::ptr_eq(&tree.root().children[1], &snapshot.root().children[1]));
+        assert!(tree.node_mut(&[2]).is_none());
+        tree.node_mut(&[1]).unwrap().edit_properties(|node| { node.add_child(SgfNode::new(vec![("W".to_string(), vec!["ee".to_string()])])); });
+        assert!(tree.root().children[1].node().children.is_empty());
+        assert_eq!(tree.root().descendant(&[1, 0]).unwrap().node().get_point("W").unwrap(), "ee");
+        assert_eq!(snapshot.root().children[1].children.len(), 0);
+    }
+}
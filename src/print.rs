@@ -0,0 +1,91 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Print layout of diagrams(PM and FG)
+
+use sgf_node::*;
+
+/// How move numbers are printed, the value of PM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintMode {
+    /// PM[0], no move numbers
+    NoNumbers,
+    /// PM[1], move numbers as they are. The default when no PM is in effect.
+    Numbered,
+    /// PM[2], move numbers modulo 100
+    Modulo100,
+}
+
+/// A figure starting at a node, the value of FG.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Figure {
+    /// Flags of the figure. None for FG[], which means the default settings of the application.
+    ///
+    /// * 0x0001: coordinates off
+    /// * 0x0002: diagram name off
+    /// * 0x0004: list moves not shown in the diagram
+    /// * 0x0100: captured stones removed from the diagram
+    /// * 0x0200: hoshi dots off
+    /// * 0x1000: flags 0x0001 to 0x0200 ignored and the application defaults used
+    pub flags: Option<SgfNumber>,
+    /// Name of the diagram
+    pub name: Option<SgfSimpleText>,
+}
+
+/// Properties for printing a node, which diagram generators consult to number moves in each figure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintLayout {
+    /// The effective PM. It is inherited from ancestors until set again.
+    pub print_mode: PrintMode,
+    /// Some if a new figure starts at the node. FG is not inherited.
+    pub figure: Option<Figure>,
+}
+
+impl SgfNode {
+    /// Returns the print layout of the node reached from the node by path of child indices.
+    /// Since PM is inherited, the node should be the root of the game.
+    /// Returns InvalidPath if path doesn't lead to a node,
+    /// and OutOfRange or ParseError if the effective PM or FG of the node is invalid.
+    pub fn print_layout(&self, path: &[usize]) -> Result<PrintLayout, SgfError> {
+        let mut node = self;
+        for &i in path {
+            node = node.children.get(i).ok_or_else(|| SgfError::InvalidPath(path.to_vec()))?;
+        }
+        let print_mode = match self.effective_property(path, "PM").map(|v| v[0].trim()) {
+            None => PrintMode::Numbered,
+            Some("0") => PrintMode::NoNumbers,
+            Some("1") => PrintMode::Numbered,
+            Some("2") => PrintMode::Modulo100,
+            Some(v) => return Err(SgfError::OutOfRange(v.to_string())),
+        };
+        let figure = match node.get_property("FG") {
+            Ok(v) if v[0].is_empty() => Some(Figure { flags: None, name: None }),
+            Ok(_) => {
+                let (flags, name) = node.get_number_simple_text("FG")?;
+                Some(Figure { flags: Some(flags), name: Some(name) })
+            },
+            Err(_) => None,
+        };
+        Ok(PrintLayout { print_mode, figure })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_layout() {
+        let node = &SgfCollection::from_sgf("(;FF[4]PM[2]FG[];B[aa];W[bb]FG[257:Figure 2]PM[0];B[cc]PM[])").unwrap()[0];
+        let layout = node.print_layout(&[]).unwrap();
+        assert_eq!(layout, PrintLayout { print_mode: PrintMode::Modulo100, figure: Some(Figure { flags: None, name: None }) });
+        assert_eq!(node.print_layout(&[0]).unwrap(), PrintLayout { print_mode: PrintMode::Modulo100, figure: None });
+        let layout = node.print_layout(&[0, 0]).unwrap();
+        assert_eq!(layout.print_mode, PrintMode::NoNumbers);
+        assert_eq!(layout.figure, Some(Figure { flags: Some(257), name: Some("Figure 2".to_string()) }));
+        assert_eq!(node.print_layout(&[0, 0, 0]).unwrap().print_mode, PrintMode::Numbered);
+        assert!(node.print_layout(&[1]).is_err());
+        let node = &SgfCollection::from_sgf("(;FF[4]PM[3])").unwrap()[0];
+        assert!(node.print_layout(&[]).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Splitting of problem collections
+
+use sgf_node::*;
+use properties::{property_spec, PropertyType};
+
+impl SgfNode {
+    /// Returns each problem in the tree as a standalone game.
+    ///
+    /// A problem root is a node which has PL and at least one of AB and AW.
+    /// The problem is a copy of the subtree from the problem root, to which root properties
+    /// (FF, GM, SZ, CA and so on) of `self` are added unless the problem root has them.
+    /// Stones set up by ancestors are not included.
+    /// Descendants of a problem root belong to the problem and are not searched for other problems.
+    /// Problems are returned in depth-first order.
+    pub fn extract_problems(&self) -> Vec<SgfNode> {
+        let mut problems = Vec::new();
+        collect_problems(self, self, &mut problems);
+        problems
+    }
+}
+
+fn collect_problems(root: &SgfNode, node: &SgfNode, problems: &mut Vec<SgfNode>) {
+    let has_setup = node.get_property("AB").is_ok() || node.get_property("AW").is_ok();
+    if has_setup && node.get_property("PL").is_ok() {
+        let mut problem = node.clone();
+        for (id, values) in root.iter_values() {
+            let is_root_property = property_spec(id).map(|spec| spec.property_type) == Some(PropertyType::Root);
+            if is_root_property && problem.get_property(id).is_err() {
+                problem.set_property(id, values.clone());
+            }
+        }
+        problems.push(problem);
+        return;
+    }
+    for child in node.children.iter() {
+        collect_problems(root, child, problems);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_problems() {
+        let node = &SgfCollection::from_sgf("(;FF[4]GM[1]SZ[9]GN[collection]
+            (;AB[cc][dc]AW[cd]PL[B]C[problem 1](;B[dd];W[ce])(;B[ce]))
+            (;C[chapter 2](;AW[ee]AB[fe]PL[W];W[ff])(;AB[aa]C[no player]))
+            (;SZ[13]AB[kk]PL[W]))").unwrap()[0];
+        let problems = node.extract_problems();
+        assert_eq!(problems.len(), 3);
+        assert_eq!(problems[0].get_text("C").unwrap(), "problem 1");
+        assert_eq!(problems[0].children.len(), 2);
+        assert_eq!(problems[0].get_number("SZ").unwrap(), 9);
+        assert_eq!(problems[0].get_number("FF").unwrap(), 4);
+        assert!(problems[0].get_simple_text("GN").is_err());
+        assert_eq!(problems[1].get_points("AW").unwrap(), vec!["ee".to_string()]);
+        assert_eq!(problems[1].children[0].get_point("W").unwrap(), "ff");
+        assert_eq!(problems[2].get_number("SZ").unwrap(), 13);
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Storage of property values
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// Longest value in bytes which `PropValue` stores inline.
+pub const INLINE_CAPACITY: usize = 3;
+
+/// A raw property value as it is in SGF, which dereferences to `str`.
+///
+/// Values of INLINE_CAPACITY bytes or shorter, such as points like "pd", colors and most numbers,
+/// are stored inline without allocation. Longer values are Strings.
+/// Comparison, hashing and formatting are those of the `str`.
+#[derive(Clone)]
+pub struct PropValue(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline(u8, [u8; INLINE_CAPACITY]),
+    Heap(String),
+}
+
+impl PropValue {
+    /// Returns the value as `str`.
+    pub fn as_str(&self) -> &str {
+        match self.0 {
+            Repr::Inline(len, ref bytes) => ::std::str::from_utf8(&bytes[..len as usize]).expect("a whole str is stored"),
+            Repr::Heap(ref s) => s,
+        }
+    }
+
+    /// Returns true if the value is stored inline.
+    pub fn is_inline(&self) -> bool {
+        match self.0 {
+            Repr::Inline(..) => true,
+            Repr::Heap(_) => false,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for PropValue {
+    fn from(s: &'a str) -> PropValue {
+        if s.len() <= INLINE_CAPACITY {
+            let mut bytes = [0; INLINE_CAPACITY];
+            bytes[..s.len()].copy_from_slice(s.as_bytes());
+            PropValue(Repr::Inline(s.len() as u8, bytes))
+        } else {
+            PropValue(Repr::Heap(s.to_string()))
+        }
+    }
+}
+
+/// A short String is moved inline and its buffer is freed.
+impl From<String> for PropValue {
+    fn from(s: String) -> PropValue {
+        if s.len() <= INLINE_CAPACITY {
+            PropValue::from(&s[..])
+        } else {
+            PropValue(Repr::Heap(s))
+        }
+    }
+}
+
+impl<'a> From<&'a String> for PropValue {
+    fn from(s: &'a String) -> PropValue {
+        PropValue::from(&s[..])
+    }
+}
+
+impl From<PropValue> for String {
+    fn from(v: PropValue) -> String {
+        match v.0 {
+            Repr::Heap(s) => s,
+            Repr::Inline(..) => v.as_str().to_string(),
+        }
+    }
+}
+
+impl Default for PropValue {
+    fn default() -> PropValue {
+        PropValue::from("")
+    }
+}
+
+impl Deref for PropValue {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for PropValue {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for PropValue {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for PropValue {
+    fn eq(&self, other: &PropValue) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for PropValue {}
+
+impl PartialOrd for PropValue {
+    fn partial_cmp(&self, other: &PropValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PropValue {
+    fn cmp(&self, other: &PropValue) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for PropValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq<str> for PropValue {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for PropValue {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for PropValue {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<PropValue> for str {
+    fn eq(&self, other: &PropValue) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<PropValue> for &str {
+    fn eq(&self, other: &PropValue) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<PropValue> for String {
+    fn eq(&self, other: &PropValue) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl fmt::Display for PropValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for PropValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prop_value() {
+        for &s in ["", "B", "pd", "あ", "abc"].iter() {
+            let v = PropValue::from(s);
+            assert!(v.is_inline());
+            assert_eq!(v, s);
+            assert_eq!(String::from(v.clone()), s);
+            assert_eq!(PropValue::from(s.to_string()), v);
+        }
+        let long = PropValue::from("pd:qe".to_string());
+        assert!(!long.is_inline());
+        assert_eq!(&long[..2], "pd");
+        let mut sorted = vec![PropValue::from("aaa:bb"), PropValue::from("aa")];
+        sorted.sort();
+        assert_eq!(sorted, ["aa", "aaa:bb"]);
+        assert_eq!(format!("{}|{:?}", PropValue::from("pd"), PropValue::from("a\"")), "pd|\"a\\\"\"");
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Property table of SGF FF[4] and Go(GM[1]) specific properties
+
+/// Property type defined in the SGF specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyType {
+    Root,
+    GameInfo,
+    Setup,
+    Move,
+    /// Properties without type, which may appear anywhere.
+    NoType,
+}
+
+/// Value type defined in the SGF specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    None,
+    Number,
+    Real,
+    Double,
+    Color,
+    SimpleText,
+    Text,
+    Point,
+    Move,
+    ListOfPoint,
+    EListOfPoint,
+    /// list of composed point ':' point (AR, LN)
+    ListOfPointPoint,
+    /// list of composed point ':' simpletext (LB)
+    ListOfPointSimpleText,
+    /// number | composed number ':' number (SZ)
+    NumberOrNumberNumber,
+    /// composed simpletext ':' simpletext (AP)
+    SimpleTextSimpleText,
+    /// none | composed number ':' simpletext (FG)
+    NoneOrNumberSimpleText,
+}
+
+/// Definition of a property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PropertySpec {
+    pub id: &'static str,
+    pub property_type: PropertyType,
+    pub value_type: ValueType,
+    /// true for properties with the "inherit" attribute (DD, PM, VW).
+    pub inherit: bool,
+}
+
+macro_rules! spec {
+    ($id:expr, $p:ident, $v:ident) => {
+        PropertySpec { id: $id, property_type: PropertyType::$p, value_type: ValueType::$v, inherit: false }
+    };
+    ($id:expr, $p:ident, $v:ident, inherit) => {
+        PropertySpec { id: $id, property_type: PropertyType::$p, value_type: ValueType::$v, inherit: true }
+    };
+}
+
+/// All FF[4] properties and Go specific properties.
+/// The order of this table is the canonical order of properties, which is
+/// root properties, game info properties, setup properties, move properties,
+/// move annotation properties, node annotation properties, markup properties,
+/// timing properties, miscellaneous properties and then Go specific properties
+/// without game info type.
+/// Properties in each category are in alphabetical order as the specification lists them.
+pub static PROPERTIES: &[PropertySpec] = &[
+    // root
+    spec!("AP", Root, SimpleTextSimpleText),
+    spec!("CA", Root, SimpleText),
+    spec!("FF", Root, Number),
+    spec!("GM", Root, Number),
+    spec!("ST", Root, Number),
+    spec!("SZ", Root, NumberOrNumberNumber),
+    // game info
+    spec!("AN", GameInfo, SimpleText),
+    spec!("BR", GameInfo, SimpleText),
+    spec!("BT", GameInfo, SimpleText),
+    spec!("CP", GameInfo, SimpleText),
+    spec!("DT", GameInfo, SimpleText),
+    spec!("EV", GameInfo, SimpleText),
+    spec!("GC", GameInfo, Text),
+    spec!("GN", GameInfo, SimpleText),
+    spec!("HA", GameInfo, Number),
+    spec!("KM", GameInfo, Real),
+    spec!("ON", GameInfo, SimpleText),
+    spec!("OT", GameInfo, SimpleText),
+    spec!("PB", GameInfo, SimpleText),
+    spec!("PC", GameInfo, SimpleText),
+    spec!("PW", GameInfo, SimpleText),
+    spec!("RE", GameInfo, SimpleText),
+    spec!("RO", GameInfo, SimpleText),
+    spec!("RU", GameInfo, SimpleText),
+    spec!("SO", GameInfo, SimpleText),
+    spec!("TM", GameInfo, Real),
+    spec!("US", GameInfo, SimpleText),
+    spec!("WR", GameInfo, SimpleText),
+    spec!("WT", GameInfo, SimpleText),
+    // setup
+    spec!("AB", Setup, ListOfPoint),
+    spec!("AE", Setup, ListOfPoint),
+    spec!("AW", Setup, ListOfPoint),
+    spec!("PL", Setup, Color),
+    // move
+    spec!("B", Move, Move),
+    spec!("KO", Move, None),
+    spec!("MN", Move, Number),
+    spec!("W", Move, Move),
+    // move annotation
+    spec!("BM", Move, Double),
+    spec!("DO", Move, None),
+    spec!("IT", Move, None),
+    spec!("TE", Move, Double),
+    // node annotation
+    spec!("C", NoType, Text),
+    spec!("DM", NoType, Double),
+    spec!("GB", NoType, Double),
+    spec!("GW", NoType, Double),
+    spec!("HO", NoType, Double),
+    spec!("N", NoType, SimpleText),
+    spec!("UC", NoType, Double),
+    spec!("V", NoType, Real),
+    // markup
+    spec!("AR", NoType, ListOfPointPoint),
+    spec!("CR", NoType, ListOfPoint),
+    spec!("DD", NoType, EListOfPoint, inherit),
+    spec!("LB", NoType, ListOfPointSimpleText),
+    spec!("LN", NoType, ListOfPointPoint),
+    spec!("MA", NoType, ListOfPoint),
+    spec!("SL", NoType, ListOfPoint),
+    spec!("SQ", NoType, ListOfPoint),
+    spec!("TR", NoType, ListOfPoint),
+    // timing
+    spec!("BL", Move, Real),
+    spec!("OB", Move, Number),
+    spec!("OW", Move, Number),
+    spec!("WL", Move, Real),
+    // miscellaneous
+    spec!("FG", NoType, NoneOrNumberSimpleText),
+    spec!("PM", NoType, Number, inherit),
+    spec!("VW", NoType, EListOfPoint, inherit),
+    // Go specific
+    spec!("TB", NoType, EListOfPoint),
+    spec!("TW", NoType, EListOfPoint),
+];
+
+/// Returns the definition of property id if it is a standard property.
+pub fn property_spec(id: &str) -> Option<&'static PropertySpec> {
+    PROPERTIES.iter().find(|p| p.id == id)
+}
+
+/// Returns the position of property id in the canonical order.
+/// Nonstandard properties are placed after all standard ones.
+pub fn canonical_index(id: &str) -> usize {
+    PROPERTIES.iter().position(|p| p.id == id).unwrap_or(PROPERTIES.len())
+}
+
+#[test]
+fn test_property_spec() {
+    assert_eq!(property_spec("KM").unwrap().value_type, ValueType::Real);
+    assert!(property_spec("VW").unwrap().inherit);
+    assert!(property_spec("XX").is_none());
+}
@@ -0,0 +1,420 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Scoring of final positions of Go
+
+use sgf_node::*;
+use board::{Board, BoardFeature, BoardSpec};
+use game_result::{GameResult, WinBy};
+
+/// Maximum difference in points between RE and the replayed score for `SgfNode::result_matches_score` to match.
+/// Counting by hand and by `score` often differs by a point for a dame filled or not, or a seki.
+pub const RESULT_SCORE_TOLERANCE: SgfReal = 1.0;
+
+/// Counting rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleSet {
+    /// Stones on the board plus surrounded empty points (Chinese rules).
+    Area,
+    /// Surrounded empty points plus prisoners (Japanese rules).
+    Territory,
+}
+
+/// Result of `score`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreResult {
+    /// Empty points surrounded by Black, including points of dead white stones.
+    pub black_territory: Vec<(u8, u8)>,
+    /// Empty points surrounded by White, including points of dead black stones.
+    pub white_territory: Vec<(u8, u8)>,
+    /// Empty points bordering both colors or no stones.
+    pub neutral: Vec<(u8, u8)>,
+    pub black_score: SgfReal,
+    /// Score of White including komi.
+    pub white_score: SgfReal,
+}
+
+impl ScoreResult {
+    /// Returns Black's score minus White's score.
+    pub fn margin(&self) -> SgfReal {
+        self.black_score - self.white_score
+    }
+
+    /// Returns the result in the form of RE, such as "B+3.5", "W+0.5" or "0" for a draw.
+    pub fn result(&self) -> SgfSimpleText {
+        let margin = self.margin();
+        if margin > 0.0 {
+            format!("B+{}", margin)
+        } else if margin < 0.0 {
+            format!("W+{}", -margin)
+        } else {
+            "0".to_string()
+        }
+    }
+}
+
+/// Scores a final position.
+///
+/// `dead_stones` are removed from the board first and counted as prisoners of the opponent.
+/// Each empty region is flood-filled and assigned to the color of stones bordering it.
+/// A region bordering both colors is neutral.
+/// White's score includes `komi`.
+///
+/// Seki is not recognized. Shared liberties of a seki border both colors and are neutral as they should be,
+/// but an eye of a group in seki is counted as territory of the group's owner
+/// whereas Japanese rules count no territory in seki.
+/// Callers should adjust the result for such positions themselves.
+pub fn score(board: &Board, dead_stones: &[(u8, u8)], komi: SgfReal, rules: RuleSet) -> ScoreResult {
+    let mut board = board.clone();
+    let mut black_prisoners = board.captures('B');
+    let mut white_prisoners = board.captures('W');
+    for &p in dead_stones {
+        match board.get(p) {
+            Some('B') => white_prisoners += 1,
+            Some(_) => black_prisoners += 1,
+            None => continue,
+        }
+        board.set(p, None);
+    }
+    let (width, height) = board.size();
+    let mut visited = vec![false; width as usize * height as usize];
+    let mut result = ScoreResult {
+        black_territory: Vec::new(),
+        white_territory: Vec::new(),
+        neutral: Vec::new(),
+        black_score: 0.0,
+        white_score: komi,
+    };
+    let mut black_stones = 0;
+    let mut white_stones = 0;
+    for y in 0..height {
+        for x in 0..width {
+            match board.get((x, y)) {
+                Some('B') => black_stones += 1,
+                Some(_) => white_stones += 1,
+                None if !visited[y as usize * width as usize + x as usize] => {
+                    let (region, borders) = empty_region(&board, (x, y), &mut visited);
+                    match (borders.contains(&'B'), borders.contains(&'W')) {
+                        (true, false) => result.black_territory.extend(region),
+                        (false, true) => result.white_territory.extend(region),
+                        _ => result.neutral.extend(region),
+                    }
+                },
+                None => (),
+            }
+        }
+    }
+    result.black_territory.sort_by_key(|&(x, y)| (y, x));
+    result.white_territory.sort_by_key(|&(x, y)| (y, x));
+    result.neutral.sort_by_key(|&(x, y)| (y, x));
+    result.black_score += result.black_territory.len() as SgfReal;
+    result.white_score += result.white_territory.len() as SgfReal;
+    match rules {
+        RuleSet::Area => {
+            result.black_score += black_stones as SgfReal;
+            result.white_score += white_stones as SgfReal;
+        },
+        RuleSet::Territory => {
+            result.black_score += black_prisoners as SgfReal;
+            result.white_score += white_prisoners as SgfReal;
+        },
+    }
+    result
+}
+
+/// Returns the empty region including p and the colors of stones bordering it.
+fn empty_region(board: &Board, p: (u8, u8), visited: &mut [bool]) -> (Vec<(u8, u8)>, Vec<SgfColor>) {
+    let width = board.size().0 as usize;
+    let mut region = vec![p];
+    let mut borders = Vec::new();
+    let mut stack = vec![p];
+    visited[p.1 as usize * width + p.0 as usize] = true;
+    while let Some(q) = stack.pop() {
+        for n in board.neighbors(q) {
+            match board.get(n) {
+                Some(c) => if !borders.contains(&c) {
+                    borders.push(c);
+                },
+                None => {
+                    let i = n.1 as usize * width + n.0 as usize;
+                    if !visited[i] {
+                        visited[i] = true;
+                        region.push(n);
+                        stack.push(n);
+                    }
+                },
+            }
+        }
+    }
+    (region, borders)
+}
+
+/// Result of `estimate_dead_stones`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadStoneEstimate {
+    /// Points of stones guessed dead in the order of rows and then columns.
+    pub dead_stones: Vec<(u8, u8)>,
+    /// False if some groups could not be judged, for example groups in seki or groups of an unfinished game.
+    /// Even if true, `dead_stones` may be wrong. Ask players to confirm them when it matters.
+    pub confident: bool,
+}
+
+/// Guesses dead stones of a finished position.
+///
+/// This is a conservative heuristic and it will be wrong sometimes.
+/// Groups without any territory, that is, without an empty region bordered only by themselves,
+/// are examined from the smallest one.
+/// A group is guessed dead if removing it leaves an empty region bordered only by opponent stones
+/// which are at least twice as many as the group.
+/// Then the group is removed and the examination is repeated.
+///
+/// The estimate is not confident if a group without territory or a group with only a one-point eye remains.
+pub fn estimate_dead_stones(board: &Board) -> DeadStoneEstimate {
+    let mut board = board.clone();
+    let (width, height) = board.size();
+    let mut dead_stones = Vec::new();
+    'search: loop {
+        let mut candidates = chains_with_territory(&board).into_iter()
+            .filter(|t| t.regions == 0)
+            .map(|t| t.chain)
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|chain| chain.len());
+        for chain in candidates {
+            let color = board.get(chain[0]);
+            let mut removed = board.clone();
+            for &p in chain.iter() {
+                removed.set(p, None);
+            }
+            let mut visited = vec![false; width as usize * height as usize];
+            let (region, borders) = empty_region(&removed, chain[0], &mut visited);
+            if borders.len() == 1 && Some(borders[0]) != color && bordering_stones(&removed, &region) >= 2 * chain.len() {
+                dead_stones.extend(chain);
+                board = removed;
+                continue 'search;
+            }
+        }
+        break;
+    }
+    dead_stones.sort_by_key(|&(x, y)| (y, x));
+    let confident = chains_with_territory(&board).iter().all(|t| t.regions >= 2 || t.points >= 2);
+    DeadStoneEstimate { dead_stones, confident }
+}
+
+/// A chain and its territory.
+struct ChainTerritory {
+    chain: Vec<(u8, u8)>,
+    /// the number of empty regions adjacent to the chain and bordered only by its color
+    regions: usize,
+    /// the total number of points of the regions
+    points: usize,
+}
+
+/// Returns all chains on the board with their territories.
+fn chains_with_territory(board: &Board) -> Vec<ChainTerritory> {
+    let (width, height) = board.size();
+    let mut visited = vec![false; width as usize * height as usize];
+    let mut regions = Vec::new();
+    let mut region_index = vec![None; width as usize * height as usize];
+    for y in 0..height {
+        for x in 0..width {
+            if board.get((x, y)).is_none() && !visited[y as usize * width as usize + x as usize] {
+                let (region, borders) = empty_region(board, (x, y), &mut visited);
+                for &(x, y) in region.iter() {
+                    region_index[y as usize * width as usize + x as usize] = Some(regions.len());
+                }
+                regions.push((region.len(), borders));
+            }
+        }
+    }
+    let mut in_chain = vec![false; width as usize * height as usize];
+    let mut result = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let color = match board.get((x, y)) {
+                Some(c) if !in_chain[y as usize * width as usize + x as usize] => c,
+                _ => continue,
+            };
+            let (chain, _) = board.chain((x, y));
+            let mut own = Vec::new();
+            for &p in chain.iter() {
+                in_chain[p.1 as usize * width as usize + p.0 as usize] = true;
+                for (nx, ny) in board.neighbors(p) {
+                    if let Some(i) = region_index[ny as usize * width as usize + nx as usize] {
+                        if regions[i].1 == [color] && !own.contains(&i) {
+                            own.push(i);
+                        }
+                    }
+                }
+            }
+            let points = own.iter().map(|&i| regions[i].0).sum();
+            result.push(ChainTerritory { chain, regions: own.len(), points });
+        }
+    }
+    result
+}
+
+/// Returns the number of stones in chains adjacent to the region.
+fn bordering_stones(board: &Board, region: &[(u8, u8)]) -> usize {
+    let width = board.size().0 as usize;
+    let mut counted = vec![false; board.size().0 as usize * board.size().1 as usize];
+    let mut count = 0;
+    for &p in region {
+        for n in board.neighbors(p) {
+            if board.get(n).is_some() && !counted[n.1 as usize * width + n.0 as usize] {
+                let (chain, _) = board.chain(n);
+                for &(x, y) in chain.iter() {
+                    counted[y as usize * width + x as usize] = true;
+                }
+                count += chain.len();
+            }
+        }
+    }
+    count
+}
+
+impl SgfNode {
+    /// Scores the final position of the main line.
+    /// The board size is taken from SZ and komi from KM of the node, 0 if absent.
+    /// Returns UnsupportedBoard if SZ is not supported.
+    pub fn score_final_position(&self, dead_stones: &[(u8, u8)], rules: RuleSet) -> Result<ScoreResult, SgfError> {
+        let spec = self.board_spec()?;
+        spec.supports(BoardFeature::Scoring)?;
+        let board = self.final_position(spec.size())?;
+        Ok(score(&board, dead_stones, self.get_real("KM").unwrap_or(0.0), rules))
+    }
+
+    /// Returns whether RE of the node agrees with the score of the final position of the main line
+    /// within RESULT_SCORE_TOLERANCE points, with dead stones estimated by `estimate_dead_stones`
+    /// and komi from KM, 0 if absent.
+    /// Returns None if RE is not a score margin nor a draw, the size is not supported for scoring,
+    /// or the dead stones are not estimated confidently.
+    pub fn result_matches_score(&self, size: (u8, u8), rules: RuleSet) -> Option<bool> {
+        let declared = match self.get_simple_text("RE").ok().and_then(|re| GameResult::parse(&re))? {
+            GameResult::Win { winner: 'B', by: WinBy::Score(margin) } => margin,
+            GameResult::Win { winner: _, by: WinBy::Score(margin) } => -margin,
+            GameResult::Draw => 0.0,
+            _ => return None,
+        };
+        BoardSpec::new(size.0, size.1).and_then(|spec| spec.supports(BoardFeature::Scoring)).ok()?;
+        let board = self.final_position(size).ok()?;
+        let estimate = estimate_dead_stones(&board);
+        if !estimate.confident {
+            return None;
+        }
+        let result = score(&board, &estimate.dead_stones, self.get_real("KM").unwrap_or(0.0), rules);
+        Some((result.margin() - declared).abs() <= RESULT_SCORE_TOLERANCE)
+    }
+
+    /// Writes a score back into the game.
+    /// RE is set in the node, which should be the root of a game, since it is a game info property.
+    /// TB and TW are set in the last node of the main line.
+    pub fn set_score(&mut self, result: &ScoreResult) -> &mut Self {
+        self.set_simple_text("RE", result.result());
+        {
+            let leaf = self.leaf_mut();
+            leaf.set_points("TB", result.black_territory.iter().map(|&p| coord_to_point(p)).collect());
+            leaf.set_points("TW", result.white_territory.iter().map(|&p| coord_to_point(p)).collect());
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Black owns the left four columns with a dead white stone at bb,
+    // White owns the right three columns and captures a black stone at ii during the game.
+    const GAME: &str = "(;FF[4]SZ[9]KM[6.5]AB[ea:ei]AW[fa:fi][bb][ih];B[ii];W[hi];B[];W[])";
+
+    #[test]
+    fn test_score_territory() {
+        let node = &SgfCollection::from_sgf(GAME).unwrap()[0];
+        let result = node.score_final_position(&[(1, 1)], RuleSet::Territory).unwrap();
+        assert_eq!(result.black_territory.len(), 36);
+        assert_eq!(result.white_territory.len(), 25);
+        assert!(result.neutral.is_empty());
+        // 36 + 1 dead stone vs 25 + 1 prisoner + 6.5
+        assert_eq!(result.result(), "B+4.5");
+    }
+
+    #[test]
+    fn test_score_area() {
+        let node = &SgfCollection::from_sgf(GAME).unwrap()[0];
+        let result = node.score_final_position(&[(1, 1)], RuleSet::Area).unwrap();
+        // 36 + 9 stones vs 25 + 11 stones + 6.5
+        assert_eq!(result.result(), "B+2.5");
+        assert_eq!(result.margin(), 2.5);
+    }
+
+    #[test]
+    fn test_result_matches_score() {
+        let with_result = |re: &str| SgfCollection::from_sgf(&GAME.replacen("KM[6.5]", &format!("KM[6.5]RE[{}]", re), 1)).unwrap();
+        assert_eq!(with_result("B+4.5")[0].result_matches_score((9, 9), RuleSet::Territory), Some(true));
+        assert_eq!(with_result("B+5.5")[0].result_matches_score((9, 9), RuleSet::Territory), Some(true));
+        assert_eq!(with_result("B+2.5")[0].result_matches_score((9, 9), RuleSet::Area), Some(true));
+        assert_eq!(with_result("W+3.5")[0].result_matches_score((9, 9), RuleSet::Territory), Some(false));
+        assert_eq!(with_result("0")[0].result_matches_score((9, 9), RuleSet::Territory), Some(false));
+        assert_eq!(with_result("B+R")[0].result_matches_score((9, 9), RuleSet::Territory), None);
+        assert_eq!(SgfCollection::from_sgf(GAME).unwrap()[0].result_matches_score((9, 9), RuleSet::Territory), None);
+        let unfinished = &SgfCollection::from_sgf("(;FF[4]SZ[9]RE[B+1.5]AB[ee][dc]AW[ef][fg])").unwrap()[0];
+        assert_eq!(unfinished.result_matches_score((9, 9), RuleSet::Area), None);
+    }
+
+    #[test]
+    fn test_score_neutral() {
+        let mut board = Board::new((3, 1)).unwrap();
+        board.set((0, 0), Some('B'));
+        board.set((2, 0), Some('W'));
+        let result = score(&board, &[], 0.0, RuleSet::Area);
+        assert_eq!(result.neutral, vec![(1, 0)]);
+        assert_eq!(result.result(), "0");
+    }
+
+    #[test]
+    fn test_set_score() {
+        let mut node = SgfCollection::from_sgf(GAME).unwrap()[0].clone();
+        let result = node.score_final_position(&[(1, 1)], RuleSet::Territory).unwrap();
+        node.set_score(&result);
+        assert_eq!(node.get_simple_text("RE").unwrap(), "B+4.5");
+        let leaf = node.leaf_mut();
+        let tb = leaf.get_points("TB").unwrap();
+        assert_eq!(tb.len(), 36);
+        assert!(tb.contains(&"bb".to_string()));
+        let tw = leaf.get_points("TW").unwrap();
+        assert_eq!(tw.len(), 25);
+        assert!(tw.contains(&"ii".to_string()));
+        assert!(!tw.contains(&"hi".to_string()));
+    }
+
+    #[test]
+    fn test_estimate_dead_stones() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9]AB[ea:ei][hc]AW[fa:fi][bb])").unwrap()[0];
+        let board = node.final_position((9, 9)).unwrap();
+        let estimate = estimate_dead_stones(&board);
+        assert_eq!(estimate.dead_stones, vec![(1, 1), (7, 2)]);
+        assert!(estimate.confident);
+        let result = score(&board, &estimate.dead_stones, 0.0, RuleSet::Territory);
+        assert_eq!(result.black_territory.len(), 36);
+        assert_eq!(result.white_territory.len(), 27);
+    }
+
+    #[test]
+    fn test_estimate_dead_stones_unfinished() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[9]AB[ee][dc]AW[ef][fg])").unwrap()[0];
+        let estimate = estimate_dead_stones(&node.final_position((9, 9)).unwrap());
+        assert!(estimate.dead_stones.is_empty());
+        assert!(!estimate.confident);
+    }
+
+    #[test]
+    fn test_score_final_position_board_size() {
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[31];B[aa];W[];B[])").unwrap()[0];
+        assert_eq!(node.score_final_position(&[], RuleSet::Area).unwrap().black_score, 961.0);
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[60];B[aa];W[];B[])").unwrap()[0];
+        match node.score_final_position(&[], RuleSet::Area) {
+            Err(SgfError::UnsupportedBoard { cols: 60, rows: 60, feature: BoardFeature::Coordinates }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+}
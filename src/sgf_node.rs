@@ -1,9 +1,16 @@
-use std::collections::HashMap;
-use std::collections::hash_map::Iter;
+use std::collections::{HashMap, VecDeque};
 use std::ops::{Index, IndexMut, Deref, DerefMut};
+use std::error::Error;
 use std::fmt;
+use std::io;
+use std::slice;
 use regex::Regex;
 use parser::*;
+use properties::{property_spec, PropertyType};
+use board::{BoardFeature, BoardSpec};
+use options::locate_runaway_value;
+use prop_value::PropValue;
+use writer::{is_valid_identifier, write_property};
 
 pub type SgfPoint      = String;
 pub type SgfColor      = char;
@@ -15,17 +22,84 @@ pub type SgfSimpleText = String;
 
 #[derive(Debug)]
 pub enum SgfError {
-    NoProperties,
+    /// The property is absent. The value is its identifier.
+    NoProperties(String),
     EmptyProperty,
-    ParseError,
+    /// The value of the property is not of the requested type.
+    /// `id` is empty when the value is not from a property.
+    ParseError { id: String, value: String },
+    /// A move of the same color as the previous move. The value is the move number.
+    IllegalTurn(usize),
+    /// A charset label which is not supported
+    UnknownEncoding(String),
+    /// A character which can't be represented in the requested charset and the path to the node containing it
+    Unencodable { character: char, path: Vec<usize> },
+    Io(io::Error),
+    /// Number of values is not what the type expects.
+    WrongArity { expected: usize, found: usize },
+    /// A valid value followed by extra characters
+    TrailingGarbage(String),
+    /// A value out of the range of its type
+    OutOfRange(String),
+    /// Child indices which don't lead to a node
+    InvalidPath(Vec<usize>),
+    /// A board size which the feature doesn't support
+    UnsupportedBoard { cols: u8, rows: u8, feature: BoardFeature },
+    /// Data which is not of the compact format of the feature "compact"
+    CompactFormat(String),
+    /// Input which is not SGF, with the 1-based position of the error
+    InvalidSgf { line: usize, column: usize },
+    /// A pattern which is not a grid of stones, with the reason
+    InvalidPattern(String),
+    /// A property identifier which is not uppercase letters and can't be written
+    InvalidIdentifier(String),
+}
+
+impl fmt::Display for SgfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SgfError::NoProperties(ref id) => write!(f, "property {} is absent", id),
+            SgfError::EmptyProperty => write!(f, "value is empty or lacks a part of a compose"),
+            SgfError::ParseError { ref id, ref value } if id.is_empty() => write!(f, "invalid value '{}'", value),
+            SgfError::ParseError { ref id, ref value } => write!(f, "property {} is present but '{}' is not a valid value", id, value),
+            SgfError::IllegalTurn(n) => write!(f, "move {} has the same color as the previous move", n),
+            SgfError::UnknownEncoding(ref label) => write!(f, "unknown charset '{}'", label),
+            SgfError::Unencodable { character, ref path } => write!(f, "'{}' at {:?} can't be encoded", character, path),
+            SgfError::Io(ref e) => write!(f, "I/O error: {}", e),
+            SgfError::WrongArity { expected, found } => write!(f, "{} values where {} expected", found, expected),
+            SgfError::TrailingGarbage(ref v) => write!(f, "extra characters after a valid value in '{}'", v),
+            SgfError::OutOfRange(ref v) => write!(f, "'{}' is out of range", v),
+            SgfError::InvalidPath(ref path) => write!(f, "path {:?} doesn't lead to a node", path),
+            SgfError::UnsupportedBoard { cols, rows, feature } => write!(f, "{:?} doesn't support {}x{} boards", feature, cols, rows),
+            SgfError::CompactFormat(ref reason) => write!(f, "invalid compact data: {}", reason),
+            SgfError::InvalidSgf { line, column } => write!(f, "invalid SGF at line {}, column {}", line, column),
+            SgfError::InvalidPattern(ref reason) => write!(f, "invalid pattern: {}", reason),
+            SgfError::InvalidIdentifier(ref id) => write!(f, "'{}' is not a property identifier", id),
+        }
+    }
+}
+
+impl Error for SgfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SgfError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn parse_error(id: &str, value: &str) -> SgfError {
+    SgfError::ParseError { id: id.to_string(), value: value.to_string() }
 }
 
 /// SGF collection
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SgfCollection(Vec<SgfNode>);
 
 impl SgfCollection {
     /// Parses a SGF string and returns a SgfCollection.
+    /// When a value ending with a backslash, like `C[C:\]`, seems to run past its `]`,
+    /// the error points at the start of the value rather than where the parse failed.
     ///
     /// # Example
     ///
@@ -36,12 +110,61 @@ impl SgfCollection {
     /// ```
     ///
     pub fn from_sgf(sgf_str: &str) -> ParseResult<SgfCollection> {
-        collection(sgf_str)
+        collection(sgf_str).map_err(|e| locate_runaway_value(sgf_str, e))
     }
 
     pub fn new(games: Vec<SgfNode>) -> SgfCollection {
         SgfCollection(games)
     }
+
+    /// Appends a game to the collection.
+    pub fn push(&mut self, game: SgfNode) {
+        self.0.push(game)
+    }
+
+    /// Returns the collection as SGF, the same string as Display gives,
+    /// or `SgfError::InvalidIdentifier` with the first property identifier which is not uppercase letters,
+    /// with which the string wouldn't be valid SGF.
+    pub fn to_sgf(&self) -> Result<String, SgfError> {
+        let invalid = self.iter().flat_map(|game| game.iter_depth_first()).flat_map(|node| node.iter_values())
+            .map(|(id, _)| id).find(|id| !is_valid_identifier(id));
+        match invalid {
+            Some(id) => Err(SgfError::InvalidIdentifier(id.clone())),
+            None => Ok(self.to_string()),
+        }
+    }
+
+    /// Returns every game in the collection, taking games nested under a root by broken exporters into account.
+    ///
+    /// Children of a top-level root are regarded as separate games rather than variations
+    /// if the root has two or more children, the root has only root properties(FF, GM, SZ and so on),
+    /// and the first node of every child has game info properties(PB, PW, DT and so on).
+    /// Otherwise the top-level root is a game.
+    /// Note that games nested under a root don't have root properties of the root.
+    pub fn flatten_games(&self) -> Vec<&SgfNode> {
+        let mut games = Vec::new();
+        for game in self.iter() {
+            let has_type = |node: &SgfNode, t: PropertyType| node.iter_values().any(|(id, _)|
+                property_spec(id).map(|spec| spec.property_type) == Some(t));
+            let only_root = game.iter_values().all(|(id, _)|
+                property_spec(id).map(|spec| spec.property_type) == Some(PropertyType::Root));
+            if game.children.len() >= 2 && only_root && game.children.iter().all(|c| has_type(c, PropertyType::GameInfo)) {
+                games.extend(game.children.iter());
+            } else {
+                games.push(game);
+            }
+        }
+        games
+    }
+}
+
+impl IntoIterator for SgfCollection {
+    type Item = SgfNode;
+    type IntoIter = ::std::vec::IntoIter<SgfNode>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
 }
 
 impl Deref for SgfCollection {
@@ -100,12 +223,69 @@ mod test_sgf_collection {
         assert!(node.children.len() == 2 && node.children[0].children[0].children.len() == 2);
     }
 
+    #[test]
+    fn test_tree_navigation() {
+        let node = &SgfCollection::from_sgf("(;FF[4]C[root](;C[a];C[b](;C[c])
+            (;C[d];C[e]))
+            (;C[f](;C[g];C[h];C[i])
+            (;C[j])))").unwrap()[0];
+        let comments = |nodes: Vec<&SgfNode>| nodes.iter().map(|n| n.get_text("C").unwrap()).collect::<Vec<_>>();
+        assert_eq!(comments(node.main_line().collect()), vec!["root", "a", "b", "c"]);
+        assert_eq!(comments(node.iter_depth_first().collect()), vec!["root", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j"]);
+        assert_eq!(node.iter_depth_first().filter(|n| n.children.is_empty()).count(), 4);
+        assert_eq!(node.node_at(&[1, 0, 0]).unwrap().get_text("C").unwrap(), "h");
+        assert_eq!(node.node_at(&[]), Some(node));
+        assert_eq!(node.node_at(&[0, 0, 2]), None);
+        let leaf = node.node_at(&[1, 1]).unwrap();
+        assert_eq!(leaf.main_line().count(), 1);
+        assert_eq!(leaf.iter_depth_first().count(), 1);
+    }
+
+    #[test]
+    fn test_tree_iterators_moves() {
+        let node = &SgfCollection::from_sgf("(;FF[4]C[root](;B[aa];W[bb](;B[cc])
+            (;B[dd];W[ee]))
+            (;B[ff](;W[gg];B[hh];W[ii])
+            (;W[jj])))").unwrap()[0];
+        assert_eq!(node.iter_depth_first().count(), 11);
+        assert_eq!(node.main_line().count(), 4);
+        let black = node.main_line().filter_map(|n| n.get_point("B").ok()).collect::<Vec<_>>();
+        assert_eq!(black, vec!["aa", "cc"]);
+        let black = node.iter_depth_first().filter_map(|n| n.get_point("B").ok()).collect::<Vec<_>>();
+        assert_eq!(black, vec!["aa", "cc", "dd", "ff", "hh"]);
+    }
+
+    #[test]
+    fn test_tree_editing() {
+        let mut node = SgfCollection::from_sgf("(;C[root](;C[a])(;C[b])(;C[c]))").unwrap().into_iter().next().unwrap();
+        let comment = |node: &SgfNode, i: usize| node.children[i].get_text("C").unwrap();
+        node.promote_variation(0);
+        assert_eq!(comment(&node, 0), "a");
+        node.promote_variation(2);
+        assert_eq!((comment(&node, 0), comment(&node, 1), comment(&node, 2)), ("c".to_string(), "a".to_string(), "b".to_string()));
+        let removed = node.remove_child(1);
+        assert_eq!(removed.get_text("C").unwrap(), "a");
+        assert_eq!(node.add_child(removed.clone()), 2);
+        node.insert_child(0, removed);
+        assert_eq!(node.children.len(), 4);
+        node.node_at_mut(&[3]).unwrap().add_child(SgfNode::new(Vec::new()));
+        assert_eq!(node.node_at(&[3, 0]).unwrap().iter().count(), 0);
+        assert!(node.node_at_mut(&[3, 1]).is_none());
+    }
+
     #[test]
     fn test_from_sgf_long_propid() {
         let result = SgfCollection::from_sgf("(;CA[UTF-8]LONGNAME[])");
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_from_sgf_whitespace_between_values() {
+        let node = &SgfCollection::from_sgf("(;FF[4]AB[aa] [bb]\n\t[cc]\x0B[dd]\r\n;B[ee])").unwrap()[0];
+        assert_eq!(node.get_points("AB").unwrap(), vec!["aa", "bb", "cc", "dd"]);
+        assert!(SgfCollection::from_sgf("(;FF[4]AB[aa]v[bb])").is_err());
+    }
+
     #[test]
     fn test_fmt() {
         let sgf = "(;FF[4];C[a];C[b](;C[c])(;C[d];C[e])(;C[f](;C[g];C[h];C[i])(;C[j])))";
@@ -114,6 +294,26 @@ mod test_sgf_collection {
         assert_eq!(&string, sgf);
     }
 
+    #[test]
+    fn test_fmt_keeps_property_order() {
+        let sgf = "(;GM[1]FF[4]SZ[19]PB[foo]PW[bar];B[pd]C[first]N[move])";
+        let mut collection = SgfCollection::from_sgf(sgf).unwrap();
+        assert_eq!(collection.to_string(), sgf);
+        collection[0].set_simple_text("PB", "baz".to_string());
+        collection[0].set_number("HA", 2);
+        assert_eq!(collection.to_string(), "(;GM[1]FF[4]SZ[19]PB[baz]PW[bar]HA[2];B[pd]C[first]N[move])");
+    }
+
+    #[test]
+    fn test_round_trip_multi_property_node() {
+        let sgf = "(;FF[4]GM[1]SZ[19]KM[6.5])";
+        let collection = SgfCollection::from_sgf(sgf).unwrap();
+        for _ in 0..10 {
+            assert_eq!(format!("{}", collection), sgf);
+        }
+        assert_eq!(format!("{:?}", collection[0]), "{\n    FF: 4\n    GM: 1\n    SZ: 19\n    KM: 6.5\n}");
+    }
+
     #[test]
     fn test_index() {
         let sgf = "(;FF[4]GC[game1])(;FF[4]GC[game2])";
@@ -123,19 +323,157 @@ mod test_sgf_collection {
 }
 
 /// SGF node with children. It means that a node also represents game tree.
-/// Access the field 'children' directly to traverse in its tree.
+/// Use `main_line` and `iter_depth_first` to traverse in its tree, or access the field 'children' directly.
 /// To access SGF properties of the node, use various accessors below.
+#[derive(Clone, PartialEq)]
 pub struct SgfNode {
-    properties: HashMap<String, Vec<String>>,
+    properties: PropertyMap,
     pub children: Vec<SgfNode>,
 }
 
+/// Properties of a node in insertion order.
+/// Most nodes have a few properties, which are looked up linearly.
+/// Nodes with more than INDEX_THRESHOLD properties keep an index from identifiers to positions.
+#[derive(Clone, Default)]
+struct PropertyMap {
+    entries: Vec<(String, Vec<PropValue>)>,
+    index: Option<HashMap<String, usize>>,
+}
+
+/// Number of properties up to which `PropertyMap` has no index.
+const INDEX_THRESHOLD: usize = 8;
+
+impl PropertyMap {
+    fn position(&self, id: &str) -> Option<usize> {
+        match self.index {
+            Some(ref index) => index.get(id).cloned(),
+            None => self.entries.iter().position(|p| p.0 == id),
+        }
+    }
+
+    fn get(&self, id: &str) -> Option<&Vec<PropValue>> {
+        self.position(id).map(|i| &self.entries[i].1)
+    }
+
+    fn contains_key(&self, id: &str) -> bool {
+        self.position(id).is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Replaces the values of id keeping its position, or appends id if it is new.
+    /// Returns false if id was new.
+    fn insert(&mut self, id: String, values: Vec<PropValue>) -> bool {
+        if let Some(i) = self.position(&id) {
+            self.entries[i].1 = values;
+            return true;
+        }
+        if let Some(ref mut index) = self.index {
+            index.insert(id.clone(), self.entries.len());
+        }
+        self.entries.push((id, values));
+        if self.index.is_none() && self.entries.len() > INDEX_THRESHOLD {
+            self.reindex();
+        }
+        false
+    }
+
+    fn remove(&mut self, id: &str) -> Option<Vec<PropValue>> {
+        let i = self.position(id)?;
+        let values = self.entries.remove(i).1;
+        if self.index.is_some() {
+            self.reindex();
+        }
+        Some(values)
+    }
+
+    /// Moves the property at i to new_index shifting the others.
+    fn move_to(&mut self, i: usize, new_index: usize) {
+        let property = self.entries.remove(i);
+        self.entries.insert(new_index, property);
+        if self.index.is_some() {
+            self.reindex();
+        }
+    }
+
+    /// Rebuilds the index, or drops it if there are INDEX_THRESHOLD properties or fewer.
+    fn reindex(&mut self) {
+        self.index = if self.entries.len() > INDEX_THRESHOLD {
+            Some(self.entries.iter().enumerate().map(|(i, p)| (p.0.clone(), i)).collect())
+        } else {
+            None
+        };
+    }
+
+    fn iter(&self) -> PropertyValues<'_> {
+        PropertyValues(self.entries.iter())
+    }
+
+    fn iter_mut(&mut self) -> PropertiesMut<'_> {
+        PropertiesMut(self.entries.iter_mut())
+    }
+}
+
+/// Properties are equal regardless of their order.
+impl PartialEq for PropertyMap {
+    fn eq(&self, other: &PropertyMap) -> bool {
+        self.len() == other.len() && self.entries.iter().all(|p| other.get(&p.0) == Some(&p.1))
+    }
+}
+
+/// Iterator of properties returned by `SgfNode::iter`.
+pub struct Properties<'a>(slice::Iter<'a, (String, Vec<PropValue>)>);
+
+impl<'a> Iterator for Properties<'a> {
+    type Item = (&'a String, Vec<&'a str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|p| (&p.0, p.1.iter().map(|v| v.as_str()).collect()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Iterator of properties returned by `SgfNode::iter_values`.
+pub struct PropertyValues<'a>(slice::Iter<'a, (String, Vec<PropValue>)>);
+
+impl<'a> Iterator for PropertyValues<'a> {
+    type Item = (&'a String, &'a Vec<PropValue>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|p| (&p.0, &p.1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Iterator of properties with mutable values returned by `SgfNode::iter_values_mut`.
+pub struct PropertiesMut<'a>(slice::IterMut<'a, (String, Vec<PropValue>)>);
+
+impl<'a> Iterator for PropertiesMut<'a> {
+    type Item = (&'a String, &'a mut Vec<PropValue>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|p| (&p.0, &mut p.1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
 impl fmt::Debug for SgfNode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut result = write!(f, "{{\n");
         for (key, value) in self.properties.iter() {
             let vstr = if value.len() == 1 {
-                value[0].clone()
+                value[0].to_string()
             } else {
                 value.iter().map(|e| format!("[{}]", e)).fold("".to_string(), |acc, i| acc + &i)
             };
@@ -145,14 +483,16 @@ impl fmt::Debug for SgfNode {
     }
 }
 
+/// Writes the tree as a game tree without parentheses.
+/// Values which would break the output are escaped as `write_sgf_with_options` does,
+/// and an empty list of values is written as "[]".
+/// Identifiers are written as they are, so the output isn't valid SGF if one is not uppercase letters.
+/// `SgfCollection::to_sgf` reports such identifiers.
 impl fmt::Display for SgfNode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut result = write!(f, ";");
         for (key, value) in self.properties.iter() {
-            result = result.and(write!(f, "{}", key));
-            for v in value {
-                result = result.and(write!(f, "[{}]", v));
-            }
+            result = result.and_then(|_| write_property(f, key, value));
         }
         if self.children.len() == 1 {
             result = result.and(write!(f, "{}", self.children[0]));
@@ -168,7 +508,7 @@ impl fmt::Display for SgfNode {
 #[test]
 #[should_panic]
 fn test_debug_fmt() {
-    let mut hash = HashMap::new();
+    let mut hash = ::std::collections::HashMap::new();
     hash.insert("GC".to_string(), vec!["test".to_string()]);
     hash.insert("FF".to_string(), vec!["4".to_string()]);
 
@@ -180,14 +520,35 @@ fn test_debug_fmt() {
 impl SgfNode {
     /// Constructor.
     /// Returns an SgfNode with given propertes.
-    /// Properties should be stored in a HashMap in String name and a vector of String value.
-    pub fn new(properties: HashMap<String, Vec<String>>) -> SgfNode {
+    /// Properties are pairs of String name and a vector of String value, such as a HashMap or a Vec.
+    /// Their order is kept, and the last one wins if a name appears twice.
+    pub fn new<I: IntoIterator<Item = (String, Vec<String>)>>(properties: I) -> SgfNode {
+        let mut map = PropertyMap::default();
+        for (id, values) in properties {
+            map.insert(id, values.into_iter().map(PropValue::from).collect());
+        }
         SgfNode {
-            properties: properties,
+            properties: map,
             children: Vec::new(),
         }
     }
 
+    /// Constructor for the parser.
+    /// Returns an SgfNode with properties in the given order, or an error message for the parser
+    /// if a name appears twice. An empty list gives a node without properties.
+    pub(crate) fn from_parsed_props(properties: Vec<(String, Vec<PropValue>)>) -> Result<SgfNode, &'static str> {
+        let mut map = PropertyMap::default();
+        for (id, values) in properties {
+            if map.insert(id, values) {
+                return Err("duplicated properties");
+            }
+        }
+        Ok(SgfNode {
+            properties: map,
+            children: Vec::new(),
+        })
+    }
+
     /// Returns a mutable reference of a leaf node in main line
     /// This is for the parser.
     pub fn leaf_mut(&mut self) -> &mut SgfNode {
@@ -198,24 +559,244 @@ impl SgfNode {
         }
     }
 
-    fn get_property(&self, id: &str) -> Result<&Vec<String>, SgfError> {
-        self.properties.get(id).ok_or(SgfError::NoProperties)
+    /// Returns the index-th variation from the node, that is, `children.get(index)`.
+    /// The variation 0 is the main line.
+    pub fn variation(&self, index: usize) -> Option<&SgfNode> {
+        self.children.get(index)
+    }
+
+    /// Returns the move which the index-th variation begins with on a width x height board.
+    /// The point is None for a pass. See `get_move`.
+    /// Returns None if there is no such variation or its first node has no valid move.
+    pub fn variation_move(&self, index: usize, width: u8, height: u8) -> Option<(SgfColor, Option<(u8, u8)>)> {
+        let node = self.variation(index)?;
+        for &color in ['B', 'W'].iter() {
+            if let Ok(point) = node.get_move(&color.to_string(), width, height) {
+                return Some((color, point));
+            }
+        }
+        None
+    }
+
+    /// Returns the effective value of property id at the node reached from the node by path of child indices.
+    ///
+    /// Properties with the inherit attribute in the FF[4] specification, that is, DD, PM and VW,
+    /// are in effect until they are set again, and an empty value resets them.
+    /// So their effective value is that of the nearest node on the path having the property,
+    /// or None if the value is empty.
+    /// Other properties are effective only in the node where they are.
+    /// Returns None if path is invalid or the property is not in effect.
+    pub fn effective_property(&self, path: &[usize], id: &str) -> Option<Vec<&str>> {
+        self.effective_values(path, id).map(|values| values.iter().map(|v| v.as_str()).collect())
+    }
+
+    /// Same as `effective_property` except that it returns values as they are stored.
+    pub fn effective_values(&self, path: &[usize], id: &str) -> Option<&[PropValue]> {
+        let inherit = property_spec(id).map_or(false, |spec| spec.inherit);
+        let mut node = self;
+        let mut value = node.properties.get(id);
+        for &i in path {
+            node = node.children.get(i)?;
+            match node.properties.get(id) {
+                Some(v) => value = Some(v),
+                None if !inherit => value = None,
+                None => (),
+            }
+        }
+        match value {
+            Some(v) if inherit && v.iter().all(|e| e.is_empty()) => None,
+            Some(v) => Some(v),
+            None => None,
+        }
+    }
+
+    /// Returns an iterator of (move number, node) along the main line from the node.
+    /// A node with B or W increments the move number, or sets it to the value of MN if the node has MN.
+    /// A node without moves shares the number of the preceding move, so nodes before the first move are numbered 0.
+    pub fn main_line_numbered(&self) -> MainLineNumbered<'_> {
+        MainLineNumbered { node: Some(self), move_number: 0 }
+    }
+
+    /// Returns an iterator of nodes along the main line, that is, the first children, from the node itself.
+    pub fn main_line(&self) -> MainLine<'_> {
+        MainLine { node: Some(self) }
+    }
+
+    /// Returns an iterator of the node and all its descendants in depth-first pre-order.
+    /// Children are visited in the order of their indices.
+    pub fn iter_depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst { stack: vec![self] }
+    }
+
+    /// Returns true if the node or any of its descendants has a move(B or W).
+    /// A game without moves, such as a placeholder record of a tournament, has only game info and setup.
+    pub fn has_moves(&self) -> bool {
+        self.iter_depth_first().any(|n| n.properties.contains_key("B") || n.properties.contains_key("W"))
+    }
+
+    /// Returns the node reached from the node by path of child indices, or None if path is invalid.
+    pub fn node_at(&self, path: &[usize]) -> Option<&SgfNode> {
+        path.iter().try_fold(self, |node, &i| node.children.get(i))
+    }
+
+    /// Mutable version of `node_at`.
+    pub fn node_at_mut(&mut self, path: &[usize]) -> Option<&mut SgfNode> {
+        path.iter().try_fold(self, |node, &i| node.children.get_mut(i))
+    }
+
+    /// Appends child as the last variation and returns its index.
+    pub fn add_child(&mut self, child: SgfNode) -> usize {
+        self.children.push(child);
+        self.children.len() - 1
+    }
+
+    /// Inserts child at index shifting the following variations.
+    /// Panics if index is greater than the number of children.
+    pub fn insert_child(&mut self, index: usize, child: SgfNode) {
+        self.children.insert(index, child);
+    }
+
+    /// Removes and returns the child at index shifting the following variations.
+    /// Panics if index is out of range.
+    pub fn remove_child(&mut self, index: usize) -> SgfNode {
+        self.children.remove(index)
+    }
+
+    /// Moves the child at index to the position 0 so that it becomes the main line.
+    /// The other variations keep their relative order. `promote_variation(0)` does nothing.
+    /// Panics if index is out of range.
+    pub fn promote_variation(&mut self, index: usize) {
+        let child = self.children.remove(index);
+        self.children.insert(0, child);
+    }
+
+    /// Returns the path and the decoded text of the longest C in the tree, measured in characters,
+    /// as a representative annotation for previews.
+    /// Ties are broken by the order of `iter_depth_first`, so the first one visited wins.
+    /// Returns None if no node has C.
+    pub fn longest_comment(&self) -> Option<(Vec<usize>, SgfText)> {
+        let mut longest: Option<(Vec<usize>, SgfText)> = None;
+        let mut stack = vec![(vec![], self)];
+        while let Some((path, node)) = stack.pop() {
+            if let Ok(text) = node.get_text("C") {
+                if longest.as_ref().map_or(true, |l| text.chars().count() > l.1.chars().count()) {
+                    longest = Some((path.clone(), text));
+                }
+            }
+            for (i, child) in node.children.iter().enumerate().rev() {
+                let mut child_path = path.clone();
+                child_path.push(i);
+                stack.push((child_path, child));
+            }
+        }
+        longest
+    }
+
+    /// Returns the number of nodes at each depth(ply) of the tree.
+    /// The first element is 1 for the node itself.
+    pub fn width_profile(&self) -> Vec<usize> {
+        let mut profile = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back((0, self));
+        while let Some((depth, node)) = queue.pop_front() {
+            if profile.len() <= depth {
+                profile.push(0);
+            }
+            profile[depth] += 1;
+            for child in node.children.iter() {
+                queue.push_back((depth + 1, child));
+            }
+        }
+        profile
+    }
+
+    /// Returns a copy of the node without its children.
+    pub(crate) fn without_children(&self) -> SgfNode {
+        SgfNode {
+            properties: self.properties.clone(),
+            children: Vec::new(),
+        }
+    }
+
+    pub(crate) fn get_property(&self, id: &str) -> Result<&Vec<PropValue>, SgfError> {
+        self.properties.get(id).ok_or_else(|| SgfError::NoProperties(id.to_string()))
     }
 
-    fn set_property(&mut self, id: &str, value: Vec<String>) -> &mut Self {
-        self.properties.remove(id);
-        self.properties.insert(id.to_string(), value);
+    pub(crate) fn set_property<V: Into<PropValue>>(&mut self, id: &str, value: Vec<V>) -> &mut Self {
+        self.properties.insert(id.to_string(), value.into_iter().map(Into::into).collect());
         self
     }
 
-    /// Returns an iterator of its properties.
-    pub fn iter(&self) -> Iter<String, Vec<String>> {
+    pub(crate) fn remove_property(&mut self, id: &str) -> Option<Vec<PropValue>> {
+        self.properties.remove(id)
+    }
+
+    /// Returns an iterator of its properties in order.
+    ///
+    /// Properties are in the order of the source when parsed. Setting an existing property keeps its position,
+    /// and a new property is appended at the end, so removing and setting a property again moves it to the end.
+    /// Display and `write_sgf_with_options` write properties in this order while `write_sgf_canonical` sorts them.
+    /// Values are raw strings as they appear in SGF, i.e., escaped.
+    pub fn iter(&self) -> Properties<'_> {
+        Properties(self.properties.entries.iter())
+    }
+
+    /// Same as `iter`.
+    pub fn properties(&self) -> Properties<'_> {
+        self.iter()
+    }
+
+    /// Returns an iterator of its properties in the same order as `iter` with values as they are stored.
+    /// Unlike `iter`, it doesn't collect values of each property into a Vec.
+    pub fn iter_values(&self) -> PropertyValues<'_> {
         self.properties.iter()
     }
 
+    /// Returns an iterator of its properties with mutable values.
+    /// Values are raw strings as they appear in SGF, i.e., escaped.
+    pub fn iter_values_mut(&mut self) -> PropertiesMut<'_> {
+        self.properties.iter_mut()
+    }
+
+    /// Same as `iter_values_mut`. Values are `PropValue`s, which are made from Strings by `into`.
+    #[deprecated(since = "0.1.6", note = "use iter_values_mut")]
+    pub fn iter_mut(&mut self) -> PropertiesMut<'_> {
+        self.iter_values_mut()
+    }
+
+    /// Moves property id to new_index in the order of properties, shifting the others.
+    /// new_index larger than the last index moves it to the end.
+    /// Returns false if the node doesn't have the property.
+    pub fn reorder_property(&mut self, id: &str, new_index: usize) -> bool {
+        match self.properties.position(id) {
+            Some(i) => {
+                let new_index = new_index.min(self.properties.len() - 1);
+                self.properties.move_to(i, new_index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns an iterator of raw values of property id without copying them.
+    /// The iterator is empty if the node doesn't have the property.
+    pub fn values_iter<'a>(&'a self, id: &str) -> impl Iterator<Item = &'a str> + 'a {
+        self.properties.get(id).into_iter().flat_map(|values| values.iter().map(|v| v.as_str()))
+    }
+
+    /// Returns an iterator of zero-based (column, row) of a list of points property id.
+    /// Compressed rectangles like "aa:cc" are expanded lazily and invalid values are skipped.
+    pub fn points_iter(&self, id: &str) -> PointsIter<'_> {
+        let values = match self.properties.get(id) {
+            Some(values) => values.iter(),
+            None => [].iter(),
+        };
+        PointsIter::new(values)
+    }
+
     /// Returns a Result of id's value as SgfPoint.
     pub fn get_point(&self, id: &str) -> Result<SgfPoint, SgfError> {
-        self.get_property(id).map(|v| v[0].clone())
+        self.get_property(id).map(|v| v[0].to_string())
     }
 
     /// Sets an SgfpPoint value to property id.
@@ -223,9 +804,53 @@ impl SgfNode {
         self.set_property(id, vec![value.to_string()]) // to_string is redundant but looks like consistent.
     }
 
+    /// Returns a Result of id's value as zero-based (column, row), or None for an empty value.
+    /// "tt" is the point (19, 19) since the size of the board is unknown here.
+    /// Use `get_move` to take "tt" as a pass on boards up to 19x19.
+    /// Returns ParseError if the value is neither empty nor two letters of 'a'-'z' and 'A'-'Z'.
+    pub fn get_coord_opt(&self, id: &str) -> Result<Option<(u8, u8)>, SgfError> {
+        self.get_property(id).and_then(|v| match &v[0][..] {
+            "" => Ok(None),
+            p => point_to_coord(p).map(Some).ok_or_else(|| parse_error(id, p)),
+        })
+    }
+
+    /// Returns a Result of id's value as zero-based (column, row).
+    /// Returns EmptyProperty for a pass and ParseError for other values which are not points.
+    /// See `get_coord_opt`.
+    pub fn get_coord(&self, id: &str) -> Result<(u8, u8), SgfError> {
+        self.get_coord_opt(id).and_then(|c| c.ok_or(SgfError::EmptyProperty))
+    }
+
+    /// Returns a Result of id's value as a move in zero-based (column, row) on a width x height board,
+    /// or None for a pass.
+    /// A pass is an empty value, or "tt" when the board is up to 19x19.
+    /// Returns ParseError if the value is not a point on the board, and UnsupportedBoard for Coordinates
+    /// if the board is out of 1x1 to 52x52.
+    pub fn get_move(&self, id: &str, width: u8, height: u8) -> Result<Option<(u8, u8)>, SgfError> {
+        BoardSpec::new(width, height)?;
+        self.get_property(id).and_then(|v| match &v[0][..] {
+            "" => Ok(None),
+            "tt" if width <= 19 && height <= 19 => Ok(None),
+            p => match point_to_coord(p) {
+                Some((x, y)) if x < width && y < height => Ok(Some((x, y))),
+                _ => Err(parse_error(id, p)),
+            },
+        })
+    }
+
+    /// Sets zero-based (column, row) to property id as a point.
+    /// Returns OutOfRange without modifying the node if a coordinate is 52 or more, which SGF can't represent.
+    pub fn set_coord(&mut self, id: &str, value: (u8, u8)) -> Result<&mut Self, SgfError> {
+        if value.0 >= 52 || value.1 >= 52 {
+            return Err(SgfError::OutOfRange(format!("{:?}", value)));
+        }
+        Ok(self.set_point(id, coord_to_point(value)))
+    }
+
     /// Returns a Result of id's value as SgfNumber.
     pub fn get_number(&self, id: &str) -> Result<SgfNumber, SgfError> {
-        self.get_property(id).and_then(|v| v[0].parse::<i32>().map_err(|_| SgfError::ParseError))
+        self.get_property(id).and_then(|v| v[0].parse::<i32>().map_err(|_| parse_error(id, &v[0])))
     }
 
     /// Sets an SgfpNumber value to property id.
@@ -235,7 +860,71 @@ impl SgfNode {
 
     /// Returns a Result of id's value as a vector of SgfPoint.
     pub fn get_points(&self, id: &str) -> Result<Vec<SgfPoint>, SgfError> {
-        self.properties.get(id).cloned().ok_or(SgfError::NoProperties)
+        self.get_property(id).map(|v| v.iter().map(|e| e.to_string()).collect())
+    }
+
+    /// Returns points of AE(add empty) with compressed rectangles expanded.
+    /// Invalid values are skipped and an absent AE is an empty vector.
+    pub fn get_add_empty(&self) -> Vec<SgfPoint> {
+        self.points_iter("AE").map(coord_to_point).collect()
+    }
+
+    /// Returns a Result of id's value as a list of points with compressed rectangles like "aa:cc" expanded,
+    /// in the order of values and row by row in a rectangle.
+    /// Returns ParseError for a value which is not a point, a rectangle whose first point is not the upper left
+    /// of the second, or a point out of a board of width and height.
+    pub fn get_expanded_points(&self, id: &str, width: u8, height: u8) -> Result<Vec<SgfPoint>, SgfError> {
+        let mut points = Vec::new();
+        for v in self.get_property(id)? {
+            let coord = |p: &str| match point_to_coord(p) {
+                Some((x, y)) if x < width && y < height => Ok((x, y)),
+                _ => Err(parse_error(id, v)),
+            };
+            let mut compose = v.splitn(2, ':');
+            let (left, top) = coord(compose.next().unwrap())?;
+            let (right, bottom) = match compose.next() {
+                Some(s) => coord(s)?,
+                None => (left, top),
+            };
+            if left > right || top > bottom {
+                return Err(parse_error(id, v));
+            }
+            for y in top..=bottom {
+                points.extend((left..=right).map(|x| coord_to_point((x, y))));
+            }
+        }
+        Ok(points)
+    }
+
+    /// Sets points to property id compressing rectangles into values like "aa:cc".
+    /// Rectangles are taken greedily from the upper left, each as wide and then as tall as possible.
+    /// Duplicated points are written once and the property is removed if points is empty.
+    pub fn set_compressed_points(&mut self, id: &str, points: &[(u8, u8)]) -> &mut Self {
+        let mut rest = points.iter().map(|&(x, y)| (y, x)).collect::<Vec<_>>();
+        rest.sort();
+        rest.dedup();
+        if rest.is_empty() {
+            self.remove_property(id);
+            return self;
+        }
+        let mut values = Vec::new();
+        while let Some(&(top, left)) = rest.first() {
+            let mut right = left;
+            while right < u8::MAX && rest.binary_search(&(top, right + 1)).is_ok() {
+                right += 1;
+            }
+            let mut bottom = top;
+            while bottom < u8::MAX && (left..=right).all(|x| rest.binary_search(&(bottom + 1, x)).is_ok()) {
+                bottom += 1;
+            }
+            rest.retain(|&(y, x)| !(top..=bottom).contains(&y) || !(left..=right).contains(&x));
+            values.push(if (left, top) == (right, bottom) {
+                coord_to_point((left, top))
+            } else {
+                format!("{}:{}", coord_to_point((left, top)), coord_to_point((right, bottom)))
+            });
+        }
+        self.set_points(id, values)
     }
 
     /// Sets an SgfPoint vector to property id.
@@ -285,7 +974,7 @@ impl SgfNode {
 
     /// Returns a Result of id's value as SgfReal.
     pub fn get_real(&self, id: &str) -> Result<SgfReal, SgfError> {
-        self.get_property(id).and_then(|v| v[0].parse::<f32>().map_err(|_| SgfError::ParseError))
+        self.get_property(id).and_then(|v| v[0].parse::<f32>().map_err(|_| parse_error(id, &v[0])))
     }
 
     /// Sets an SgfReal to property id.
@@ -340,12 +1029,12 @@ impl SgfNode {
         self.get_property(id).and_then(|v| {
             let mut compose = v[0].splitn(2, ":");
             compose.next().ok_or(SgfError::EmptyProperty)
-                .and_then(|f| f.parse::<i32>().map_err(|_| SgfError::ParseError))
+                .and_then(|f| f.parse::<i32>().map_err(|_| parse_error(id, &v[0])))
                 .and_then(|f| {
                     compose.next().ok_or(SgfError::EmptyProperty).and_then(|s|
                         match s.parse::<i32>() {
                             Ok(s) => Ok((f, s)),
-                            Err(_) => Err(SgfError::ParseError),
+                            Err(_) => Err(parse_error(id, &v[0])),
                         }
                     )
                 })
@@ -362,7 +1051,7 @@ impl SgfNode {
         self.get_property(id).and_then(|v| {
             let mut compose = v[0].splitn(2, ":");
             compose.next().ok_or(SgfError::EmptyProperty)
-                .and_then(|f| f.parse::<i32>().map_err(|_| SgfError::ParseError))
+                .and_then(|f| f.parse::<i32>().map_err(|_| parse_error(id, &v[0])))
                 .and_then(|f| compose.next().ok_or(SgfError::EmptyProperty).map(|s| (f, decode_simple_text(s))))
         })
     }
@@ -371,11 +1060,338 @@ impl SgfNode {
     pub fn set_number_simple_text(&mut self, id: &str, value: (SgfNumber, SgfSimpleText)) -> &mut Self {
         self.set_property(id, vec![format!("{}:{}", value.0, encode_text(&value.1))])
     }
+
+    /// Returns the description of the opening(ON) if it exists.
+    pub fn opening_description(&self) -> Option<SgfSimpleText> {
+        self.get_simple_text("ON").ok()
+    }
+
+    /// Returns the comment(C) with labels(LB) of the node for rendering annotated diagrams.
+    /// Returns None if there is no comment. Labels without ':' are ignored.
+    pub fn comment_with_labels(&self) -> Option<(SgfText, Vec<(SgfPoint, SgfSimpleText)>)> {
+        let comment = self.get_text("C").ok()?;
+        let labels = match self.get_property("LB") {
+            Ok(values) => values.iter().filter_map(|v| {
+                let mut compose = v.splitn(2, ':');
+                match (compose.next(), compose.next()) {
+                    (Some(p), Some(l)) => Some((p.to_string(), decode_simple_text(l))),
+                    _ => None,
+                }
+            }).collect(),
+            Err(_) => vec![],
+        };
+        Some((comment, labels))
+    }
+
+    /// Returns markup properties TR, CR, SQ and MA of the node.
+    /// Values are raw, so they may be compressed point lists like "aa:cc".
+    pub fn get_markup(&self) -> Markup {
+        let points = |id| self.get_points(id).unwrap_or_default();
+        Markup { triangles: points("TR"), circles: points("CR"), squares: points("SQ"), marks: points("MA") }
+    }
+
+    /// Sets markup properties TR, CR, SQ and MA as point lists.
+    /// A property for an empty slice is removed.
+    pub fn set_markup(&mut self, triangles: &[SgfPoint], circles: &[SgfPoint], squares: &[SgfPoint], marks: &[SgfPoint]) -> &mut Self {
+        for &(id, points) in [("TR", triangles), ("CR", circles), ("SQ", squares), ("MA", marks)].iter() {
+            if points.is_empty() {
+                self.remove_property(id);
+            } else {
+                self.set_points(id, points.to_vec());
+            }
+        }
+        self
+    }
+
+    /// Returns game info properties of the node.
+    pub fn game_info(&self) -> GameInfo {
+        GameInfo {
+            black_player: self.get_simple_text("PB").ok(),
+            black_rank: self.get_simple_text("BR").ok(),
+            black_team: self.get_simple_text("BT").ok(),
+            white_player: self.get_simple_text("PW").ok(),
+            white_rank: self.get_simple_text("WR").ok(),
+            white_team: self.get_simple_text("WT").ok(),
+            game_name: self.get_simple_text("GN").ok(),
+            event: self.get_simple_text("EV").ok(),
+            round: self.get_simple_text("RO").ok(),
+            date: self.get_simple_text("DT").ok(),
+            place: self.get_simple_text("PC").ok(),
+            rules: self.get_simple_text("RU").ok(),
+            result: self.get_simple_text("RE").ok(),
+            handicap: self.get_number("HA").ok(),
+            komi: self.get_real("KM").ok(),
+            time_limit: self.get_real("TM").ok(),
+            opening: self.opening_description(),
+        }
+    }
+
+    /// Returns true if the game has a result(RE) or the last two moves of the main line are passes.
+    /// Only the main line is checked for passes.
+    /// A pass is an empty move value, or "tt" when the board(SZ, 19 by default) is 19x19 or smaller.
+    pub fn is_finished(&self) -> bool {
+        if self.properties.contains_key("RE") {
+            return true;
+        }
+        let small = self.get_number("SZ").map(|s| s <= 19).unwrap_or(true);
+        let mut passes = 0;
+        let mut node = self;
+        loop {
+            if let Ok(m) = node.get_point("B").or_else(|_| node.get_point("W")) {
+                passes = if m.is_empty() || (small && m == "tt") { passes + 1 } else { 0 };
+            }
+            if node.children.is_empty() {
+                break;
+            }
+            node = &node.children[0];
+        }
+        passes >= 2
+    }
+
+    /// Returns colors of moves in the main line.
+    /// Returns Err(SgfError::IllegalTurn(n)) if the n-th move(1-origin) is not of the color expected to play.
+    ///
+    /// The color of the first move is expected as below.
+    ///
+    /// * PL of the root if it exists.
+    /// * White if the game is a handicap game, that is, the root has HA[2] or more,
+    ///   or it has AB before the first move.
+    /// * Black otherwise.
+    ///
+    /// After that, colors should alternate except that PL in a node overrides the expected color of its move.
+    pub fn turn_at_each_move(&self) -> Result<Vec<SgfColor>, SgfError> {
+        let mut expected = if self.get_number("HA").map(|h| h >= 2).unwrap_or(false) { 'W' } else { 'B' };
+        let mut colors = Vec::new();
+        let mut node = self;
+        loop {
+            if colors.is_empty() && node.properties.contains_key("AB") && !node.properties.contains_key("PL") {
+                expected = 'W';
+            }
+            if let Ok(pl) = node.get_color("PL") {
+                expected = pl;
+            }
+            let color = match (node.properties.contains_key("B"), node.properties.contains_key("W")) {
+                (true, false) => Some('B'),
+                (false, true) => Some('W'),
+                (true, true) => return Err(SgfError::IllegalTurn(colors.len() + 1)),
+                (false, false) => None,
+            };
+            if let Some(color) = color {
+                if color != expected {
+                    return Err(SgfError::IllegalTurn(colors.len() + 1));
+                }
+                colors.push(color);
+                expected = if color == 'B' { 'W' } else { 'B' };
+            }
+            if node.children.is_empty() {
+                break;
+            }
+            node = &node.children[0];
+        }
+        Ok(colors)
+    }
+}
+
+/// Iterator returned by `SgfNode::main_line_numbered`.
+#[derive(Debug, Clone)]
+pub struct MainLineNumbered<'a> {
+    node: Option<&'a SgfNode>,
+    move_number: usize,
 }
 
-fn decode_text(s: &str) -> String {
-    let s = Regex::new(r"\\(\r\n|\n\r|\n|\r)").unwrap().replace_all(s, ""); // soft line break
-    let s = Regex::new(r"\\(.)").unwrap().replace_all(&s, "$1"); // escaping
+impl<'a> Iterator for MainLineNumbered<'a> {
+    type Item = (usize, &'a SgfNode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.node?;
+        if node.properties.contains_key("B") || node.properties.contains_key("W") {
+            self.move_number = match node.get_number("MN") {
+                Ok(n) if n >= 0 => n as usize,
+                _ => self.move_number + 1,
+            };
+        }
+        self.node = node.children.first();
+        Some((self.move_number, node))
+    }
+}
+
+/// Iterator returned by `SgfNode::main_line`.
+pub struct MainLine<'a> {
+    node: Option<&'a SgfNode>,
+}
+
+impl<'a> Iterator for MainLine<'a> {
+    type Item = &'a SgfNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.node?;
+        self.node = node.children.first();
+        Some(node)
+    }
+}
+
+/// Iterator returned by `SgfNode::iter_depth_first`.
+pub struct DepthFirst<'a> {
+    stack: Vec<&'a SgfNode>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = &'a SgfNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
+
+/// Game info properties of a game.
+/// Each field is None when the corresponding property is absent or invalid.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GameInfo {
+    pub black_player: Option<SgfSimpleText>,
+    pub black_rank: Option<SgfSimpleText>,
+    pub black_team: Option<SgfSimpleText>,
+    pub white_player: Option<SgfSimpleText>,
+    pub white_rank: Option<SgfSimpleText>,
+    pub white_team: Option<SgfSimpleText>,
+    pub game_name: Option<SgfSimpleText>,
+    pub event: Option<SgfSimpleText>,
+    pub round: Option<SgfSimpleText>,
+    pub date: Option<SgfSimpleText>,
+    pub place: Option<SgfSimpleText>,
+    pub rules: Option<SgfSimpleText>,
+    pub result: Option<SgfSimpleText>,
+    pub handicap: Option<SgfNumber>,
+    pub komi: Option<SgfReal>,
+    pub time_limit: Option<SgfReal>,
+    pub opening: Option<SgfSimpleText>,
+}
+
+/// Markup properties of a node.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Markup {
+    /// TR
+    pub triangles: Vec<SgfPoint>,
+    /// CR
+    pub circles: Vec<SgfPoint>,
+    /// SQ
+    pub squares: Vec<SgfPoint>,
+    /// MA
+    pub marks: Vec<SgfPoint>,
+}
+
+/// Converts a point like "ab" into zero-based (column, row).
+/// 'a'-'z' are 0-25 and 'A'-'Z' are 26-51.
+/// Returns None if p is not two of those letters.
+pub fn point_to_coord(p: &str) -> Option<(u8, u8)> {
+    let mut chars = p.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(x), Some(y), None) => letter_to_index(x).and_then(|x| letter_to_index(y).map(|y| (x, y))),
+        _ => None,
+    }
+}
+
+/// Converts zero-based (column, row) into a point like "ab".
+///
+/// # Panics
+///
+/// Panics if a coordinate is 52 or more, which SGF can't represent.
+pub fn coord_to_point((x, y): (u8, u8)) -> String {
+    assert!(x < 52 && y < 52, "coordinates must be less than 52: {:?}", (x, y));
+    format!("{}{}", index_to_letter(x), index_to_letter(y))
+}
+
+/// Expands a list of points which may contain compressed rectangles like "aa:cc" into coordinates.
+/// Invalid values are skipped.
+pub(crate) fn expand_point_list(values: &[PropValue]) -> Vec<(u8, u8)> {
+    PointsIter::new(values.iter()).collect()
+}
+
+/// Iterator returned by `SgfNode::points_iter`.
+#[derive(Debug, Clone)]
+pub struct PointsIter<'a> {
+    values: slice::Iter<'a, PropValue>,
+    /// the next point of the rectangle being expanded
+    next: Option<(u8, u8)>,
+    left: u8,
+    right: u8,
+    bottom: u8,
+}
+
+impl<'a> PointsIter<'a> {
+    fn new(values: slice::Iter<'a, PropValue>) -> PointsIter<'a> {
+        PointsIter { values, next: None, left: 0, right: 0, bottom: 0 }
+    }
+}
+
+impl<'a> Iterator for PointsIter<'a> {
+    type Item = (u8, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((x, y)) = self.next {
+                self.next = if x < self.right {
+                    Some((x + 1, y))
+                } else if y < self.bottom {
+                    Some((self.left, y + 1))
+                } else {
+                    None
+                };
+                return Some((x, y));
+            }
+            let v = self.values.next()?;
+            let mut compose = v.splitn(2, ':');
+            let f = compose.next().and_then(point_to_coord);
+            match (f, compose.next()) {
+                (Some(f), None) => return Some(f),
+                (Some(f), Some(s)) => if let Some(s) = point_to_coord(s) {
+                    self.left = f.0.min(s.0);
+                    self.right = f.0.max(s.0);
+                    self.bottom = f.1.max(s.1);
+                    self.next = Some((self.left, f.1.min(s.1)));
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+fn letter_to_index(c: char) -> Option<u8> {
+    match c {
+        'a'..='z' => Some(c as u8 - b'a'),
+        'A'..='Z' => Some(c as u8 - b'A' + 26),
+        _ => None,
+    }
+}
+
+fn index_to_letter(i: u8) -> char {
+    if i < 26 { (b'a' + i) as char } else { (b'A' + i - 26) as char }
+}
+
+thread_local! {
+    // compiled once per thread since they are used for every text value
+    static SOFT_LINE_BREAK: Regex = compile(r"\\(\r\n|\n\r|\n|\r)");
+    static ESCAPED: Regex = compile(r"\\(.)");
+    static LINE_BREAK: Regex = compile(r"\r\n|\n\r|\n|\r");
+    static OTHER_WHITESPACE: Regex = compile(r"[\t\x0B\x0C]");
+    static TO_ESCAPE: Regex = compile(r"([\]\\:])");
+}
+
+#[cfg(test)]
+thread_local! {
+    static COMPILATIONS: ::std::cell::Cell<usize> = const { ::std::cell::Cell::new(0) };
+}
+
+fn compile(re: &str) -> Regex {
+    #[cfg(test)]
+    COMPILATIONS.with(|c| c.set(c.get() + 1));
+    Regex::new(re).unwrap()
+}
+
+pub(crate) fn decode_text(s: &str) -> String {
+    let s = SOFT_LINE_BREAK.with(|re| re.replace_all(s, "")); // soft line break
+    let s = ESCAPED.with(|re| re.replace_all(&s, "$1")); // escaping
     s
 }
 
@@ -384,9 +1400,10 @@ fn test_decode_text() {
     assert_eq!(decode_text("[test\\\ntest\\:\\]"), "[testtest:]".to_string());
 }
 
-fn decode_simple_text(s: &str) -> String {
+pub(crate) fn decode_simple_text(s: &str) -> String {
     let s = decode_text(s);
-    let s = Regex::new(r"\r\n|\n\r|\n|\r").unwrap().replace_all(&s, " ");
+    let s = LINE_BREAK.with(|re| re.replace_all(&s, " "));
+    let s = OTHER_WHITESPACE.with(|re| re.replace_all(&s, " ")); // other whitespaces than space
     s
 }
 
@@ -395,8 +1412,13 @@ fn test_decode_simple_text() {
     assert_eq!(decode_simple_text("test\ntest\r\ntest\n\rtest\rtest"), "test test test test test".to_string());
 }
 
-fn encode_text(s: &str) -> String {
-    Regex::new(r"([\]\\:])").unwrap().replace_all(&s, "\\$1") // escaping
+#[test]
+fn test_decode_simple_text_with_tab() {
+    assert_eq!(decode_simple_text("Lee\tSedol\x0B9p"), "Lee Sedol 9p".to_string());
+}
+
+pub(crate) fn encode_text(s: &str) -> String {
+    TO_ESCAPE.with(|re| re.replace_all(s, "\\$1")) // escaping
 }
 
 #[test]
@@ -404,9 +1426,142 @@ fn test_encode_text() {
     assert_eq!(encode_text("]\\:"), "\\]\\\\\\:".to_string());
 }
 
+#[test]
+fn test_regexes_compiled_once() {
+    for _ in 0..1000 {
+        assert_eq!(decode_simple_text("a\\]\\\nb\tc\nd"), "a]b c d");
+        assert_eq!(encode_text("a]b"), "a\\]b");
+    }
+    assert_eq!(COMPILATIONS.with(|c| c.get()), 5);
+}
+
 #[cfg(test)]
 mod sgf_node_tests {
     use sgf_node::*;
+    use writer::*;
+    #[test]
+    fn test_longest_comment() {
+        let c = SgfCollection::from_sgf("(;C[root];B[aa]C[two\\]](;W[bb]C[three])(;W[cc]C[tiny];B[dd]C[123456]))").unwrap();
+        assert_eq!(c[0].longest_comment(), Some((vec![0, 1, 0], "123456".to_string())));
+        let c = SgfCollection::from_sgf("(;C[abc](;C[xyz])(;C[ab]))").unwrap();
+        assert_eq!(c[0].longest_comment(), Some((vec![], "abc".to_string())));
+        assert_eq!(SgfCollection::from_sgf("(;FF[4];B[aa])").unwrap()[0].longest_comment(), None);
+    }
+
+    #[test]
+    fn test_from_parsed_props() {
+        let props = vec![("FF".to_string(), vec![PropValue::from("4")]), ("AB".to_string(), vec![PropValue::from("aa")])];
+        let node = SgfNode::from_parsed_props(props).unwrap();
+        assert_eq!(node.iter().map(|(id, _)| &id[..]).collect::<Vec<_>>(), vec!["FF", "AB"]);
+        let duplicated = vec![("AB".to_string(), vec![PropValue::from("aa")]), ("AB".to_string(), vec![PropValue::from("bb")])];
+        assert_eq!(SgfNode::from_parsed_props(duplicated).err(), Some("duplicated properties"));
+        let empty = SgfNode::from_parsed_props(vec![]).unwrap();
+        assert!(empty.iter().next().is_none() && empty.children.is_empty());
+    }
+
+    #[test]
+    fn test_iter_and_iter_values() {
+        let mut node = SgfCollection::from_sgf("(;FF[4]AB[aa][bb]C[long comment])").unwrap()[0].clone();
+        assert_eq!(node.iter().collect::<Vec<_>>(), vec![
+            (&"FF".to_string(), vec!["4"]),
+            (&"AB".to_string(), vec!["aa", "bb"]),
+            (&"C".to_string(), vec!["long comment"]),
+        ]);
+        let values = node.iter_values().map(|(_, values)| values.iter().map(|v| v.is_inline()).collect::<Vec<_>>()).collect::<Vec<_>>();
+        assert_eq!(values, vec![vec![true], vec![true, true], vec![false]]);
+        for (_, values) in node.iter_values_mut() {
+            values[0] = "cc".into();
+        }
+        assert_eq!(node.to_string(), ";FF[cc]AB[cc][bb]C[cc]");
+    }
+
+    #[test]
+    fn test_get_coord() {
+        let mut node = SgfCollection::from_sgf("(;B[ab])").unwrap()[0].clone();
+        assert_eq!(node.get_coord("B").unwrap(), (0, 1));
+        node.set_coord("W", (0, 1)).unwrap().set_coord("B", (51, 25)).unwrap();
+        assert_eq!(node.get_point("W").unwrap(), "ab");
+        assert_eq!(node.get_point("B").unwrap(), "Zz");
+        assert!(matches!(node.set_coord("B", (52, 0)), Err(SgfError::OutOfRange(_))));
+        assert!(matches!(node.set_coord("B", (0, 255)), Err(SgfError::OutOfRange(_))));
+        assert_eq!(node.get_point("B").unwrap(), "Zz");
+        node.set_point("B", "".to_string());
+        assert_eq!(node.get_coord_opt("B").unwrap(), None);
+        assert!(matches!(node.get_coord("B"), Err(SgfError::EmptyProperty)));
+        node.set_point("B", "tt".to_string());
+        assert_eq!(node.get_coord_opt("B").unwrap(), Some((19, 19)));
+        for invalid in ["a", "a1", "abc", "あい"].iter() {
+            node.set_point("B", invalid.to_string());
+            assert!(matches!(node.get_coord_opt("B"), Err(SgfError::ParseError { .. })));
+        }
+        assert!(matches!(node.get_coord("AB"), Err(SgfError::NoProperties(ref id)) if id == "AB"));
+    }
+
+    #[test]
+    fn test_markup() {
+        let mut node = SgfCollection::from_sgf("(;B[aa]TR[bb]MA[cc])").unwrap()[0].clone();
+        let p = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        node.set_markup(&p(&["dd", "ee"]), &p(&["ff"]), &p(&["aa:bb"]), &[]);
+        let sgf = SgfCollection::new(vec![node]).to_string();
+        assert_eq!(sgf, "(;B[aa]TR[dd][ee]CR[ff]SQ[aa:bb])");
+        let markup = SgfCollection::from_sgf(&sgf).unwrap()[0].get_markup();
+        assert_eq!(markup, Markup { triangles: p(&["dd", "ee"]), circles: p(&["ff"]), squares: p(&["aa:bb"]), marks: vec![] });
+    }
+
+    #[test]
+    fn test_expanded_points() {
+        let mut node = SgfCollection::from_sgf("(;AB[aa:bc][dd]AW[cc:bb]AE[zz])").unwrap()[0].clone();
+        assert_eq!(node.get_expanded_points("AB", 19, 19).unwrap(), vec!["aa", "ba", "ab", "bb", "ac", "bc", "dd"]);
+        assert!(matches!(node.get_expanded_points("AW", 19, 19), Err(SgfError::ParseError { .. })));
+        assert!(matches!(node.get_expanded_points("AE", 19, 19), Err(SgfError::ParseError { .. })));
+        assert!(node.get_expanded_points("AE", 26, 26).is_ok());
+        assert!(matches!(node.get_expanded_points("AB", 3, 3), Err(SgfError::ParseError { .. })));
+        node.set_compressed_points("AB", &[(0, 0), (1, 0), (0, 1), (1, 1), (0, 2), (1, 2), (3, 3), (2, 0), (0, 0)]);
+        assert_eq!(node.get_points("AB").unwrap(), vec!["aa:ca", "ab:bc", "dd"]);
+        assert_eq!(node.get_expanded_points("AB", 19, 19).unwrap().len(), 8);
+        node.set_compressed_points("AB", &[]);
+        assert!(node.get_points("AB").is_err());
+    }
+
+    #[test]
+    fn test_get_move() {
+        let c = SgfCollection::from_sgf("(;SZ[19];B[pd];W[];B[tt];W[zz])(;SZ[21];B[tt];W[ub])(;SZ[9];B[jj])").unwrap();
+        let moves = |game: &SgfNode| {
+            let (width, height) = game.board_size(BoardFeature::Coordinates).unwrap();
+            game.main_line().skip(1)
+                .map(|n| n.get_move(if n.get_point("B").is_ok() { "B" } else { "W" }, width, height))
+                .collect::<Vec<_>>()
+        };
+        let moves19 = moves(&c[0]);
+        assert_eq!(moves19[0].as_ref().ok(), Some(&Some((15, 3))));
+        assert_eq!(moves19[1].as_ref().ok(), Some(&None));
+        assert_eq!(moves19[2].as_ref().ok(), Some(&None));
+        assert!(matches!(moves19[3], Err(SgfError::ParseError { .. })));
+        let moves21 = moves(&c[1]);
+        assert_eq!(moves21[0].as_ref().ok(), Some(&Some((19, 19))));
+        assert_eq!(moves21[1].as_ref().ok(), Some(&Some((20, 1))));
+        assert!(matches!(moves(&c[2])[0], Err(SgfError::ParseError { .. })));
+        assert!(matches!(c[1].children[0].get_move("B", 60, 60), Err(SgfError::UnsupportedBoard { .. })));
+        assert_eq!(point_to_coord("Ab"), Some((26, 1)));
+        assert_eq!(coord_to_point((26, 1)), "Ab");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_coord_to_point_out_of_range() {
+        coord_to_point((52, 0));
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let node = &SgfCollection::from_sgf("(;KM[6.x])").unwrap()[0];
+        let e = node.get_real("KM").unwrap_err();
+        assert!(matches!(e, SgfError::ParseError { ref id, ref value } if id == "KM" && value == "6.x"));
+        assert_eq!(e.to_string(), "property KM is present but '6.x' is not a valid value");
+        let e: Box<dyn Error> = Box::new(node.get_number("HA").unwrap_err());
+        assert_eq!(e.to_string(), "property HA is absent");
+    }
+
     #[test]
     fn test_get_number() {
         let node = &SgfCollection::from_sgf("(;CA[UTF-8]FF[4])").unwrap()[0];
@@ -462,4 +1617,224 @@ mod sgf_node_tests {
         println!("foo {}", node);
         assert_eq!(node.get_simple_text_simple_text("AP").unwrap(), ("mimiaka".to_string(), "1.0".to_string()));
     }
+
+    #[test]
+    fn test_width_profile() {
+        let node = &SgfCollection::from_sgf("(;FF[4]C[root](;C[a];C[b](;C[c])
+            (;C[d];C[e]))
+            (;C[f](;C[g];C[h];C[i])
+            (;C[j])))").unwrap()[0];
+        assert_eq!(node.width_profile(), vec![1, 2, 3, 3, 2]);
+    }
+
+    #[test]
+    fn test_opening_description() {
+        let node = &SgfCollection::from_sgf("(;FF[4]PB[foo]KM[6.5]ON[Chinese\
+fuseki])").unwrap()[0];
+        assert_eq!(node.opening_description(), Some("Chinesefuseki".to_string()));
+        let info = node.game_info();
+        assert_eq!(info.opening, Some("Chinesefuseki".to_string()));
+        assert_eq!(info.black_player, Some("foo".to_string()));
+        assert_eq!(info.komi, Some(6.5));
+        assert_eq!(info.white_player, None);
+        let node = &SgfCollection::from_sgf("(;FF[4])").unwrap()[0];
+        assert_eq!(node.opening_description(), None);
+    }
+
+    #[test]
+    fn test_turn_at_each_move() {
+        let node = &SgfCollection::from_sgf("(;FF[4];B[aa];W[bb];B[cc])").unwrap()[0];
+        assert_eq!(node.turn_at_each_move().unwrap(), vec!['B', 'W', 'B']);
+        let node = &SgfCollection::from_sgf("(;FF[4]HA[2]AB[dd][pp];W[aa];B[bb])").unwrap()[0];
+        assert_eq!(node.turn_at_each_move().unwrap(), vec!['W', 'B']);
+        let node = &SgfCollection::from_sgf("(;FF[4];B[aa];B[bb])").unwrap()[0];
+        match node.turn_at_each_move() {
+            Err(SgfError::IllegalTurn(2)) => (),
+            _ => panic!("repeated color should be flagged"),
+        }
+        let node = &SgfCollection::from_sgf("(;FF[4];B[aa];PL[B]B[bb])").unwrap()[0];
+        assert_eq!(node.turn_at_each_move().unwrap(), vec!['B', 'B']);
+    }
+
+    #[test]
+    fn test_is_finished() {
+        let finished = |sgf: &str| SgfCollection::from_sgf(sgf).unwrap()[0].is_finished();
+        assert!(finished("(;FF[4]RE[B+R];B[aa])"));
+        assert!(finished("(;FF[4];B[aa];W[];B[tt])"));
+        assert!(finished("(;FF[4];B[aa];W[]C[pass];C[comment only];B[])"));
+        assert!(!finished("(;FF[4];B[aa];W[];B[bb])"));
+        assert!(!finished("(;FF[4]SZ[21];B[aa];W[tt];B[tt])"));
+        assert!(!finished("(;FF[4];B[aa](;W[bb])(;W[];B[]))"));
+    }
+
+    #[test]
+    fn test_variation() {
+        let node = &SgfCollection::from_sgf("(;FF[4](;B[dd];W[pp])(;B[])(;C[no move]))").unwrap()[0];
+        assert_eq!(node.variation(0).unwrap().get_point("B").unwrap(), "dd");
+        assert!(node.variation(3).is_none());
+        assert_eq!(node.variation_move(0, 19, 19), Some(('B', Some((3, 3)))));
+        assert_eq!(node.variation_move(1, 19, 19), Some(('B', None)));
+        assert_eq!(node.variation_move(2, 19, 19), None);
+        assert_eq!(node.variation_move(3, 19, 19), None);
+        let node = &SgfCollection::from_sgf("(;FF[4]SZ[21](;B[tt])(;B[ub])(;B[]))").unwrap()[0];
+        assert_eq!(node.variation_move(0, 21, 21), Some(('B', Some((19, 19)))));
+        assert_eq!(node.variation_move(0, 19, 19), Some(('B', None)));
+        assert_eq!(node.variation_move(1, 19, 19), None);
+        assert_eq!(node.variation_move(2, 21, 21), Some(('B', None)));
+    }
+
+    #[test]
+    fn test_comment_with_labels() {
+        let node = &SgfCollection::from_sgf("(;FF[4]C[A is better than B]LB[dd:A][pp:B];B[dd]LB[aa:x])").unwrap()[0];
+        assert_eq!(node.comment_with_labels(), Some(("A is better than B".to_string(), vec![
+            ("dd".to_string(), "A".to_string()),
+            ("pp".to_string(), "B".to_string()),
+        ])));
+        assert_eq!(node.children[0].comment_with_labels(), None);
+        let node = &SgfCollection::from_sgf("(;C[no labels])").unwrap()[0];
+        assert_eq!(node.comment_with_labels(), Some(("no labels".to_string(), vec![])));
+    }
+
+    #[test]
+    fn test_main_line_numbered() {
+        let node = &SgfCollection::from_sgf("(;FF[4];B[aa];C[comment];W[bb](;B[cc]MN[10];W[dd])(;B[ee]))").unwrap()[0];
+        let numbers = node.main_line_numbered().map(|(n, _)| n).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![0, 1, 1, 2, 10, 11]);
+        assert_eq!(node.main_line_numbered().last().unwrap().1.get_point("W").unwrap(), "dd");
+    }
+
+    #[test]
+    fn test_values_iter_100k() {
+        let mut node = SgfNode::new(Vec::new());
+        let points = (0..100000).map(|i| coord_to_point(((i % 52) as u8, (i / 52 % 52) as u8))).collect::<Vec<_>>();
+        node.set_points("AB", points);
+        assert_eq!(node.values_iter("AB").count(), 100000);
+        assert_eq!(node.values_iter("AB").nth(53), Some("bb"));
+        assert_eq!(node.points_iter("AB").nth(99999), Some((3, 51)));
+        assert_eq!(node.values_iter("AW").count(), 0);
+    }
+
+    #[test]
+    fn test_points_iter() {
+        let node = &SgfCollection::from_sgf("(;SZ[19]AB[aa:ss][cb:ab]AW[zz][a])").unwrap()[0];
+        let points = node.points_iter("AB").collect::<Vec<_>>();
+        assert_eq!(points.len(), 361 + 3);
+        assert_eq!(points[0], (0, 0));
+        assert_eq!(points[19], (0, 1));
+        assert_eq!(points[360], (18, 18));
+        assert_eq!(&points[361..], &[(0, 1), (1, 1), (2, 1)]);
+        assert_eq!(node.points_iter("AW").collect::<Vec<_>>(), vec![(25, 25)]);
+    }
+
+    #[test]
+    fn test_parse_100k_values() {
+        use std::time::Instant;
+        let mut sgf = "(;FF[4]SZ[52]AB".to_string();
+        for i in 0..100000 {
+            sgf.push('[');
+            sgf.push_str(&coord_to_point(((i % 52) as u8, (i / 52 % 52) as u8)));
+            sgf.push(']');
+        }
+        sgf.push(')');
+        let start = Instant::now();
+        let c = SgfCollection::from_sgf(&sgf).unwrap();
+        assert_eq!(c[0].values_iter("AB").count(), 100000);
+        assert!(start.elapsed().as_secs() < 30);
+    }
+
+    #[test]
+    fn test_flatten_games() {
+        let c = SgfCollection::from_sgf("(;FF[4]SZ[19](;PB[a]PW[b];B[aa])(;PB[c]PW[d];B[bb]))
+            (;FF[4]PB[e];B[cc])
+            (;FF[4](;B[dd])(;B[ee]))
+            (;FF[4](;PB[f];B[dd])(;B[ee]))").unwrap();
+        let games = c.flatten_games();
+        assert_eq!(games.len(), 5);
+        assert_eq!(games[1].get_simple_text("PB").unwrap(), "c");
+        assert_eq!(games[2].get_simple_text("PB").unwrap(), "e");
+        assert_eq!(games[3].children.len(), 2);
+        assert_eq!(games[4].children.len(), 2);
+    }
+
+    #[test]
+    fn test_effective_property() {
+        let node = &SgfCollection::from_sgf("(;FF[4]VW[aa:cc]PM[2]C[root];B[aa](;VW[]C[a];W[bb];B[cc]VW[bb:dd])(;W[dd]))").unwrap()[0];
+        assert_eq!(node.effective_property(&[], "VW"), Some(vec!["aa:cc"]));
+        assert_eq!(node.effective_property(&[0, 1], "VW"), Some(vec!["aa:cc"]));
+        assert_eq!(node.effective_property(&[0, 0], "VW"), None);
+        assert_eq!(node.effective_property(&[0, 0, 0], "VW"), None);
+        assert_eq!(node.effective_property(&[0, 0, 0, 0], "VW"), Some(vec!["bb:dd"]));
+        assert_eq!(node.effective_property(&[0, 0, 0, 0], "PM"), Some(vec!["2"]));
+        assert_eq!(node.effective_property(&[0], "C"), None);
+        assert_eq!(node.effective_property(&[0, 0], "C"), Some(vec!["a"]));
+        assert_eq!(node.effective_values(&[0, 0], "C"), Some(&[PropValue::from("a")][..]));
+        assert_eq!(node.effective_property(&[0, 5], "VW"), None);
+    }
+
+    #[test]
+    fn test_get_add_empty() {
+        let node = &SgfCollection::from_sgf("(;AE[aa:bb][dd][x];AB[cc])").unwrap()[0];
+        assert_eq!(node.get_add_empty(), vec!["aa", "ba", "ab", "bb", "dd"]);
+        assert!(node.children[0].get_add_empty().is_empty());
+    }
+
+    fn ids(node: &SgfNode) -> Vec<&str> {
+        node.properties().map(|(id, _)| &id[..]).collect()
+    }
+
+    #[test]
+    fn test_property_order_after_edits() {
+        let mut c = SgfCollection::from_sgf("(;GM[1]FF[4]SZ[19]PB[foo]PW[bar])").unwrap();
+        assert_eq!(ids(&c[0]), vec!["GM", "FF", "SZ", "PB", "PW"]);
+        c[0].set_simple_text("PB", "baz".to_string());
+        assert_eq!(ids(&c[0]), vec!["GM", "FF", "SZ", "PB", "PW"]);
+        c[0].set_real("KM", 6.5);
+        assert_eq!(ids(&c[0]), vec!["GM", "FF", "SZ", "PB", "PW", "KM"]);
+        let values = c[0].remove_property("SZ").unwrap();
+        c[0].set_property("SZ", values);
+        assert_eq!(ids(&c[0]), vec!["GM", "FF", "PB", "PW", "KM", "SZ"]);
+        assert!(c[0].reorder_property("SZ", 1));
+        assert!(c[0].reorder_property("GM", 10));
+        assert!(!c[0].reorder_property("RE", 0));
+        assert_eq!(ids(&c[0]), vec!["SZ", "FF", "PB", "PW", "KM", "GM"]);
+        assert_eq!(c.to_string(), "(;SZ[19]FF[4]PB[baz]PW[bar]KM[6.5]GM[1])");
+        let mut written = String::new();
+        write_sgf_with_options(&mut written, &c, &WriteOptions::default()).unwrap();
+        assert_eq!(written, c.to_string());
+        let mut canonical = String::new();
+        write_sgf_canonical(&mut canonical, &c, false).unwrap();
+        assert_eq!(canonical, "(;FF[4]GM[1]SZ[19]KM[6.5]PB[baz]PW[bar])\n");
+    }
+
+    #[test]
+    fn test_property_order_equality() {
+        let a = SgfNode::new(vec![("FF".to_string(), vec!["4".to_string()]), ("GM".to_string(), vec!["1".to_string()])]);
+        let mut b = a.clone();
+        b.reorder_property("GM", 0);
+        assert_eq!(a, b);
+        assert_eq!(ids(&b), vec!["GM", "FF"]);
+    }
+
+    #[test]
+    fn test_many_properties() {
+        let n = 20_000;
+        let id = |i: usize| (0..4).map(|k| (b'A' + (i / 26usize.pow(k) % 26) as u8) as char).collect::<String>();
+        let properties = (0..n).map(|i| format!("{}[{}]", id(i), i)).collect::<String>();
+        let start = ::std::time::Instant::now();
+        let node = SgfCollection::from_sgf(&format!("(;{})", properties)).unwrap()[0].clone();
+        assert_eq!(node.iter().count(), n);
+        assert_eq!(node.get_number(&id(n - 1)).unwrap(), n as SgfNumber - 1);
+        let mut reversed = SgfNode::new((0..n).rev().map(|i| (id(i), vec![i.to_string()])));
+        assert_eq!(reversed, node);
+        assert!(reversed.reorder_property(&id(0), 0));
+        assert!(reversed.remove_property(&id(1)).is_some());
+        assert_eq!(ids(&reversed)[..2], [id(0), id(n - 1)]);
+        assert_eq!(reversed.get_number(&id(2)).unwrap(), 2);
+        assert!(reversed.get_number(&id(1)).is_err());
+        assert_ne!(reversed, node);
+        let duplicated = format!("(;{}{}[x])", properties, id(n / 2));
+        assert!(SgfCollection::from_sgf(&duplicated).is_err());
+        // quadratic lookups take minutes
+        assert!(start.elapsed().as_secs() < 10);
+    }
 }
@@ -0,0 +1,169 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Bundles of parse, write and fix options with presets
+
+use std::fmt;
+use sgf_node::*;
+use parser::*;
+use options::{ParseOptions, ParseReport};
+use writer::{write_sgf_with_options, Alteration, WriteOptions};
+use tidy::{TrimOptions, VariationCompare};
+
+/// Repairs applied by `SgfCollection::fix_with`, in the order of the fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FixOptions {
+    /// Applies `SgfNode::split_root_move` to each game.
+    pub split_root_move: bool,
+    /// Applies `SgfNode::remove_empty_nodes` to each game.
+    pub remove_empty_nodes: bool,
+    /// Applies `SgfNode::dedup_sibling_variations` with `VariationCompare::Exact` to each game.
+    pub dedup_variations: bool,
+    /// If some, applies `SgfNode::trim_trailing_noise` with the options to each game.
+    pub trim_trailing_noise: Option<TrimOptions>,
+}
+
+/// Options for `parse_with`, `write_with` and `fix_with` of SgfCollection in one bundle.
+///
+/// Start from a preset and override fields for a call, for example
+/// `SgfOptions { fix: FixOptions { remove_empty_nodes: true, ..Default::default() }, ..SgfOptions::strict() }`.
+///
+/// | preset | parse | fix | write |
+/// |--------|-------|-----|-------|
+/// | `strict` | strict, values up to 1MiB | none | Display |
+/// | `archival` | strict, values of any length | none | Display |
+/// | `lenient` | lenient, normalized, values up to 1MiB | split root move, remove empty nodes | Display |
+/// | `sgfc_compatible` | lenient, normalized, values up to 1MiB | all, trimming without extra passes | Display |
+/// | `web_upload_hardened` | strict, values up to 64KiB, nesting up to 64 | none | Display |
+#[derive(Debug, Clone, Default)]
+pub struct SgfOptions {
+    pub parse: ParseOptions,
+    pub write: WriteOptions,
+    pub fix: FixOptions,
+}
+
+impl SgfOptions {
+    /// Strict FF[4] everywhere. Same as Default.
+    pub fn strict() -> SgfOptions {
+        SgfOptions::default()
+    }
+
+    /// Accepts malformed real-world files as `ParseOptions::lenient` does, normalizes values
+    /// and fixes root moves and empty nodes.
+    pub fn lenient() -> SgfOptions {
+        SgfOptions {
+            parse: ParseOptions { lenient: true, normalize_values: true, ..Default::default() },
+            fix: FixOptions { split_root_move: true, remove_empty_nodes: true, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    /// Like `lenient` and also removes duplicated variations and empty nodes at the end of the main line.
+    pub fn sgfc_compatible() -> SgfOptions {
+        SgfOptions {
+            fix: FixOptions {
+                split_root_move: true,
+                remove_empty_nodes: true,
+                dedup_variations: true,
+                trim_trailing_noise: Some(TrimOptions::default()),
+            },
+            ..SgfOptions::lenient()
+        }
+    }
+
+    /// Strict parsing without the limit on the length of values, so that no valid file is rejected,
+    /// and no changes, so that writing a parsed valid file reproduces it except for whitespaces between properties.
+    pub fn archival() -> SgfOptions {
+        SgfOptions {
+            parse: ParseOptions { max_value_length: usize::MAX, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    /// Strict parsing of untrusted input with values up to 64KiB and nesting up to 64.
+    pub fn web_upload_hardened() -> SgfOptions {
+        SgfOptions {
+            parse: ParseOptions { max_value_length: 1 << 16, max_nesting: Some(64), ..Default::default() },
+            ..Default::default()
+        }
+    }
+}
+
+impl SgfCollection {
+    /// Parses a SGF string with `options.parse`.
+    pub fn parse_with(sgf_str: &str, options: &SgfOptions) -> ParseResult<(SgfCollection, ParseReport)> {
+        SgfCollection::from_sgf_with_options(sgf_str, &options.parse)
+    }
+
+    /// Writes the collection with `options.write`. See `write_sgf_with_options`.
+    pub fn write_with<W: fmt::Write>(&self, f: &mut W, options: &SgfOptions) -> Result<Vec<Alteration>, fmt::Error> {
+        write_sgf_with_options(f, self, &options.write)
+    }
+
+    /// Repairs each game with `options.fix`.
+    /// Returns the error of `SgfNode::trim_trailing_noise` for a game whose SZ is not supported,
+    /// after the games before it are repaired.
+    pub fn fix_with(&mut self, options: &SgfOptions) -> Result<(), SgfError> {
+        let fix = options.fix;
+        for game in self.iter_mut() {
+            if fix.split_root_move {
+                game.split_root_move();
+            }
+            if fix.remove_empty_nodes {
+                game.remove_empty_nodes();
+            }
+            if fix.dedup_variations {
+                game.dedup_sibling_variations(VariationCompare::Exact);
+            }
+            if let Some(trim) = fix.trim_trailing_noise {
+                game.trim_trailing_noise(trim)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use options::DEFAULT_MAX_VALUE_LENGTH;
+
+    const MESSY: &str = "(;FF[4]CoPyright[me]PB[foo]B[pd];;W[dd];(;B[pp])(;B[pp]))\nDownloaded";
+    const ARCHIVAL: &str = "(;GM[1]FF[4]SZ[19]PB[foo];B[pd]C[first]N[move](;W[dd])(;W[dp]))";
+
+    fn run(sgf: &str, options: &SgfOptions) -> Result<String, ()> {
+        let (mut c, _) = SgfCollection::parse_with(sgf, options).map_err(|_| ())?;
+        c.fix_with(options).map_err(|_| ())?;
+        let mut s = String::new();
+        c.write_with(&mut s, options).unwrap();
+        Ok(s)
+    }
+
+    #[test]
+    fn test_presets_messy() {
+        assert!(run(MESSY, &SgfOptions::strict()).is_err());
+        assert!(run(MESSY, &SgfOptions::web_upload_hardened()).is_err());
+        assert_eq!(run(MESSY, &SgfOptions::lenient()).unwrap(), "(;FF[4]CP[me]PB[foo];B[pd];W[dd](;B[pp])(;B[pp]))");
+        assert_eq!(run(MESSY, &SgfOptions::sgfc_compatible()).unwrap(), "(;FF[4]CP[me]PB[foo];B[pd];W[dd];B[pp])");
+    }
+
+    #[test]
+    fn test_presets_nesting() {
+        let deep = "(;FF[4]".to_string() + &"(;B[aa]".repeat(100) + &")".repeat(101);
+        let e = SgfCollection::parse_with(&deep, &SgfOptions::web_upload_hardened()).unwrap_err();
+        assert_eq!(deep[..e.offset].matches('(').count(), 64);
+        assert!(run(&deep, &SgfOptions::strict()).is_ok());
+        let unlimited = ParseOptions { max_nesting: None, ..SgfOptions::web_upload_hardened().parse };
+        assert!(run(&deep, &SgfOptions { parse: unlimited, ..SgfOptions::web_upload_hardened() }).is_ok());
+        assert!(run(ARCHIVAL, &SgfOptions::web_upload_hardened()).is_ok());
+    }
+
+    #[test]
+    fn test_presets_archival() {
+        assert_eq!(run(ARCHIVAL, &SgfOptions::archival()).unwrap(), ARCHIVAL);
+        assert_eq!(run(ARCHIVAL, &SgfOptions::sgfc_compatible()).unwrap(), ARCHIVAL);
+        let long = format!("(;FF[4]C[{}])", "a".repeat(DEFAULT_MAX_VALUE_LENGTH + 1));
+        assert!(run(&long, &SgfOptions::strict()).is_err());
+        assert_eq!(run(&long, &SgfOptions::archival()).unwrap(), long);
+    }
+}
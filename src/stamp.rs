@@ -0,0 +1,131 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Bulk stamping of properties over collections
+
+use sgf_node::*;
+use properties::{property_spec, ValueType};
+
+/// How `SgfCollection::stamp_metadata` treats existing values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replaces existing values.
+    Always,
+    /// Sets only missing properties.
+    IfMissing,
+    /// Sets missing properties and keeps different existing values reporting them as conflicts.
+    IfDifferentWarn,
+}
+
+/// What `SgfCollection::stamp_metadata` did for a property of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StampAction {
+    /// The missing property was set.
+    Set,
+    /// A different value was replaced.
+    Overwritten { previous: String },
+    /// The existing value was kept since it is the same or the policy is IfMissing.
+    Unchanged,
+    /// A different existing value was kept under IfDifferentWarn.
+    Conflict { existing: String },
+    /// The value is invalid for the type of the property, so nothing was done.
+    Invalid,
+}
+
+/// A record of `StampReport`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StampEntry {
+    /// Index of the game in the collection
+    pub game: usize,
+    pub id: String,
+    pub action: StampAction,
+}
+
+/// Report of `SgfCollection::stamp_metadata` in the order of games and then properties.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StampReport {
+    pub entries: Vec<StampEntry>,
+}
+
+impl SgfCollection {
+    /// Sets properties to the root of every game, such as EV and RO of a round of a tournament.
+    ///
+    /// `{index}` in a value is replaced with the 1-based index of the game.
+    /// Values are raw text and encoded according to the type of the property in the specification,
+    /// that is, escaped for Text and SimpleText, and checked and trimmed for Number and Real.
+    /// Values of other types and of unknown properties are set as they are.
+    /// An existing value is compared with the encoded value as it is.
+    pub fn stamp_metadata(&mut self, props: &[(&str, &str)], overwrite: OverwritePolicy) -> StampReport {
+        let mut report = StampReport::default();
+        for (game, root) in self.iter_mut().enumerate() {
+            for &(id, value) in props {
+                let value = value.replace("{index}", &(game + 1).to_string());
+                let action = match encode_value(id, &value) {
+                    None => StampAction::Invalid,
+                    Some(encoded) => match root.get_property(id).ok().map(|v| v.join("][")) {
+                        None => {
+                            root.set_property(id, vec![encoded]);
+                            StampAction::Set
+                        },
+                        Some(ref existing) if *existing == encoded => StampAction::Unchanged,
+                        Some(existing) => match overwrite {
+                            OverwritePolicy::Always => {
+                                root.set_property(id, vec![encoded]);
+                                StampAction::Overwritten { previous: existing }
+                            },
+                            OverwritePolicy::IfMissing => StampAction::Unchanged,
+                            OverwritePolicy::IfDifferentWarn => StampAction::Conflict { existing },
+                        },
+                    },
+                };
+                report.entries.push(StampEntry { game, id: id.to_string(), action });
+            }
+        }
+        report
+    }
+}
+
+/// Returns the raw value of value for property id, or None if it is invalid.
+fn encode_value(id: &str, value: &str) -> Option<String> {
+    match property_spec(id).map(|spec| spec.value_type) {
+        Some(ValueType::Text) | Some(ValueType::SimpleText) => Some(encode_text(value)),
+        Some(ValueType::Number) => value.trim().parse::<SgfNumber>().ok().map(|n| n.to_string()),
+        Some(ValueType::Real) => value.trim().parse::<SgfReal>().ok().map(|_| value.trim().to_string()),
+        _ => Some(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp_metadata_if_missing() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]EV[Cup])(;FF[4]RO[9];B[aa])(;FF[4]EV[Meijin]RO[1])(;FF[4])").unwrap();
+        let report = c.stamp_metadata(&[("EV", "Cup"), ("RO", "Round {index}"), ("HA", "two")], OverwritePolicy::IfMissing);
+        let actions = report.entries.iter().map(|e| (e.game, &e.id[..], e.action.clone())).collect::<Vec<_>>();
+        assert_eq!(actions, vec![
+            (0, "EV", StampAction::Unchanged), (0, "RO", StampAction::Set), (0, "HA", StampAction::Invalid),
+            (1, "EV", StampAction::Set), (1, "RO", StampAction::Unchanged), (1, "HA", StampAction::Invalid),
+            (2, "EV", StampAction::Unchanged), (2, "RO", StampAction::Unchanged), (2, "HA", StampAction::Invalid),
+            (3, "EV", StampAction::Set), (3, "RO", StampAction::Set), (3, "HA", StampAction::Invalid),
+        ]);
+        assert_eq!(c[0].get_simple_text("RO").unwrap(), "Round 1");
+        assert_eq!(c[1].get_simple_text("RO").unwrap(), "9");
+        assert_eq!(c[2].get_simple_text("EV").unwrap(), "Meijin");
+        assert_eq!(c[3].get_simple_text("RO").unwrap(), "Round 4");
+        assert!(c[3].get_property("HA").is_err());
+    }
+
+    #[test]
+    fn test_stamp_metadata_overwrite() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]EV[Old]KM[6.5])").unwrap();
+        let report = c.stamp_metadata(&[("EV", "New: A]B"), ("KM", " 7.5 ")], OverwritePolicy::IfDifferentWarn);
+        assert_eq!(report.entries[0].action, StampAction::Conflict { existing: "Old".to_string() });
+        assert_eq!(c[0].get_simple_text("EV").unwrap(), "Old");
+        let report = c.stamp_metadata(&[("EV", "New: A]B"), ("KM", " 7.5 ")], OverwritePolicy::Always);
+        assert_eq!(report.entries[1].action, StampAction::Overwritten { previous: "6.5".to_string() });
+        assert_eq!(c[0].get_simple_text("EV").unwrap(), "New: A]B");
+        assert_eq!(c[0].get_real("KM").unwrap(), 7.5);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Statistics of collections
+
+use std::collections::BTreeMap;
+use std::fmt;
+use sgf_node::*;
+use properties::property_spec;
+
+/// Usage of a property in a collection.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PropertyUsage {
+    /// Number of root nodes having the property
+    pub root_count: usize,
+    /// Number of other nodes having the property
+    pub non_root_count: usize,
+    /// First 3 distinct raw values
+    pub examples: Vec<String>,
+    /// True if the property ever has more than one value
+    pub multi_valued: bool,
+    /// True if the property is in `properties::PROPERTIES`
+    pub standard: bool,
+}
+
+/// Result of `property_census`.
+/// Display prints a table sorted by identifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PropertyCensus {
+    pub properties: BTreeMap<String, PropertyUsage>,
+}
+
+/// Counts properties in all nodes of a collection.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let c = SgfCollection::from_sgf("(;FF[4];B[aa];W[bb])").unwrap();
+/// let census = property_census(&c);
+/// assert_eq!(census.properties["B"].non_root_count, 1);
+/// ```
+pub fn property_census(collection: &SgfCollection) -> PropertyCensus {
+    let mut census = PropertyCensus::default();
+    for game in collection.iter() {
+        count_properties(game, true, &mut census);
+    }
+    census
+}
+
+fn count_properties(node: &SgfNode, root: bool, census: &mut PropertyCensus) {
+    for (id, values) in node.iter_values() {
+        let usage = census.properties.entry(id.clone()).or_insert_with(|| PropertyUsage {
+            standard: property_spec(id).is_some(),
+            ..Default::default()
+        });
+        if root {
+            usage.root_count += 1;
+        } else {
+            usage.non_root_count += 1;
+        }
+        usage.multi_valued |= values.len() > 1;
+        for v in values {
+            if usage.examples.len() < 3 && !usage.examples.iter().any(|e| e == v) {
+                usage.examples.push(v.to_string());
+            }
+        }
+    }
+    for child in node.children.iter() {
+        count_properties(child, false, census);
+    }
+}
+
+impl fmt::Display for PropertyCensus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<8} {:>8} {:>8} {:<5} {:<8} examples", "id", "root", "non-root", "multi", "standard")?;
+        for (id, usage) in self.properties.iter() {
+            writeln!(f, "{:<8} {:>8} {:>8} {:<5} {:<8} {}",
+                id, usage.root_count, usage.non_root_count,
+                if usage.multi_valued { "yes" } else { "no" },
+                if usage.standard { "yes" } else { "no" },
+                usage.examples.iter().map(|e| format!("[{}]", e)).collect::<Vec<_>>().join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialized size of a property in `SizeReport`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PropertySize {
+    /// Bytes of the identifier and the bracketed values summed over all nodes
+    pub total: usize,
+    /// Bytes of the largest single value without brackets
+    pub max_value: usize,
+}
+
+/// A value in `SizeReport::largest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeValue {
+    /// Child indices from the node of `size_report` to the node having the value
+    pub path: Vec<usize>,
+    pub id: String,
+    /// Bytes of the value without brackets
+    pub size: usize,
+}
+
+/// Result of `SgfNode::size_report`.
+/// Display prints properties sorted by their total sizes in descending order, followed by the largest values.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SizeReport {
+    /// Bytes of the tree written as a game tree by Display, parentheses included
+    pub total: usize,
+    pub nodes: usize,
+    pub properties: BTreeMap<String, PropertySize>,
+    /// Largest values in descending order of sizes, earlier ones in depth-first order first on ties
+    pub largest: Vec<LargeValue>,
+}
+
+/// A fmt::Write sink counting bytes.
+struct ByteCounter(usize);
+
+impl fmt::Write for ByteCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+impl SgfNode {
+    /// Returns how many bytes each property contributes to the serialized tree, with the top largest values.
+    /// The tree is not serialized into memory.
+    pub fn size_report(&self, top: usize) -> SizeReport {
+        let mut counter = ByteCounter(0);
+        let _ = fmt::Write::write_fmt(&mut counter, format_args!("({})", self));
+        let mut report = SizeReport { total: counter.0, ..Default::default() };
+        measure(self, &mut vec![], top, &mut report);
+        report
+    }
+}
+
+fn measure(node: &SgfNode, path: &mut Vec<usize>, top: usize, report: &mut SizeReport) {
+    report.nodes += 1;
+    for (id, values) in node.iter_values() {
+        let size = report.properties.entry(id.clone()).or_default();
+        size.total += id.len();
+        for v in values {
+            size.total += v.len() + 2;
+            size.max_value = size.max_value.max(v.len());
+            let i = report.largest.partition_point(|l| l.size >= v.len());
+            if i < top {
+                report.largest.insert(i, LargeValue { path: path.clone(), id: id.clone(), size: v.len() });
+                report.largest.truncate(top);
+            }
+        }
+    }
+    for (i, child) in node.children.iter().enumerate() {
+        path.push(i);
+        measure(child, path, top, report);
+        path.pop();
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} bytes in {} nodes", self.total, self.nodes)?;
+        writeln!(f, "{:<8} {:>10} {:>6} {:>10}", "id", "total", "share", "max value")?;
+        let mut properties = self.properties.iter().collect::<Vec<_>>();
+        properties.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+        for (id, size) in properties {
+            let share = if self.total == 0 { 0.0 } else { 100.0 * size.total as f64 / self.total as f64 };
+            writeln!(f, "{:<8} {:>10} {:>5.1}% {:>10}", id, size.total, share, size.max_value)?;
+        }
+        writeln!(f, "largest values")?;
+        for value in self.largest.iter() {
+            let path = value.path.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(".");
+            writeln!(f, "{:<8} {:>10} at [{}]", value.id, value.size, path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_property_census() {
+        let c = SgfCollection::from_sgf("(;FF[4]SZ[19]XX[private];B[aa]C[x];W[bb]C[x])(;FF[3]AB[aa][bb];C[y];C[z];C[w])").unwrap();
+        let census = property_census(&c);
+        assert_eq!(census.properties.len(), 7);
+        let ff = &census.properties["FF"];
+        assert_eq!((ff.root_count, ff.non_root_count), (2, 0));
+        assert_eq!(ff.examples, vec!["4", "3"]);
+        let comment = &census.properties["C"];
+        assert_eq!((comment.root_count, comment.non_root_count), (0, 5));
+        assert_eq!(comment.examples, vec!["x", "y", "z"]);
+        assert!(census.properties["AB"].multi_valued);
+        assert!(!census.properties["B"].multi_valued);
+        let nonstandard = census.properties.iter().filter(|&(_, u)| !u.standard).map(|(id, _)| id.as_str()).collect::<Vec<_>>();
+        assert_eq!(nonstandard, vec!["XX"]);
+        let table = census.to_string();
+        assert_eq!(table.lines().count(), 8);
+        assert!(table.lines().nth(1).unwrap().starts_with("AB "));
+        assert!(table.lines().last().unwrap().starts_with("XX "));
+    }
+
+    #[test]
+    fn test_size_report() {
+        let comment = "x".repeat(100);
+        let sgf = format!("(;FF[4]C[{0}];B[aa](;W[bb]C[{0}{0}])(;W[cc]C[{0}]))", comment);
+        let c = SgfCollection::from_sgf(&sgf).unwrap();
+        let report = c[0].size_report(2);
+        assert_eq!(report.total, sgf.len());
+        assert_eq!(report.nodes, 4);
+        assert_eq!(report.properties["C"], PropertySize { total: 3 + 400 + 3 * 2, max_value: 200 });
+        assert_eq!(report.properties["W"].total, 10);
+        let structure = report.nodes + 6;
+        assert_eq!(report.properties.values().map(|s| s.total).sum::<usize>() + structure, report.total);
+        assert_eq!(report.largest, vec![
+            LargeValue { path: vec![0, 0], id: "C".to_string(), size: 200 },
+            LargeValue { path: vec![], id: "C".to_string(), size: 100 },
+        ]);
+        let table = report.to_string();
+        assert!(table.lines().nth(2).unwrap().starts_with("C ") && table.lines().nth(2).unwrap().contains("93.2%"));
+        assert!(table.lines().last().unwrap().ends_with("at []"));
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Streaming extraction of moves
+
+use std::collections::{HashSet, VecDeque};
+use sgf_node::*;
+use parser::*;
+
+/// Returns an iterator of moves in the main line of the first game in `input`.
+/// Each move is a color and a point, which is None for a pass.
+///
+/// Moves are yielded as soon as they are tokenized without parsing the whole input.
+/// The main line ends at the first ')' so the rest of the input is never read.
+/// When the input is malformed, moves before the error are yielded and then one Err terminates the iteration.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let moves = main_line_moves_streaming("(;FF[4];B[pd];W[](;B[dd])(;B[dp]))").collect::<Vec<_>>();
+/// assert_eq!(moves.len(), 3);
+/// assert_eq!(moves[2].as_ref().unwrap(), &('B', Some("dd".to_string())));
+/// ```
+pub fn main_line_moves_streaming(input: &str) -> MainLineMoves<'_> {
+    MainLineMoves {
+        input,
+        pos: 0,
+        started: false,
+        in_node: false,
+        done: false,
+        small_board: true,
+        pending: VecDeque::new(),
+    }
+}
+
+/// Iterator returned by `main_line_moves_streaming`.
+pub struct MainLineMoves<'a> {
+    input: &'a str,
+    pos: usize,
+    started: bool,
+    in_node: bool,
+    done: bool,
+    small_board: bool,
+    pending: VecDeque<(SgfColor, Option<SgfPoint>)>,
+}
+
+impl<'a> MainLineMoves<'a> {
+    fn error(&mut self, expected: &[&'static str]) -> Option<ParseResult<(SgfColor, Option<SgfPoint>)>> {
+        self.done = true;
+        let before = &self.input[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        Some(Err(ParseError {
+            line,
+            column,
+            offset: self.pos,
+            expected: expected.iter().cloned().collect::<HashSet<_>>(),
+        }))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Reads a property value after '[' and returns it without brackets.
+    fn value(&mut self) -> Option<&'a str> {
+        let bytes = self.input.as_bytes();
+        let start = self.pos;
+        let mut i = start;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b']' => {
+                    self.pos = i + 1;
+                    return Some(&self.input[start..i]);
+                },
+                _ => i += 1,
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for MainLineMoves<'a> {
+    type Item = ParseResult<(SgfColor, Option<SgfPoint>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(m) = self.pending.pop_front() {
+                return Some(Ok(m));
+            }
+            if self.done {
+                return None;
+            }
+            self.skip_whitespace();
+            let c = match self.input[self.pos..].chars().next() {
+                Some(c) => c,
+                None => return self.error(if self.started { &[")"] } else { &["("] }),
+            };
+            if !self.started {
+                if c != '(' {
+                    return self.error(&["("]);
+                }
+                self.started = true;
+                self.pos += 1;
+                continue;
+            }
+            match c {
+                '(' => {
+                    self.in_node = false;
+                    self.pos += 1;
+                },
+                ';' => {
+                    self.in_node = true;
+                    self.pos += 1;
+                },
+                ')' if self.in_node => {
+                    self.done = true;
+                    return None;
+                },
+                'A'..='Z' if self.in_node => {
+                    let start = self.pos;
+                    let len = self.input[start..].find(|c: char| !c.is_ascii_uppercase()).unwrap_or(self.input.len() - start);
+                    let id = &self.input[start..start + len];
+                    self.pos += len;
+                    let mut count = 0;
+                    loop {
+                        self.skip_whitespace();
+                        if !self.input[self.pos..].starts_with('[') {
+                            break;
+                        }
+                        self.pos += 1;
+                        let value = match self.value() {
+                            Some(v) => v,
+                            None => return self.error(&["]"]),
+                        };
+                        count += 1;
+                        match id {
+                            "B" | "W" => {
+                                let point = if value.is_empty() || (self.small_board && value == "tt") {
+                                    None
+                                } else {
+                                    Some(value.to_string())
+                                };
+                                self.pending.push_back((id.chars().next().unwrap(), point));
+                            },
+                            "SZ" => self.small_board = value.split(':').next()
+                                .and_then(|s| s.parse::<i32>().ok()).map(|s| s <= 19).unwrap_or(true),
+                            _ => (),
+                        }
+                    }
+                    if count == 0 {
+                        return self.error(&["["]);
+                    }
+                },
+                _ if self.in_node => return self.error(&[";", "(", ")", "[A-Z]"]),
+                _ => return self.error(&[";"]),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eager_main_line(node: &SgfNode) -> Vec<(SgfColor, Option<SgfPoint>)> {
+        let mut moves = Vec::new();
+        let mut node = node;
+        loop {
+            for color in ['B', 'W'].iter() {
+                if let Ok(p) = node.get_point(&color.to_string()) {
+                    moves.push((*color, if p.is_empty() || p == "tt" { None } else { Some(p) }));
+                }
+            }
+            if node.children.is_empty() {
+                return moves;
+            }
+            node = &node.children[0];
+        }
+    }
+
+    #[test]
+    fn test_main_line_moves_streaming() {
+        let sgf = "(;FF[4]C[a (comment\\]]
+            (;B[pd];W[dd]C[main]
+                (;B[pp];W[tt];B[dp])
+                (;B[dp]))
+            (;B[dd]))";
+        let streamed = main_line_moves_streaming(sgf).collect::<ParseResult<Vec<_>>>().unwrap();
+        let c = SgfCollection::from_sgf(sgf).unwrap();
+        assert_eq!(streamed, eager_main_line(&c[0]));
+        assert_eq!(streamed.len(), 5);
+    }
+
+    #[test]
+    fn test_main_line_moves_streaming_error() {
+        let moves = main_line_moves_streaming("(;FF[4];B[aa];W[bb];B[cc]!W[dd])").collect::<Vec<_>>();
+        assert_eq!(moves.len(), 4);
+        assert!(moves[..3].iter().all(|m| m.is_ok()));
+        assert_eq!(moves[3].as_ref().unwrap_err().offset, 25);
+    }
+}
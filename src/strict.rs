@@ -0,0 +1,292 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Strict accessors validating values against the specification
+
+use regex::Regex;
+use sgf_node::*;
+
+/// A wrapper of SgfNode whose getters validate whole values against their types in the specification.
+///
+/// Lenient getters of SgfNode lose information in some cases, which are detected here.
+///
+/// * `get_point` of SgfNode returns the first value and ignores the rest. Here it is `SgfError::WrongArity`.
+/// * `get_color` and `get_double` of SgfNode return the first character of the value,
+///   so "Black" is 'B', "b" is 'b' and "7" is '7'. Here they are `SgfError::TrailingGarbage` or `SgfError::OutOfRange`.
+/// * `get_real` of SgfNode accepts forms which f32 accepts such as "1e3" or "inf".
+///   Here they are `SgfError::TrailingGarbage` or `SgfError::ParseError`.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let c = SgfCollection::from_sgf("(;FF[4]PL[Black])").unwrap();
+/// assert_eq!(c[0].get_color("PL").unwrap(), 'B');
+/// assert!(c[0].strict().get_color("PL").is_err());
+/// ```
+pub struct StrictNode<'a>(&'a SgfNode);
+
+impl SgfNode {
+    /// Returns a wrapper with strict getters.
+    pub fn strict(&self) -> StrictNode<'_> {
+        StrictNode(self)
+    }
+}
+
+impl<'a> StrictNode<'a> {
+    fn single(&self, id: &str) -> Result<&'a str, SgfError> {
+        self.0.get_property(id).and_then(|v|
+            if v.len() == 1 {
+                Ok(&v[0][..])
+            } else {
+                Err(SgfError::WrongArity { expected: 1, found: v.len() })
+            }
+        )
+    }
+
+    /// Returns a Result of id's value as SgfPoint. An empty value(pass) is accepted.
+    pub fn get_point(&self, id: &str) -> Result<SgfPoint, SgfError> {
+        self.single(id).and_then(|v| if v.is_empty() { Ok(v.to_string()) } else { validate_point(id, v) })
+    }
+
+    /// Returns a Result of id's value as a vector of SgfPoint.
+    /// Each value should be a point or a compressed point list like "aa:cc".
+    pub fn get_points(&self, id: &str) -> Result<Vec<SgfPoint>, SgfError> {
+        self.0.get_property(id).and_then(|v|
+            v.iter().map(|p| {
+                let mut compose = p.splitn(2, ':');
+                let f = compose.next().unwrap();
+                validate_point(id, f).and_then(|f| match compose.next() {
+                    Some(s) => validate_point(id, s).map(|s| format!("{}:{}", f, s)),
+                    None => Ok(f),
+                })
+            }).collect()
+        )
+    }
+
+    /// Returns a Result of id's value as SgfNumber.
+    pub fn get_number(&self, id: &str) -> Result<SgfNumber, SgfError> {
+        self.single(id).and_then(|v| validate_number(id, v))
+    }
+
+    /// Returns a Result of id's value as SgfReal.
+    pub fn get_real(&self, id: &str) -> Result<SgfReal, SgfError> {
+        self.single(id).and_then(|v| validate_real(id, v))
+    }
+
+    /// Returns a Result of id's value as SgfColor, which should be "B" or "W".
+    pub fn get_color(&self, id: &str) -> Result<SgfColor, SgfError> {
+        self.single(id).and_then(|v| match v {
+            "B" => Ok('B'),
+            "W" => Ok('W'),
+            _ if v.starts_with('B') || v.starts_with('W') => Err(SgfError::TrailingGarbage(v.to_string())),
+            _ => Err(SgfError::OutOfRange(v.to_string())),
+        })
+    }
+
+    /// Returns a Result of id's value as SgfDouble, which should be "1" or "2".
+    pub fn get_double(&self, id: &str) -> Result<SgfDouble, SgfError> {
+        self.single(id).and_then(|v| match v {
+            "1" => Ok('1'),
+            "2" => Ok('2'),
+            _ if v.starts_with('1') || v.starts_with('2') => Err(SgfError::TrailingGarbage(v.to_string())),
+            _ => Err(SgfError::OutOfRange(v.to_string())),
+        })
+    }
+
+    /// Returns a Result of id's value as SgfText.
+    pub fn get_text(&self, id: &str) -> Result<SgfText, SgfError> {
+        self.single(id).map(decode_text)
+    }
+
+    /// Returns a Result of id's value as SgfSimpleText.
+    pub fn get_simple_text(&self, id: &str) -> Result<SgfSimpleText, SgfError> {
+        self.single(id).map(decode_simple_text)
+    }
+
+    /// Returns a Result of id's value as Compose of SgfPoints.
+    pub fn get_point_point(&self, id: &str) -> Result<(SgfPoint, SgfPoint), SgfError> {
+        self.single(id).and_then(split_compose).and_then(|(f, s)|
+            validate_point(id, f).and_then(|f| validate_point(id, s).map(|s| (f, s))))
+    }
+
+    /// Returns a Result of id's value as Compose of SgfPoint and SgfSimpleText.
+    pub fn get_point_simple_text(&self, id: &str) -> Result<(SgfPoint, SgfSimpleText), SgfError> {
+        self.single(id).and_then(split_compose).and_then(|(f, s)|
+            validate_point(id, f).map(|f| (f, decode_simple_text(s))))
+    }
+
+    /// Returns a Result of id's value as Compose of SgfSimpleTexts.
+    pub fn get_simple_text_simple_text(&self, id: &str) -> Result<(SgfSimpleText, SgfSimpleText), SgfError> {
+        self.single(id).and_then(split_compose).map(|(f, s)| (decode_simple_text(f), decode_simple_text(s)))
+    }
+
+    /// Returns a Result of id's value as Compose of SgfNumbers.
+    pub fn get_number_number(&self, id: &str) -> Result<(SgfNumber, SgfNumber), SgfError> {
+        self.single(id).and_then(split_compose).and_then(|(f, s)|
+            validate_number(id, f).and_then(|f| validate_number(id, s).map(|s| (f, s))))
+    }
+
+    /// Returns a Result of id's value as Compose of SgfNumber and SgfSimpleText.
+    pub fn get_number_simple_text(&self, id: &str) -> Result<(SgfNumber, SgfSimpleText), SgfError> {
+        self.single(id).and_then(split_compose).and_then(|(f, s)|
+            validate_number(id, f).map(|f| (f, decode_simple_text(s))))
+    }
+}
+
+impl SgfNode {
+    /// Returns a Result of the value of KM parsed by `parse_real`.
+    pub fn get_komi(&self) -> Result<SgfReal, SgfError> {
+        self.get_property("KM").and_then(|v| parse_real_of("KM", &v[0]))
+    }
+}
+
+/// Parses a Real value such as komi, accepting a decimal comma seen in files from some locales.
+///
+/// Surrounding whitespace is ignored and a single ',' without '.' is read as a decimal point, so "6,5" is 6.5.
+/// Otherwise the value is validated as by `StrictNode::get_real`.
+/// Returns ParseError if the value doesn't begin with digits and TrailingGarbage if the digits are followed
+/// by other characters, as in "1e3", "6.5.1" or "1,000.5". The id of ParseError is empty.
+pub fn parse_real(v: &str) -> Result<SgfReal, SgfError> {
+    parse_real_of("", v)
+}
+
+fn parse_real_of(id: &str, v: &str) -> Result<SgfReal, SgfError> {
+    let trimmed = v.trim();
+    if trimmed.matches(',').count() == 1 && !trimmed.contains('.') {
+        validate_real(id, &trimmed.replace(',', ".")).map_err(|e| match e {
+            SgfError::ParseError { .. } => parse_error(id, v),
+            SgfError::TrailingGarbage(_) => SgfError::TrailingGarbage(v.to_string()),
+            e => e,
+        })
+    } else {
+        validate_real(id, trimmed)
+    }
+}
+
+/// Splits a compose value at the first unescaped ':'.
+fn split_compose(v: &str) -> Result<(&str, &str), SgfError> {
+    let mut escaped = false;
+    for (i, c) in v.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            ':' if !escaped => return Ok((&v[..i], &v[i + 1..])),
+            _ => escaped = false,
+        }
+    }
+    Err(SgfError::EmptyProperty)
+}
+
+fn validate_point(id: &str, v: &str) -> Result<SgfPoint, SgfError> {
+    let is_letter = |c: char| c.is_ascii_alphabetic();
+    let chars = v.chars().collect::<Vec<_>>();
+    if chars.len() >= 2 && is_letter(chars[0]) && is_letter(chars[1]) {
+        if chars.len() == 2 {
+            Ok(v.to_string())
+        } else {
+            Err(SgfError::TrailingGarbage(v.to_string()))
+        }
+    } else {
+        Err(parse_error(id, v))
+    }
+}
+
+thread_local! {
+    // compiled once per thread since they are used for every number and real value
+    static NUMBER: Regex = Regex::new(r"^([+-]?[0-9]+)(.*)$").unwrap();
+    static REAL: Regex = Regex::new(r"^([+-]?[0-9]+(\.[0-9]+)?)(.*)$").unwrap();
+}
+
+fn validate_number(id: &str, v: &str) -> Result<SgfNumber, SgfError> {
+    NUMBER.with(|re| match re.captures(v) {
+        Some(ref caps) if caps.at(2) == Some("") => v.parse::<SgfNumber>().map_err(|_| SgfError::OutOfRange(v.to_string())),
+        Some(_) => Err(SgfError::TrailingGarbage(v.to_string())),
+        None => Err(parse_error(id, v)),
+    })
+}
+
+fn validate_real(id: &str, v: &str) -> Result<SgfReal, SgfError> {
+    REAL.with(|re| match re.captures(v) {
+        Some(ref caps) if caps.at(3) == Some("") => v.parse::<SgfReal>().map_err(|_| parse_error(id, v)),
+        Some(_) => Err(SgfError::TrailingGarbage(v.to_string())),
+        None => Err(parse_error(id, v)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(sgf: &str) -> SgfNode {
+        SgfCollection::from_sgf(sgf).unwrap()[0].clone()
+    }
+
+    #[test]
+    fn test_strict_point_arity() {
+        let node = root("(;B[aa][bb])");
+        assert_eq!(node.get_point("B").unwrap(), "aa"); // lenient ignores the rest
+        match node.strict().get_point("B") {
+            Err(SgfError::WrongArity { expected: 1, found: 2 }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        assert_eq!(root("(;B[])").strict().get_point("B").unwrap(), "");
+        assert!(root("(;B[aab])").strict().get_point("B").is_err());
+    }
+
+    #[test]
+    fn test_strict_color() {
+        assert_eq!(root("(;PL[Black])").get_color("PL").unwrap(), 'B'); // lenient takes the first character
+        assert_eq!(root("(;PL[b])").get_color("PL").unwrap(), 'b'); // lenient takes the first character
+        match root("(;PL[Black])").strict().get_color("PL") {
+            Err(SgfError::TrailingGarbage(ref v)) if v == "Black" => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        match root("(;PL[b])").strict().get_color("PL") {
+            Err(SgfError::OutOfRange(ref v)) if v == "b" => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        assert_eq!(root("(;PL[W])").strict().get_color("PL").unwrap(), 'W');
+    }
+
+    #[test]
+    fn test_strict_double() {
+        assert_eq!(root("(;GB[7])").get_double("GB").unwrap(), '7'); // lenient accepts any character
+        match root("(;GB[7])").strict().get_double("GB") {
+            Err(SgfError::OutOfRange(ref v)) if v == "7" => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        assert_eq!(root("(;GB[2])").strict().get_double("GB").unwrap(), '2');
+    }
+
+    #[test]
+    fn test_strict_real_and_number() {
+        assert_eq!(root("(;KM[1e1])").get_real("KM").unwrap(), 10.0); // lenient accepts forms of f32
+        assert!(root("(;KM[1e1])").strict().get_real("KM").is_err());
+        assert_eq!(root("(;KM[6.5])").strict().get_real("KM").unwrap(), 6.5);
+        match root("(;SZ[19x])").strict().get_number("SZ") {
+            Err(SgfError::TrailingGarbage(_)) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        assert_eq!(root("(;SZ[19:13])").strict().get_number_number("SZ").unwrap(), (19, 13));
+    }
+
+    #[test]
+    fn test_parse_real() {
+        assert_eq!(parse_real("6.5").unwrap(), 6.5);
+        assert_eq!(parse_real("6,5").unwrap(), 6.5);
+        assert_eq!(parse_real(" -0,5 ").unwrap(), -0.5);
+        assert_eq!(parse_real("7").unwrap(), 7.0);
+        match parse_real("1e3") {
+            Err(SgfError::TrailingGarbage(ref v)) if v == "1e3" => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        assert!(matches!(parse_real("6.5.1"), Err(SgfError::TrailingGarbage(_))));
+        assert!(matches!(parse_real("1,000.5"), Err(SgfError::TrailingGarbage(_))));
+        assert!(matches!(parse_real("6,5,0"), Err(SgfError::TrailingGarbage(_))));
+        assert!(matches!(parse_real("inf"), Err(SgfError::ParseError { .. })));
+        assert!(matches!(parse_real(""), Err(SgfError::ParseError { .. })));
+        assert_eq!(root("(;KM[6,5])").get_komi().unwrap(), 6.5);
+        assert!(root("(;KM[6,5])").get_real("KM").is_err());
+    }
+}
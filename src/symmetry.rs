@@ -0,0 +1,170 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Board symmetries
+
+use sgf_node::*;
+use board::BoardSpec;
+
+/// One of the 8 symmetries of a square board.
+/// Rotations are clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirror with the vertical axis (left <-> right)
+    FlipHorizontal,
+    /// Mirror with the horizontal axis (top <-> bottom)
+    FlipVertical,
+    /// Mirror with the diagonal from top-left to bottom-right
+    Transpose,
+    /// Mirror with the diagonal from top-right to bottom-left
+    AntiTranspose,
+}
+
+impl Symmetry {
+    /// Returns all 8 symmetries.
+    pub fn all() -> [Symmetry; 8] {
+        [Symmetry::Identity, Symmetry::Rotate90, Symmetry::Rotate180, Symmetry::Rotate270,
+         Symmetry::FlipHorizontal, Symmetry::FlipVertical, Symmetry::Transpose, Symmetry::AntiTranspose]
+    }
+
+    /// Transforms zero-based coordinates (x, y) on a board of `size`.
+    pub fn apply(&self, (x, y): (u8, u8), size: u8) -> (u8, u8) {
+        let n = size - 1;
+        match *self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (n - y, x),
+            Symmetry::Rotate180 => (n - x, n - y),
+            Symmetry::Rotate270 => (y, n - x),
+            Symmetry::FlipHorizontal => (n - x, y),
+            Symmetry::FlipVertical => (x, n - y),
+            Symmetry::Transpose => (y, x),
+            Symmetry::AntiTranspose => (n - y, n - x),
+        }
+    }
+}
+
+/// Properties whose values are points, lists of points or composed values beginning with points.
+/// Their values are rewritten by `SgfNode::transform`.
+///
+/// * moves: B, W
+/// * setup: AB, AE, AW
+/// * markup: CR, MA, SL, SQ, TR, DD, AR, LN, LB (the point part only)
+/// * territory and view: TB, TW, VW
+pub static POINT_PROPERTIES: &[&str] = &[
+    "B", "W", "AB", "AE", "AW", "CR", "MA", "SL", "SQ", "TR", "DD", "AR", "LN", "LB", "TB", "TW", "VW",
+];
+
+impl SgfNode {
+    /// Applies a symmetry to every point value of the node and its descendants on a square board of `size`.
+    /// Compressed point lists are transformed into compressed point lists of the same rectangles.
+    /// Passes(empty values and "tt" on boards up to 19x19) and values which are not valid points are left as they are.
+    /// See `POINT_PROPERTIES` for the transformed properties.
+    /// Returns UnsupportedBoard for Coordinates without modifying the node if size is out of 1 to 52.
+    pub fn transform(&mut self, sym: Symmetry, size: u8) -> Result<(), SgfError> {
+        BoardSpec::new(size, size)?;
+        transform_node(self, sym, size);
+        Ok(())
+    }
+}
+
+fn transform_node(node: &mut SgfNode, sym: Symmetry, size: u8) {
+    for (id, values) in node.iter_values_mut() {
+        match &id[..] {
+            "AR" | "LN" => for v in values.iter_mut() {
+                let mut compose = v.splitn(2, ':');
+                let f = transform_point(compose.next().unwrap(), sym, size);
+                let s = compose.next().map(|s| transform_point(s, sym, size));
+                *v = match s {
+                    Some(s) => format!("{}:{}", f, s),
+                    None => f,
+                }.into();
+            },
+            "LB" => for v in values.iter_mut() {
+                let t = match v.find(':') {
+                    Some(i) => format!("{}{}", transform_point(&v[..i], sym, size), &v[i..]),
+                    None => continue,
+                };
+                *v = t.into();
+            },
+            id if POINT_PROPERTIES.contains(&id) => for v in values.iter_mut() {
+                *v = transform_point_or_rectangle(v, sym, size).into();
+            },
+            _ => (),
+        }
+    }
+    for child in node.children.iter_mut() {
+        transform_node(child, sym, size);
+    }
+}
+
+fn transform_point(p: &str, sym: Symmetry, size: u8) -> String {
+    if size <= 19 && p == "tt" {
+        return p.to_string();
+    }
+    match point_to_coord(p) {
+        Some((x, y)) if x < size && y < size => coord_to_point(sym.apply((x, y), size)),
+        _ => p.to_string(),
+    }
+}
+
+fn transform_point_or_rectangle(v: &str, sym: Symmetry, size: u8) -> String {
+    let mut compose = v.splitn(2, ':');
+    let f = compose.next().unwrap();
+    match compose.next() {
+        None => transform_point(f, sym, size),
+        Some(s) => match (point_to_coord(f), point_to_coord(s)) {
+            (Some(a), Some(b)) if a.0 < size && a.1 < size && b.0 < size && b.1 < size => {
+                let a = sym.apply(a, size);
+                let b = sym.apply(b, size);
+                format!("{}:{}",
+                    coord_to_point((a.0.min(b.0), a.1.min(b.1))),
+                    coord_to_point((a.0.max(b.0), a.1.max(b.1))))
+            },
+            _ => v.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform() {
+        let mut node = SgfCollection::from_sgf("(;FF[4]SZ[19]AB[aa:bc]LB[pd:A]C[pd];B[pd];W[tt];B[]AR[aa:sa])").unwrap()[0].clone();
+        node.transform(Symmetry::Rotate90, 19).unwrap();
+        assert_eq!(node.get_points("AB").unwrap(), vec!["qa:sb".to_string()]);
+        assert_eq!(node.get_point_simple_text("LB").unwrap(), ("pp".to_string(), "A".to_string()));
+        assert_eq!(node.get_text("C").unwrap(), "pd");
+        assert_eq!(node.children[0].get_point("B").unwrap(), "pp");
+        assert_eq!(node.children[0].children[0].get_point("W").unwrap(), "tt");
+        let last = &node.children[0].children[0].children[0];
+        assert_eq!(last.get_point("B").unwrap(), "");
+        assert_eq!(last.get_point_point("AR").unwrap(), ("sa".to_string(), "ss".to_string()));
+    }
+
+    #[test]
+    fn test_symmetries() {
+        let original = SgfCollection::from_sgf("(;FF[4]SZ[9]AW[bc][ch:ei];B[ab])").unwrap()[0].clone();
+        for sym in Symmetry::all().iter() {
+            let mut node = original.clone();
+            node.transform(*sym, 9).unwrap();
+            let inverse = match *sym {
+                Symmetry::Rotate90 => Symmetry::Rotate270,
+                Symmetry::Rotate270 => Symmetry::Rotate90,
+                s => s,
+            };
+            node.transform(inverse, 9).unwrap();
+            assert_eq!(node, original);
+        }
+        let mut node = original.clone();
+        node.transform(Symmetry::AntiTranspose, 9).unwrap();
+        assert_eq!(node.children[0].get_point("B").unwrap(), "hi");
+        assert!(matches!(node.transform(Symmetry::Rotate90, 60), Err(SgfError::UnsupportedBoard { .. })));
+        assert_eq!(node.children[0].get_point("B").unwrap(), "hi");
+    }
+}
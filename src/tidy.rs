@@ -0,0 +1,583 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Clean-up of trees
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use regex::{Captures, Regex};
+use sgf_node::*;
+use prop_value::PropValue;
+use board::BoardFeature;
+use properties::{canonical_index, property_spec, PropertyType, ValueType};
+
+const COMMENT_SEPARATOR: &str = "\n\n";
+
+impl SgfNode {
+    /// Merges consecutive nodes without moves into one node and returns the number of removed nodes.
+    ///
+    /// A node is merged into its parent if neither of them has move properties(B, W and other properties of move type)
+    /// and the parent has only the node as its child. The node's children become the parent's children.
+    /// Values of list properties such as AB and TR are unioned, and comments(C, GC) are concatenated with an empty line.
+    /// Nodes are not merged if the merge would change the position, that is, a point in a setup property
+    /// of the child appears in another setup property of the parent, or if they have different values
+    /// of the same other property.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sgf::*;
+    ///
+    /// let mut c = SgfCollection::from_sgf("(;FF[4];AB[aa];AB[bb];AB[cc];W[dd])").unwrap();
+    /// assert_eq!(c[0].coalesce_setup_nodes(), 3);
+    /// assert_eq!(c[0].get_points("AB").unwrap().len(), 3);
+    /// ```
+    pub fn coalesce_setup_nodes(&mut self) -> usize {
+        let mut removed = 0;
+        while self.children.len() == 1 && !has_move(self) && !has_move(&self.children[0]) && can_merge(self, &self.children[0]) {
+            let child = self.children.remove(0);
+            merge_node(self, child);
+            removed += 1;
+        }
+        for child in self.children.iter_mut() {
+            removed += child.coalesce_setup_nodes();
+        }
+        removed
+    }
+}
+
+impl SgfNode {
+    /// Removes descendant nodes without any properties.
+    /// Children of a removed node take its place among its siblings in order.
+    /// The node itself is kept even if it is empty, and nodes with setup properties or moves are never removed.
+    pub fn remove_empty_nodes(&mut self) {
+        let children = mem::take(&mut self.children);
+        for mut child in children {
+            child.remove_empty_nodes();
+            if child.iter_values().next().is_none() {
+                self.children.append(&mut child.children);
+            } else {
+                self.children.push(child);
+            }
+        }
+    }
+}
+
+impl SgfNode {
+    /// Removes repeated values of point-set properties in the tree, keeping the first of each,
+    /// and returns the number of removed values.
+    ///
+    /// Point-set properties are those of list or elist of point type in FF[4]: AB, AW, AE, CR, MA, SL, SQ, TR,
+    /// DD, VW, TB and TW. AR, LN and LB are ordered lists of pairs and left as they are,
+    /// and so are unknown properties. Only identical values are duplicates, so "aa" overlapping "aa:bb" is kept.
+    pub fn dedup_point_lists(&mut self) -> usize {
+        let mut removed = 0;
+        for (id, values) in self.iter_values_mut() {
+            let is_set = property_spec(id)
+                .map_or(false, |spec| matches!(spec.value_type, ValueType::ListOfPoint | ValueType::EListOfPoint));
+            if is_set {
+                let len = values.len();
+                let mut seen = Vec::with_capacity(len);
+                values.retain(|v| if seen.contains(v) { false } else { seen.push(v.clone()); true });
+                removed += len - values.len();
+            }
+        }
+        for child in self.children.iter_mut() {
+            removed += child.dedup_point_lists();
+        }
+        removed
+    }
+}
+
+impl SgfCollection {
+    /// Rewrites every game into a canonical form so that semantically identical collections
+    /// serialize byte-identically by Display, for deduplication and version control.
+    ///
+    /// The transforms for each node are
+    ///
+    /// * values of point-set properties(AB, AW, AE, CR, MA, SL, SQ, TR, DD, VW, TB and TW) are expanded,
+    ///   deduplicated and compressed again by `set_compressed_points`. Lists with invalid values,
+    ///   including an empty elist, are only deduplicated and sorted as strings.
+    /// * B and W of "tt" on boards up to 19x19 become "", the FF[4] pass.
+    /// * Text values are decoded and encoded again, which removes soft line breaks and unnecessary escapes
+    ///   and escapes every ']', '\\' and ':'. SimpleText values are also decoded, so their line breaks become spaces.
+    /// * leading and trailing whitespaces are trimmed from values of other types except composed ones,
+    ///   and from RE and DT, as `ParseOptions::normalize_values` does.
+    /// * properties are sorted as `write_sgf_canonical` does.
+    ///
+    /// Point sets are only deduplicated and sorted, and "tt" is kept, in games whose SZ is not supported.
+    /// Nonstandard properties, composed values and the structure of trees are kept as they are.
+    /// The original formatting, such as the order of properties and line breaks in values, is lost.
+    pub fn canonicalize(&mut self) {
+        for game in self.iter_mut() {
+            // SZ is trimmed before it is read, as it will be
+            let sz = game.get_property("SZ").map(|values| values.iter().map(|v| v.trim().to_string()).collect::<Vec<_>>());
+            let sz = SgfNode::new(sz.map(|values| vec![("SZ".to_string(), values)]).unwrap_or_default());
+            let size = sz.board_size(BoardFeature::Coordinates).ok();
+            canonicalize_node(game, size);
+        }
+    }
+}
+
+fn canonicalize_node(node: &mut SgfNode, size: Option<(u8, u8)>) {
+    let small = match size {
+        Some((width, height)) => width <= 19 && height <= 19,
+        None => false,
+    };
+    let mut properties = node.iter_values().map(|(id, values)| (id.clone(), values.clone())).collect::<Vec<_>>();
+    for &mut (ref id, ref mut values) in properties.iter_mut() {
+        let value_type = match property_spec(id) {
+            Some(spec) => spec.value_type,
+            None => continue,
+        };
+        match value_type {
+            ValueType::ListOfPoint | ValueType::EListOfPoint => {
+                let mut point_node = SgfNode::new(Vec::new());
+                point_node.set_property(id, values.clone());
+                match size.map(|(width, height)| point_node.get_expanded_points(id, width, height)) {
+                    Some(Ok(ref points)) if !points.is_empty() => {
+                        let coords = points.iter().filter_map(|p| point_to_coord(p)).collect::<Vec<_>>();
+                        point_node.set_compressed_points(id, &coords);
+                        *values = point_node.get_property(id).cloned().unwrap_or_default();
+                    },
+                    _ => {
+                        values.sort();
+                        values.dedup();
+                    },
+                }
+            },
+            ValueType::Text => for v in values.iter_mut() {
+                *v = encode_text(&decode_text(v)).into();
+            },
+            ValueType::SimpleText => for v in values.iter_mut() {
+                let decoded = decode_simple_text(v);
+                *v = encode_text(if id == "RE" || id == "DT" { decoded.trim() } else { &decoded }).into();
+            },
+            ValueType::SimpleTextSimpleText | ValueType::ListOfPointSimpleText |
+            ValueType::NoneOrNumberSimpleText | ValueType::ListOfPointPoint => (),
+            _ => for v in values.iter_mut() {
+                *v = v.trim().into();
+                if (id == "B" || id == "W") && *v == "tt" && small {
+                    *v = PropValue::default();
+                }
+            },
+        }
+    }
+    properties.sort_by(|a, b| (canonical_index(&a.0), &a.0).cmp(&(canonical_index(&b.0), &b.0)));
+    let children = mem::take(&mut node.children);
+    *node = SgfNode::new(Vec::new());
+    for (id, values) in properties {
+        node.set_property(&id, values);
+    }
+    node.children = children;
+    for child in node.children.iter_mut() {
+        canonicalize_node(child, size);
+    }
+}
+
+/// Default words of `SgfNode::relabel_variation_references`.
+pub const DEFAULT_VARIATION_WORDS: &[&str] = &["variation", "var."];
+
+/// A reference rewritten by `SgfNode::relabel_variation_references`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceRewrite {
+    /// Child indices from the node of `relabel_variation_references` to the node whose C was rewritten
+    pub path: Vec<usize>,
+    /// The reference as it was, like "variation A"
+    pub original: String,
+    /// The reference as it is now, like "variation B"
+    pub rewritten: String,
+}
+
+impl SgfNode {
+    /// Rewrites references to variations by letters in comments(C) after the variations at `at_path` were reordered,
+    /// and returns every rewrite in the order of `iter_depth_first` and then of positions in comments.
+    ///
+    /// This is heuristic text surgery, so nothing calls it implicitly.
+    /// A reference is one of `words`(`DEFAULT_VARIATION_WORDS` or localized ones), case-insensitively,
+    /// followed by whitespaces and a single uppercase letter 'A'-'Z' forming a whole word, like "Variation B".
+    /// Letter 'A' is the child at index 0 of the node at `at_path`.
+    /// `mapping` maps old indices to new ones and letters of indices not in `mapping` are kept.
+    /// Comments of the node at `at_path` and all its descendants are rewritten. An invalid path rewrites nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sgf::*;
+    ///
+    /// let mut c = SgfCollection::from_sgf("(;C[See variation B.](;B[aa])(;B[bb]))").unwrap();
+    /// c[0].children.swap(0, 1);
+    /// c[0].relabel_variation_references(&[], &[(0, 1), (1, 0)], DEFAULT_VARIATION_WORDS);
+    /// assert_eq!(c[0].get_text("C").unwrap(), "See variation A.");
+    /// ```
+    pub fn relabel_variation_references(&mut self, at_path: &[usize], mapping: &[(usize, usize)], words: &[&str]) -> Vec<ReferenceRewrite> {
+        let mut rewrites = Vec::new();
+        if words.is_empty() {
+            return rewrites;
+        }
+        let alternatives = words.iter().map(|w| ::regex::quote(w)).collect::<Vec<_>>().join("|");
+        let re = Regex::new(&format!(r"\b((?i:{}))(\s+)([A-Z])\b", alternatives)).expect("quoted words are valid");
+        if let Some(node) = self.node_at_mut(at_path) {
+            relabel_references(node, &mut at_path.to_vec(), mapping, &re, &mut rewrites);
+        }
+        rewrites
+    }
+}
+
+fn relabel_references(node: &mut SgfNode, path: &mut Vec<usize>, mapping: &[(usize, usize)], re: &Regex, rewrites: &mut Vec<ReferenceRewrite>) {
+    for (_, values) in node.iter_values_mut().filter(|p| p.0 == "C") {
+        for v in values.iter_mut() {
+            *v = re.replace_all(v, |caps: &Captures| {
+                let original = caps.at(0).unwrap_or("");
+                let letter = caps.at(3).and_then(|l| l.bytes().next()).unwrap_or(b'A');
+                let new_index = mapping.iter().find(|m| m.0 == (letter - b'A') as usize).map(|m| m.1);
+                match new_index {
+                    Some(i) if i < 26 && i != (letter - b'A') as usize => {
+                        let rewritten = format!("{}{}{}", caps.at(1).unwrap_or(""), caps.at(2).unwrap_or(""), (b'A' + i as u8) as char);
+                        rewrites.push(ReferenceRewrite { path: path.clone(), original: original.to_string(), rewritten: rewritten.clone() });
+                        rewritten
+                    },
+                    _ => original.to_string(),
+                }
+            }).into();
+        }
+    }
+    for (i, child) in node.children.iter_mut().enumerate() {
+        path.push(i);
+        relabel_references(child, path, mapping, re, rewrites);
+        path.pop();
+    }
+}
+
+/// How `SgfNode::dedup_sibling_variations` compares variations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariationCompare {
+    /// Variations are duplicates if their subtrees are equal including all properties.
+    Exact,
+    /// Variations are duplicates if their subtrees have the same shape and the same B and W in each node.
+    /// If merge_comments is true, comments(C) of a removed duplicate are appended to the corresponding nodes
+    /// of the kept one unless they are the same.
+    MovesOnly { merge_comments: bool },
+}
+
+impl SgfNode {
+    /// Removes later variations which are duplicates of an earlier sibling throughout the tree
+    /// and returns the number of removed variations.
+    /// Subtrees are compared by hashes first and then compared in full only if hashes are equal.
+    pub fn dedup_sibling_variations(&mut self, compare: VariationCompare) -> usize {
+        dedup_variations(self, compare).0
+    }
+}
+
+/// Options of `SgfNode::trim_trailing_noise`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TrimOptions {
+    /// Also removes nodes with only a pass following two nodes with passes, which have already ended the game.
+    pub extra_passes: bool,
+}
+
+impl SgfNode {
+    /// Removes noise at the end of the main line, which servers add after the game is decided,
+    /// and returns the number of removed nodes.
+    ///
+    /// Nodes without properties are removed, and nodes with only a pass(B or W) too if `options.extra_passes`.
+    /// A pass is an empty value, or "tt" when the board(SZ, 19x19 by default) is up to 19x19.
+    /// Only the last nodes of the main line without siblings are removed, so variations are left untouched,
+    /// and the node itself is never removed.
+    /// Returns the error of `board_spec` if SZ is not supported.
+    pub fn trim_trailing_noise(&mut self, options: TrimOptions) -> Result<usize, SgfError> {
+        let (width, height) = self.board_size(BoardFeature::Coordinates)?;
+        let is_pass = |node: &SgfNode| ["B", "W"].iter().any(|id| match node.get_point(id) {
+            Ok(p) => p.is_empty() || (p == "tt" && width <= 19 && height <= 19),
+            Err(_) => false,
+        });
+        let mut removed = 0;
+        loop {
+            let mut line = vec![&*self];
+            while let Some(child) = line[line.len() - 1].children.first() {
+                line.push(child);
+            }
+            let n = line.len();
+            if n < 2 || line[n - 2].children.len() != 1 {
+                break;
+            }
+            let last = line[n - 1];
+            let noise = last.iter().next().is_none() || (options.extra_passes && n >= 4 &&
+                last.iter().all(|(id, _)| id == "B" || id == "W") && is_pass(last) && is_pass(line[n - 2]) && is_pass(line[n - 3]));
+            if !noise {
+                break;
+            }
+            let mut node = &mut *self;
+            for _ in 0..n - 2 {
+                node = &mut node.children[0];
+            }
+            node.children.clear();
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+/// Returns the number of removed variations and the hash of the subtree after removal.
+fn dedup_variations(node: &mut SgfNode, compare: VariationCompare) -> (usize, u64) {
+    let mut removed = 0;
+    let mut hashes = Vec::with_capacity(node.children.len());
+    for child in node.children.iter_mut() {
+        let (n, hash) = dedup_variations(child, compare);
+        removed += n;
+        hashes.push(hash);
+    }
+    let mut kept: Vec<SgfNode> = Vec::with_capacity(node.children.len());
+    let mut kept_hashes = Vec::with_capacity(node.children.len());
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (child, hash) in node.children.drain(..).zip(hashes) {
+        let duplicate = by_hash.get(&hash).and_then(|indices|
+            indices.iter().cloned().find(|&i| same_variation(&kept[i], &child, compare)));
+        match duplicate {
+            Some(i) => {
+                if compare == (VariationCompare::MovesOnly { merge_comments: true }) {
+                    merge_comments(&mut kept[i], &child);
+                }
+                removed += 1;
+            },
+            None => {
+                by_hash.entry(hash).or_default().push(kept.len());
+                kept.push(child);
+                kept_hashes.push(hash);
+            },
+        }
+    }
+    node.children = kept;
+    let mut hasher = DefaultHasher::new();
+    match compare {
+        VariationCompare::Exact => {
+            let mut properties = node.iter_values().collect::<Vec<_>>();
+            properties.sort();
+            properties.hash(&mut hasher);
+        },
+        VariationCompare::MovesOnly { .. } => {
+            node.get_property("B").ok().hash(&mut hasher);
+            node.get_property("W").ok().hash(&mut hasher);
+        },
+    }
+    kept_hashes.hash(&mut hasher);
+    (removed, hasher.finish())
+}
+
+fn same_variation(a: &SgfNode, b: &SgfNode, compare: VariationCompare) -> bool {
+    match compare {
+        VariationCompare::Exact => a == b,
+        VariationCompare::MovesOnly { .. } => {
+            a.get_property("B").ok() == b.get_property("B").ok() &&
+                a.get_property("W").ok() == b.get_property("W").ok() &&
+                a.children.len() == b.children.len() &&
+                a.children.iter().zip(b.children.iter()).all(|(a, b)| same_variation(a, b, compare))
+        },
+    }
+}
+
+fn merge_comments(kept: &mut SgfNode, removed: &SgfNode) {
+    if let Ok(comment) = removed.get_property("C") {
+        let merged = match kept.get_property("C") {
+            Ok(existing) if existing == comment => None,
+            Ok(existing) => Some(vec![PropValue::from(format!("{}{}{}", existing[0], COMMENT_SEPARATOR, comment[0]))]),
+            Err(_) => Some(comment.clone()),
+        };
+        if let Some(merged) = merged {
+            kept.set_property("C", merged);
+        }
+    }
+    for (k, r) in kept.children.iter_mut().zip(removed.children.iter()) {
+        merge_comments(k, r);
+    }
+}
+
+fn has_move(node: &SgfNode) -> bool {
+    node.iter_values().any(|(id, _)| property_spec(id).map(|spec| spec.property_type) == Some(PropertyType::Move))
+}
+
+fn is_list(id: &str) -> bool {
+    match property_spec(id) {
+        Some(spec) => matches!(spec.value_type,
+            ValueType::ListOfPoint | ValueType::EListOfPoint | ValueType::ListOfPointPoint | ValueType::ListOfPointSimpleText),
+        None => false,
+    }
+}
+
+fn can_merge(parent: &SgfNode, child: &SgfNode) -> bool {
+    let setup = ["AB", "AW", "AE"];
+    for &id in setup.iter() {
+        let points = match child.get_property(id) {
+            Ok(values) => expand_point_list(values),
+            Err(_) => continue,
+        };
+        for &other in setup.iter().filter(|&&other| other != id) {
+            if let Ok(values) = parent.get_property(other) {
+                if expand_point_list(values).iter().any(|p| points.contains(p)) {
+                    return false;
+                }
+            }
+        }
+    }
+    child.iter_values().all(|(id, values)| {
+        is_list(id) || id == "C" || id == "GC" || parent.get_property(id).map_or(true, |v| v == values)
+    })
+}
+
+fn merge_node(parent: &mut SgfNode, child: SgfNode) {
+    for (id, values) in child.iter_values() {
+        let merged = match parent.get_property(id) {
+            Ok(existing) if is_list(id) => {
+                let mut merged = existing.clone();
+                merged.extend(values.iter().filter(|v| !existing.contains(v)).cloned());
+                merged
+            },
+            Ok(existing) if id == "C" || id == "GC" => vec![PropValue::from(format!("{}{}{}", existing[0], COMMENT_SEPARATOR, values[0]))],
+            _ => values.clone(),
+        };
+        parent.set_property(id, merged);
+    }
+    parent.children = child.children;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coalesce_setup_nodes() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]C[setup];AB[aa];AB[bb]C[more];AB[cc]TR[aa];B[dd])").unwrap();
+        assert_eq!(c[0].coalesce_setup_nodes(), 3);
+        assert_eq!(c[0].get_points("AB").unwrap(), vec!["aa".to_string(), "bb".to_string(), "cc".to_string()]);
+        assert_eq!(c[0].get_text("C").unwrap(), "setup\n\nmore");
+        assert_eq!(c[0].get_points("TR").unwrap(), vec!["aa".to_string()]);
+        assert_eq!(c[0].children.len(), 1);
+        assert_eq!(c[0].children[0].get_point("B").unwrap(), "dd");
+    }
+
+    #[test]
+    fn test_coalesce_setup_nodes_keeps_branches() {
+        let mut c = SgfCollection::from_sgf("(;FF[4];B[aa];AB[bb](;AW[cc])(;AW[dd]))").unwrap();
+        let original = c.clone();
+        assert_eq!(c[0].coalesce_setup_nodes(), 0);
+        assert_eq!(c, original);
+    }
+
+    #[test]
+    fn test_coalesce_setup_nodes_keeps_position() {
+        let mut c = SgfCollection::from_sgf("(;FF[4];AB[aa:bb];AE[ab])").unwrap();
+        assert_eq!(c[0].coalesce_setup_nodes(), 1);
+        assert_eq!(c[0].children.len(), 1);
+        assert_eq!(c[0].children[0].get_points("AE").unwrap(), vec!["ab".to_string()]);
+    }
+
+    #[test]
+    fn test_dedup_sibling_variations() {
+        let mut c = SgfCollection::from_sgf("(;FF[4](;B[aa]C[x];W[bb])(;B[cc])(;C[x]B[aa];W[bb]))").unwrap();
+        assert_eq!(c[0].dedup_sibling_variations(VariationCompare::Exact), 1);
+        assert_eq!(c[0].children.len(), 2);
+        assert_eq!(c[0].children[1].get_point("B").unwrap(), "cc");
+    }
+
+    #[test]
+    fn test_dedup_sibling_variations_moves_only() {
+        let sgf = "(;FF[4](;B[aa]C[x];W[bb]C[y])(;B[aa]C[x];W[bb]C[z])(;B[dd](;W[cc])(;W[cc])))";
+        let mut c = SgfCollection::from_sgf(sgf).unwrap();
+        assert_eq!(c[0].dedup_sibling_variations(VariationCompare::Exact), 1);
+        assert_eq!(c[0].children.len(), 3);
+        let mut c = SgfCollection::from_sgf(sgf).unwrap();
+        assert_eq!(c[0].dedup_sibling_variations(VariationCompare::MovesOnly { merge_comments: true }), 2);
+        assert_eq!(c[0].children.len(), 2);
+        assert_eq!(c[0].children[1].children.len(), 1);
+        assert_eq!(c[0].children[0].get_text("C").unwrap(), "x");
+        assert_eq!(c[0].children[0].children[0].get_text("C").unwrap(), "y\n\nz");
+        let mut c = SgfCollection::from_sgf(sgf).unwrap();
+        assert_eq!(c[0].dedup_sibling_variations(VariationCompare::MovesOnly { merge_comments: false }), 2);
+        assert_eq!(c[0].children[0].children[0].get_text("C").unwrap(), "y");
+    }
+
+    #[test]
+    fn test_remove_empty_nodes() {
+        let mut c = SgfCollection::from_sgf("(;;FF[4];;B[aa];;;W[bb](;;B[cc];)(;(;B[dd])(;B[ee])))").unwrap();
+        c[0].remove_empty_nodes();
+        assert_eq!(c.to_string(), "(;;FF[4];B[aa];W[bb](;B[cc])(;B[dd])(;B[ee]))");
+    }
+
+    #[test]
+    fn test_dedup_point_lists() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]AB[aa][bb][aa][cc:dd]AW[cc][cc:dd]LB[aa:x][aa:x];B[ee]TR[aa][aa];W[ff])").unwrap();
+        assert_eq!(c[0].dedup_point_lists(), 2);
+        assert_eq!(c.to_string(), "(;FF[4]AB[aa][bb][cc:dd]AW[cc][cc:dd]LB[aa:x][aa:x];B[ee]TR[aa];W[ff])");
+        assert_eq!(c[0].dedup_point_lists(), 0);
+    }
+
+    #[test]
+    fn test_relabel_variation_references() {
+        let mut c = SgfCollection::from_sgf("(;FF[4];B[aa]C[Variation A is better than var. B.]\
+            (;W[bb]C[This is variation A, see Variation B.])\
+            (;W[cc]C[variation A and variation C; variety B stays.];B[dd]C[Back to var.  A]))").unwrap();
+        c[0].children[0].children.swap(0, 1);
+        let rewrites = c[0].relabel_variation_references(&[0], &[(0, 1), (1, 0)], DEFAULT_VARIATION_WORDS);
+        let node = &c[0].children[0];
+        assert_eq!(node.get_text("C").unwrap(), "Variation B is better than var. A.");
+        assert_eq!(node.children[0].get_text("C").unwrap(), "variation B and variation C; variety B stays.");
+        assert_eq!(node.children[0].children[0].get_text("C").unwrap(), "Back to var.  B");
+        assert_eq!(node.children[1].get_text("C").unwrap(), "This is variation B, see Variation A.");
+        assert_eq!(rewrites.len(), 6);
+        assert_eq!(rewrites[0], ReferenceRewrite {
+            path: vec![0], original: "Variation A".to_string(), rewritten: "Variation B".to_string()
+        });
+        assert_eq!(rewrites[4].path, vec![0, 1]);
+        assert!(c[0].relabel_variation_references(&[5], &[(0, 1)], DEFAULT_VARIATION_WORDS).is_empty());
+        let mut localized = SgfCollection::from_sgf("(;C[Variante A](;B[aa])(;B[bb]))").unwrap();
+        assert_eq!(localized[0].relabel_variation_references(&[], &[(0, 1)], &["Variante"]).len(), 1);
+        assert_eq!(localized[0].get_text("C").unwrap(), "Variante B");
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut a = SgfCollection::from_sgf("(;SZ[ 19 ]FF[4]AB[aa][ba][ab][bb][cc]C[a long\\\ncomment\\:]PB[Honinbo\nShusaku]\
+            ;B[pd]TR[dd][cc][dd];W[tt]XX[ keep ])").unwrap();
+        let mut b = SgfCollection::from_sgf("(;FF[4]\n  SZ[19]PB[Honinbo Shusaku]AB[aa:bb][cc]C[a longcomment:]\
+            ;TR[cc][dd]B[pd];XX[ keep ]W[])").unwrap();
+        assert_ne!(a.to_string(), b.to_string());
+        a.canonicalize();
+        b.canonicalize();
+        assert_eq!(a.to_string(), b.to_string());
+        assert_eq!(a.to_string(), "(;FF[4]SZ[19]PB[Honinbo Shusaku]AB[aa:bb][cc]C[a longcomment\\:];B[pd]TR[cc][dd];W[]XX[ keep ])");
+        assert_eq!(a[0].get_text("C").unwrap(), "a longcomment:");
+        let canonical = a.clone();
+        a.canonicalize();
+        assert_eq!(a, canonical);
+        let mut default_size = SgfCollection::from_sgf("(;FF[4];B[tt])").unwrap();
+        default_size.canonicalize();
+        assert_eq!(default_size.to_string(), "(;FF[4];B[])");
+        let mut unsupported = SgfCollection::from_sgf("(;SZ[300]AB[bb][aa][bb];B[tt])").unwrap();
+        unsupported.canonicalize();
+        assert_eq!(unsupported.to_string(), "(;SZ[300]AB[aa][bb];B[tt])");
+    }
+
+    #[test]
+    fn test_trim_trailing_noise() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]SZ[19];B[pd];W[dp];B[];W[tt];;)").unwrap();
+        assert_eq!(c[0].trim_trailing_noise(TrimOptions::default()).unwrap(), 2);
+        assert_eq!(c, SgfCollection::from_sgf("(;FF[4]SZ[19];B[pd];W[dp];B[];W[tt])").unwrap());
+        let mut c = SgfCollection::from_sgf("(;FF[4];B[pd];W[];B[];W[];B[tt];)").unwrap();
+        assert_eq!(c[0].trim_trailing_noise(TrimOptions { extra_passes: true }).unwrap(), 3);
+        assert_eq!(c[0].move_sequence().unwrap(), vec![('B', Some((15, 3))), ('W', None), ('B', None)]);
+        let mut c = SgfCollection::from_sgf("(;FF[4]SZ[60];B[pd];;)").unwrap();
+        assert!(matches!(c[0].trim_trailing_noise(TrimOptions::default()),
+                         Err(SgfError::UnsupportedBoard { cols: 60, rows: 60, feature: BoardFeature::Coordinates })));
+    }
+
+    #[test]
+    fn test_trim_trailing_noise_keeps_variations() {
+        let mut c = SgfCollection::from_sgf("(;FF[4];B[pd];W[dp](;B[];W[];;)(;B[aa];;))").unwrap();
+        assert_eq!(c[0].trim_trailing_noise(TrimOptions { extra_passes: true }).unwrap(), 2);
+        assert_eq!(c, SgfCollection::from_sgf("(;FF[4];B[pd];W[dp](;B[];W[])(;B[aa];;))").unwrap());
+        let mut c = SgfCollection::from_sgf("(;FF[4];B[pd](;)(;W[aa]))").unwrap();
+        assert_eq!(c[0].trim_trailing_noise(TrimOptions::default()).unwrap(), 0);
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Trees keeping their content hash up to date while they are edited (feature "track_changes")
+
+use std::mem;
+use sgf_node::*;
+use writer::{write_canonical_properties, Digest};
+
+/// Digests of a node and its subtree, which mirror the tree.
+#[derive(Debug, Clone)]
+struct Digests {
+    /// the digest of ';' and the sorted properties of the node
+    properties: Digest,
+    /// the digest of the node and its descendants as written in a game tree
+    subtree: Digest,
+    children: Vec<Digests>,
+}
+
+impl Digests {
+    fn new(node: &SgfNode) -> Digests {
+        let mut digests = Digests {
+            properties: Digest::new(),
+            subtree: Digest::new(),
+            children: node.children.iter().map(Digests::new).collect(),
+        };
+        digests.update_properties(node);
+        digests.update_subtree();
+        digests
+    }
+
+    fn update_properties(&mut self, node: &SgfNode) {
+        self.properties = Digest::new();
+        let _ = write_canonical_properties(&mut self.properties, node);
+    }
+
+    /// Combines the digests of the properties and the children, which are up to date.
+    fn update_subtree(&mut self) {
+        self.subtree = if self.children.len() == 1 {
+            self.properties.append(self.children[0].subtree)
+        } else {
+            self.children.iter().fold(self.properties, |digest, child| {
+                digest.append(Digest::of("(")).append(child.subtree).append(Digest::of(")"))
+            })
+        };
+    }
+}
+
+/// A tree edited only through its methods, which keep its content hash up to date.
+///
+/// Each edit updates the digests of the edited node and its ancestors,
+/// so `content_hash` takes constant time however large the tree is.
+/// It is always equal to `SgfNode::content_hash` of the tree, which computes the hash from scratch.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let c = SgfCollection::from_sgf("(;FF[4]SZ[19];B[pd];W[dd])").unwrap();
+/// let mut tree = c[0].clone().track_changes();
+/// let saved = tree.content_hash();
+/// tree.edit_properties(&[0, 0], |node| { node.set_point("W", "dp".to_string()); });
+/// assert_ne!(tree.content_hash(), saved);
+/// assert_eq!(tree.content_hash(), tree.root().content_hash());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrackedTree {
+    root: SgfNode,
+    digests: Digests,
+}
+
+impl TrackedTree {
+    /// Returns a tracked tree computing the digests of root once.
+    pub fn new(root: SgfNode) -> TrackedTree {
+        let digests = Digests::new(&root);
+        TrackedTree { root, digests }
+    }
+
+    pub fn root(&self) -> &SgfNode {
+        &self.root
+    }
+
+    /// Returns the tree stopping tracking.
+    pub fn into_inner(self) -> SgfNode {
+        self.root
+    }
+
+    /// Returns the content hash of the tree, which is that of `SgfNode::content_hash`, in constant time.
+    pub fn content_hash(&self) -> u64 {
+        Digest::of("(").append(self.digests.subtree).append(Digest::of(")")).hash()
+    }
+
+    /// Edits the properties of the node at path by f.
+    /// The children of the node are detached while f runs, and children which f adds are appended as new variations.
+    /// Returns false if path is invalid.
+    pub fn edit_properties<F: FnOnce(&mut SgfNode)>(&mut self, path: &[usize], f: F) -> bool {
+        self.update(path, |node, digests| {
+            let children = mem::take(&mut node.children);
+            f(node);
+            let added = mem::replace(&mut node.children, children);
+            digests.update_properties(node);
+            for child in added {
+                digests.children.push(Digests::new(&child));
+                node.children.push(child);
+            }
+            Some(())
+        }).is_some()
+    }
+
+    /// Inserts child at index of the children of the node at path shifting the following variations.
+    /// Returns false if path is invalid or index is greater than the number of children.
+    pub fn insert_child(&mut self, path: &[usize], index: usize, child: SgfNode) -> bool {
+        self.update(path, |node, digests| {
+            if index > node.children.len() {
+                return None;
+            }
+            digests.children.insert(index, Digests::new(&child));
+            node.insert_child(index, child);
+            Some(())
+        }).is_some()
+    }
+
+    /// Removes and returns the child at index of the node at path shifting the following variations.
+    /// Returns None if path is invalid or index is out of range.
+    pub fn remove_child(&mut self, path: &[usize], index: usize) -> Option<SgfNode> {
+        self.update(path, |node, digests| {
+            if index >= node.children.len() {
+                return None;
+            }
+            digests.children.remove(index);
+            Some(node.remove_child(index))
+        })
+    }
+
+    /// Moves the child at index of the node at path to the position 0 so that it becomes the main line.
+    /// Returns false if path is invalid or index is out of range.
+    pub fn promote_variation(&mut self, path: &[usize], index: usize) -> bool {
+        self.update(path, |node, digests| {
+            if index >= node.children.len() {
+                return None;
+            }
+            let child = digests.children.remove(index);
+            digests.children.insert(0, child);
+            node.promote_variation(index);
+            Some(())
+        }).is_some()
+    }
+
+    /// Applies f to the node at path and its digests, which updates those of the node but the subtree one,
+    /// and then updates the subtree digests on the path. Nothing is updated if f returns None.
+    fn update<T, F>(&mut self, path: &[usize], f: F) -> Option<T>
+        where F: FnOnce(&mut SgfNode, &mut Digests) -> Option<T>
+    {
+        update_at(&mut self.root, &mut self.digests, path, f)
+    }
+}
+
+fn update_at<T, F>(node: &mut SgfNode, digests: &mut Digests, path: &[usize], f: F) -> Option<T>
+    where F: FnOnce(&mut SgfNode, &mut Digests) -> Option<T>
+{
+    let result = match path.split_first() {
+        None => f(node, digests)?,
+        Some((&i, rest)) => update_at(node.children.get_mut(i)?, &mut digests.children[i], rest, f)?,
+    };
+    digests.update_subtree();
+    Some(result)
+}
+
+impl SgfNode {
+    /// Returns the tree tracked to keep its content hash up to date while it is edited.
+    pub fn track_changes(self) -> TrackedTree {
+        TrackedTree::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracked_content_hash() {
+        let c = SgfCollection::from_sgf("(;FF[4]SZ[19]C[root](;B[pd];W[dd])(;B[dp]))").unwrap();
+        let mut tree = c[0].clone().track_changes();
+        let saved = tree.content_hash();
+        assert_eq!(saved, c[0].content_hash());
+        assert!(tree.edit_properties(&[], |node| { node.set_simple_text("SZ", "19".to_string()); }));
+        assert_eq!(tree.content_hash(), saved);
+        assert!(tree.edit_properties(&[0], |node| { node.set_point("B", "pp".to_string()); }));
+        assert_ne!(tree.content_hash(), saved);
+        assert!(tree.edit_properties(&[0], |node| { node.set_point("B", "pd".to_string()); }));
+        assert_eq!(tree.content_hash(), saved);
+        assert!(!tree.edit_properties(&[2], |node| { node.set_point("B", "pp".to_string()); }));
+        assert!(!tree.insert_child(&[], 3, SgfNode::new(Vec::new())));
+        assert!(tree.remove_child(&[1], 0).is_none());
+        assert_eq!(tree.content_hash(), saved);
+        // the same tree built another way
+        let mut other = SgfNode::new(vec![("C".to_string(), vec!["root".to_string()])]).track_changes();
+        other.edit_properties(&[], |node| { node.set_number("FF", 4).set_number("SZ", 19); });
+        other.insert_child(&[], 0, SgfNode::new(vec![("B".to_string(), vec!["dp".to_string()])]));
+        other.insert_child(&[], 1, SgfNode::new(vec![("B".to_string(), vec!["pd".to_string()])]));
+        other.edit_properties(&[1], |node| { node.add_child(SgfNode::new(vec![("W".to_string(), vec!["dd".to_string()])])); });
+        other.promote_variation(&[], 1);
+        assert_eq!(other.root(), &c[0]);
+        assert_eq!(other.content_hash(), saved);
+    }
+
+    fn paths(node: &SgfNode) -> Vec<Vec<usize>> {
+        let mut paths = vec![vec![]];
+        for (i, child) in node.children.iter().enumerate() {
+            paths.extend(paths_of(child, i));
+        }
+        paths
+    }
+
+    fn paths_of(node: &SgfNode, index: usize) -> Vec<Vec<usize>> {
+        paths(node).into_iter().map(|mut path| { path.insert(0, index); path }).collect()
+    }
+
+    #[test]
+    fn test_tracked_random_edits() {
+        let c = SgfCollection::from_sgf("(;FF[4]SZ[19](;B[pd];W[dd];B[pp])(;B[dp]C[a]))").unwrap();
+        let mut tree = c[0].clone().track_changes();
+        // xorshift for a reproducible sequence
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut random = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        for _ in 0..500 {
+            let paths = paths(tree.root());
+            let path = paths[random(paths.len())].clone();
+            let children = tree.root().node_at(&path).unwrap().children.len();
+            let point = format!("{}{}", (b'a' + random(19) as u8) as char, (b'a' + random(19) as u8) as char);
+            match random(6) {
+                0 => { tree.edit_properties(&path, |node| { node.set_point(["W", "B"][path.len() % 2], point); }); },
+                1 => { tree.edit_properties(&path, |node| { node.set_text("C", format!("{}]\\", point)); }); },
+                2 => { tree.edit_properties(&path, |node| { node.remove_property("C"); }); },
+                3 if paths.len() < 40 => { tree.insert_child(&path, random(children + 1), SgfNode::new(vec![("B".to_string(), vec![point])])); },
+                4 if children > 0 => { tree.remove_child(&path, random(children)); },
+                5 if children > 0 => { tree.promote_variation(&path, random(children)); },
+                _ => (),
+            }
+            assert_eq!(tree.content_hash(), tree.root().content_hash());
+        }
+        assert_eq!(tree.clone().into_inner().content_hash(), tree.content_hash());
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Validation of game trees against the specification
+
+use std::collections::HashMap;
+use std::mem;
+use sgf_node::*;
+use properties::{property_spec, PropertyType};
+
+/// Severity of a validation issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Kind of a validation issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// A move property(B or W) with more than one value
+    MultipleMoveValues,
+    /// A node with both a move property and setup properties(AB, AE, AW)
+    MixedMoveAndSetup,
+    /// A root node with both a move property and game info properties
+    MoveInRootWithGameInfo,
+    /// A tree without any moves, which is valid but usually a placeholder
+    NoMoves,
+}
+
+/// An issue found by `SgfNode::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    /// Child indices from the validated node to the node with the issue
+    pub path: Vec<usize>,
+    pub message: String,
+}
+
+impl SgfNode {
+    /// Validates the node and its descendants and returns found issues in depth-first order.
+    ///
+    /// Enforced rules are
+    ///
+    /// * B and W are Move type, so each of them must have exactly one value. (Error)
+    /// * AB, AE and AW are lists of points, so any number of values are allowed.
+    ///   But a node must not have both a move property and setup properties in FF[4]. (Error)
+    /// * The node, regarded as a root, must not have both a move property and game info properties. (Error)
+    ///   `split_root_move` repairs it.
+    /// * The tree has no moves. (Info)
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let has_type = |t| self.iter().any(|(id, _)| property_spec(id).map(|spec| spec.property_type) == Some(t));
+        if (self.get_property("B").is_ok() || self.get_property("W").is_ok()) && has_type(PropertyType::GameInfo) {
+            issues.push(ValidationIssue {
+                severity: Severity::Error,
+                kind: IssueKind::MoveInRootWithGameInfo,
+                path: vec![],
+                message: "move property in the root with game info properties".to_string(),
+            });
+        }
+        validate_node(self, &mut vec![], &mut issues);
+        if !self.has_moves() {
+            issues.push(ValidationIssue {
+                severity: Severity::Info,
+                kind: IssueKind::NoMoves,
+                path: vec![],
+                message: "no moves".to_string(),
+            });
+        }
+        issues
+    }
+}
+
+impl SgfNode {
+    /// Moves properties of move type(B, W, KO, MN, move annotations and timing) of the node into a new child
+    /// and returns true if the node has B or W. Otherwise does nothing and returns false.
+    /// The existing children of the node become children of the new node since they follow the move.
+    /// Other properties including C stay in the node.
+    pub fn split_root_move(&mut self) -> bool {
+        if self.get_property("B").is_err() && self.get_property("W").is_err() {
+            return false;
+        }
+        let ids = self.iter()
+            .filter(|&(id, _)| property_spec(id).map(|spec| spec.property_type) == Some(PropertyType::Move))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        let mut child = SgfNode::new(HashMap::new());
+        for id in ids {
+            if let Some(values) = self.remove_property(&id) {
+                child.set_property(&id, values);
+            }
+        }
+        child.children = mem::take(&mut self.children);
+        self.children.push(child);
+        true
+    }
+}
+
+/// A game in the report of `SgfCollection::validate_and_repair`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRepair {
+    /// Issues found before the repair
+    pub issues: Vec<ValidationIssue>,
+    /// Whether `split_root_move` moved the move in the root into a new node
+    pub root_move_split: bool,
+}
+
+impl SgfCollection {
+    /// Validates every game by `validate` and repairs moves in roots by `split_root_move`.
+    /// Returns a report of each game in order.
+    pub fn validate_and_repair(&mut self) -> Vec<GameRepair> {
+        self.iter_mut().map(|game| GameRepair {
+            issues: game.validate(),
+            root_move_split: game.split_root_move(),
+        }).collect()
+    }
+}
+
+/// Error of `SgfNode::edit`.
+#[derive(Debug)]
+pub enum EditError<E> {
+    /// The closure returned the error.
+    Closure(E),
+    /// The edit introduced validation issues at or above the threshold.
+    Validation(Vec<ValidationIssue>),
+}
+
+impl SgfNode {
+    /// Applies edits by f atomically.
+    /// Same as `edit_with_threshold(Severity::Error, f)`.
+    pub fn edit<E, F: FnOnce(&mut SgfNode) -> Result<(), E>>(&mut self, f: F) -> Result<(), EditError<E>> {
+        self.edit_with_threshold(Severity::Error, f)
+    }
+
+    /// Applies edits by f to the node and its descendants atomically.
+    ///
+    /// If f returns an error, or `validate` finds new issues whose severity is threshold or higher after f,
+    /// the node is restored as it was before the edit and the error is returned.
+    /// Issues which already existed before the edit don't cause a rollback.
+    /// The node is cloned for the rollback.
+    pub fn edit_with_threshold<E, F: FnOnce(&mut SgfNode) -> Result<(), E>>(&mut self, threshold: Severity, f: F) -> Result<(), EditError<E>> {
+        let backup = self.clone();
+        let before = backup.validate();
+        if let Err(e) = f(self) {
+            *self = backup;
+            return Err(EditError::Closure(e));
+        }
+        let issues = self.validate().into_iter()
+            .filter(|issue| issue.severity >= threshold && !before.contains(issue))
+            .collect::<Vec<_>>();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            *self = backup;
+            Err(EditError::Validation(issues))
+        }
+    }
+}
+
+fn validate_node(node: &SgfNode, path: &mut Vec<usize>, issues: &mut Vec<ValidationIssue>) {
+    let mut has_move = false;
+    let mut has_setup = false;
+    for (id, values) in node.iter_values() {
+        match &id[..] {
+            "B" | "W" => {
+                has_move = true;
+                if values.len() > 1 {
+                    issues.push(ValidationIssue {
+                        severity: Severity::Error,
+                        kind: IssueKind::MultipleMoveValues,
+                        path: path.clone(),
+                        message: format!("move property {} has {} values", id, values.len()),
+                    });
+                }
+            },
+            "AB" | "AE" | "AW" => has_setup = true,
+            _ => (),
+        }
+    }
+    if has_move && has_setup {
+        issues.push(ValidationIssue {
+            severity: Severity::Error,
+            kind: IssueKind::MixedMoveAndSetup,
+            path: path.clone(),
+            message: "move and setup properties in the same node".to_string(),
+        });
+    }
+    for (i, child) in node.children.iter().enumerate() {
+        path.push(i);
+        validate_node(child, path, issues);
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(sgf: &str) -> Vec<ValidationIssue> {
+        SgfCollection::from_sgf(sgf).unwrap()[0].validate()
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        assert!(validate("(;FF[4]AB[aa][bb]AW[cc:dd];B[ee];W[ff])").is_empty());
+    }
+
+    #[test]
+    fn test_validate_multiple_move_values() {
+        let issues = validate("(;FF[4];B[aa];W[bb][cc])");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::MultipleMoveValues);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].path, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_validate_mixed_move_and_setup() {
+        let issues = validate("(;FF[4];B[aa]AW[bb])");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::MixedMoveAndSetup);
+        assert_eq!(issues[0].path, vec![0]);
+    }
+
+    #[test]
+    fn test_edit_rollback() {
+        let mut c = SgfCollection::from_sgf("(;FF[4];B[aa];W[bb])").unwrap();
+        let original = c.to_string();
+        let result = c[0].edit(|root| -> Result<(), ()> {
+            root.children[0].set_point("B", "cc".to_string());
+            root.children[0].children[0].set_points("W", vec!["dd".to_string(), "ee".to_string()]);
+            Ok(())
+        });
+        match result {
+            Err(EditError::Validation(ref issues)) => assert_eq!(issues[0].kind, IssueKind::MultipleMoveValues),
+            _ => panic!("edit was not rolled back"),
+        }
+        assert_eq!(c.to_string(), original);
+        let result = c[0].edit(|root| {
+            root.set_text("C", "half applied".to_string());
+            Err("failed")
+        });
+        match result {
+            Err(EditError::Closure("failed")) => (),
+            _ => panic!("closure error was not returned"),
+        }
+        assert_eq!(c.to_string(), original);
+    }
+
+    #[test]
+    fn test_edit_commit() {
+        let mut c = SgfCollection::from_sgf("(;FF[4];B[aa]AW[bb];W[bb])").unwrap();
+        c[0].edit(|root| -> Result<(), ()> {
+            root.children[0].children[0].set_point("W", "cc".to_string());
+            Ok(())
+        }).unwrap();
+        assert_eq!(c[0].children[0].children[0].get_point("W").unwrap(), "cc");
+        assert!(c[0].edit_with_threshold(Severity::Info, |root| -> Result<(), ()> {
+            root.children[0].set_points("B", vec!["dd".to_string(), "ee".to_string()]);
+            Ok(())
+        }).is_err());
+    }
+
+    #[test]
+    fn test_split_root_move() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]SZ[19]PB[foo]B[pd]BL[300]C[game](;W[dd])(;W[dp]))").unwrap();
+        let issues = c[0].validate();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::MoveInRootWithGameInfo);
+        assert!(c[0].split_root_move());
+        assert!(c[0].validate().is_empty());
+        let reparsed = SgfCollection::from_sgf(&c.to_string()).unwrap();
+        assert_eq!(reparsed, c);
+        let root = &reparsed[0];
+        assert_eq!(root.get_text("C").unwrap(), "game");
+        assert!(root.get_property("BL").is_err());
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].get_point("B").unwrap(), "pd");
+        assert_eq!(root.children[0].get_real("BL").unwrap(), 300.0);
+        assert_eq!(root.children[0].children.len(), 2);
+        assert_eq!(root.main_line_numbered().map(|(n, _)| n).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(!c[0].split_root_move());
+    }
+}
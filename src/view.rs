@@ -0,0 +1,159 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Read-only filtered views of trees
+
+use std::fmt;
+use std::rc::Rc;
+use sgf_node::*;
+use prop_value::PropValue;
+
+/// What `SgfNodeView` hides.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PropertyFilter {
+    /// Identifiers of properties to hide
+    pub hidden_properties: Vec<String>,
+    /// If some, variations whose first node has the property are hidden with their subtrees.
+    pub hidden_variation_marker: Option<String>,
+}
+
+impl PropertyFilter {
+    fn hides(&self, id: &str) -> bool {
+        self.hidden_properties.iter().any(|h| h == id)
+    }
+
+    fn hides_variation(&self, node: &SgfNode) -> bool {
+        match self.hidden_variation_marker {
+            Some(ref marker) => node.get_property(marker).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// A view of a node hiding properties and variations by a filter without copying the tree.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let c = SgfCollection::from_sgf("(;FF[4]C[root];B[aa]C[first])").unwrap();
+/// let filter = PropertyFilter { hidden_properties: vec!["C".to_string()], ..Default::default() };
+/// let view = SgfNodeView::new(&c[0], filter);
+/// assert_eq!(view.to_string(), ";FF[4];B[aa]");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SgfNodeView<'a> {
+    node: &'a SgfNode,
+    filter: Rc<PropertyFilter>,
+}
+
+macro_rules! view_getter {
+    ($name:ident, $t:ty) => {
+        /// Same as the method of SgfNode except that hidden properties are NoProperties.
+        pub fn $name(&self, id: &str) -> Result<$t, SgfError> {
+            self.visible(id)?.$name(id)
+        }
+    };
+}
+
+impl<'a> SgfNodeView<'a> {
+    /// Returns a view of node.
+    pub fn new(node: &'a SgfNode, filter: PropertyFilter) -> SgfNodeView<'a> {
+        SgfNodeView { node, filter: Rc::new(filter) }
+    }
+
+    /// Returns the underlying node.
+    pub fn node(&self) -> &'a SgfNode {
+        self.node
+    }
+
+    fn visible(&self, id: &str) -> Result<&'a SgfNode, SgfError> {
+        if self.filter.hides(id) { Err(SgfError::NoProperties(id.to_string())) } else { Ok(self.node) }
+    }
+
+    /// Returns an iterator of visible properties.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (&'a String, Vec<&'a str>)> + 'a> {
+        let filter = self.filter.clone();
+        Box::new(self.node.iter().filter(move |&(id, _)| !filter.hides(id)))
+    }
+
+    /// Returns an iterator of visible properties with values as they are stored.
+    pub fn iter_values(&self) -> Box<dyn Iterator<Item = (&'a String, &'a Vec<PropValue>)> + 'a> {
+        let filter = self.filter.clone();
+        Box::new(self.node.iter_values().filter(move |&(id, _)| !filter.hides(id)))
+    }
+
+    /// Returns views of visible children.
+    pub fn children(&self) -> Vec<SgfNodeView<'a>> {
+        self.node.children.iter()
+            .filter(|child| !self.filter.hides_variation(child))
+            .map(|child| SgfNodeView { node: child, filter: self.filter.clone() })
+            .collect()
+    }
+
+    view_getter!(get_point, SgfPoint);
+    view_getter!(get_number, SgfNumber);
+    view_getter!(get_points, Vec<SgfPoint>);
+    view_getter!(get_color, SgfColor);
+    view_getter!(get_double, SgfDouble);
+    view_getter!(get_text, SgfText);
+    view_getter!(get_simple_text, SgfSimpleText);
+    view_getter!(get_real, SgfReal);
+    view_getter!(get_point_point, (SgfPoint, SgfPoint));
+    view_getter!(get_point_simple_text, (SgfPoint, SgfSimpleText));
+    view_getter!(get_simple_text_simple_text, (SgfSimpleText, SgfSimpleText));
+    view_getter!(get_number_number, (SgfNumber, SgfNumber));
+    view_getter!(get_number_simple_text, (SgfNumber, SgfSimpleText));
+}
+
+/// Writes the filtered node in the same form as SgfNode.
+impl<'a> fmt::Display for SgfNodeView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, ";")?;
+        for (id, values) in self.iter_values() {
+            write!(f, "{}", id)?;
+            for v in values {
+                write!(f, "[{}]", v)?;
+            }
+        }
+        let children = self.children();
+        if children.len() == 1 {
+            write!(f, "{}", children[0])
+        } else {
+            for child in children.iter() {
+                write!(f, "({})", child)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_hides_comments() {
+        let c = SgfCollection::from_sgf("(;C[root](;B[aa]C[a];W[bb]C[b])(;B[cc]))").unwrap();
+        let original = c.clone();
+        let filter = PropertyFilter { hidden_properties: vec!["C".to_string()], ..Default::default() };
+        let view = SgfNodeView::new(&c[0], filter);
+        assert_eq!(view.to_string(), ";(;B[aa];W[bb])(;B[cc])");
+        assert!(view.get_text("C").is_err());
+        let grandchild = &view.children()[0].children()[0];
+        assert!(grandchild.get_text("C").is_err());
+        assert_eq!(grandchild.get_point("W").unwrap(), "bb");
+        assert_eq!(c, original);
+        assert_eq!(c[0].children[0].children[0].get_text("C").unwrap(), "b");
+    }
+
+    #[test]
+    fn test_view_hides_variations() {
+        let c = SgfCollection::from_sgf("(;FF[4](;B[aa];W[bb])(;B[cc]HV[]))").unwrap();
+        let filter = PropertyFilter { hidden_variation_marker: Some("HV".to_string()), ..Default::default() };
+        let view = SgfNodeView::new(&c[0], filter);
+        assert_eq!(view.children().len(), 1);
+        assert_eq!(view.to_string(), ";FF[4];B[aa];W[bb]");
+    }
+}
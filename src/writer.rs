@@ -0,0 +1,608 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! SGF writers other than Display
+
+use std::fmt;
+use std::fmt::Write;
+use std::io;
+use sgf_node::*;
+use prop_value::PropValue;
+use properties::{canonical_index, property_spec, ValueType};
+
+/// Writes a collection in a deterministic and diff-friendly form.
+///
+/// The ordering rules are
+///
+/// * properties in a node are sorted in the order of `properties::PROPERTIES`,
+///   that is, root properties, game info properties, setup properties, move properties,
+///   move annotation, node annotation, markup, timing, miscellaneous and Go specific properties.
+/// * nonstandard properties follow all standard properties in alphabetical order of their identifiers.
+/// * values of a property and variations keep their original order.
+///
+/// When `one_move_per_line` is true, every node except the first one of a game tree starts a new line
+/// and so does every variation.
+/// Each game tree is terminated by a newline.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let c = SgfCollection::from_sgf("(;SZ[19]FF[4];B[pd];W[dd])").unwrap();
+/// let mut s = String::new();
+/// write_sgf_canonical(&mut s, &c, true).unwrap();
+/// assert_eq!(s, "(;FF[4]SZ[19]\n;B[pd]\n;W[dd])\n");
+/// ```
+pub fn write_sgf_canonical<W: fmt::Write>(f: &mut W, collection: &SgfCollection, one_move_per_line: bool) -> fmt::Result {
+    for game in collection.iter() {
+        write!(f, "(")?;
+        write_canonical_node(f, game, one_move_per_line, true)?;
+        writeln!(f, ")")?;
+    }
+    Ok(())
+}
+
+fn write_canonical_node<W: fmt::Write>(f: &mut W, node: &SgfNode, one_move_per_line: bool, head: bool) -> fmt::Result {
+    if one_move_per_line && !head {
+        writeln!(f)?;
+    }
+    write_canonical_properties(f, node)?;
+    if node.children.len() == 1 {
+        write_canonical_node(f, &node.children[0], one_move_per_line, false)
+    } else {
+        for child in node.children.iter() {
+            if one_move_per_line {
+                writeln!(f)?;
+            }
+            write!(f, "(")?;
+            write_canonical_node(f, child, one_move_per_line, true)?;
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes ';' and the properties of node sorted as `write_sgf_canonical` does.
+pub(crate) fn write_canonical_properties<W: fmt::Write>(f: &mut W, node: &SgfNode) -> fmt::Result {
+    write!(f, ";")?;
+    let mut properties = node.iter_values().collect::<Vec<_>>();
+    properties.sort_by(|a, b| (canonical_index(a.0), a.0).cmp(&(canonical_index(b.0), b.0)));
+    for (id, values) in properties {
+        if !is_valid_identifier(id) {
+            return Err(fmt::Error);
+        }
+        write_property(f, id, values)?;
+    }
+    Ok(())
+}
+
+/// Writes a property repairing escapes of its values as `write_sgf_with_options` does,
+/// and "[]" for an empty list of values. id is written as it is.
+pub(crate) fn write_property<W: fmt::Write>(f: &mut W, id: &str, values: &[PropValue]) -> fmt::Result {
+    write!(f, "{}", id)?;
+    if values.is_empty() {
+        write!(f, "[]")?;
+    }
+    let composed = is_composed(property_spec(id).map(|spec| spec.value_type));
+    for v in values {
+        write!(f, "[")?;
+        write_repaired_escapes(f, v, composed)?;
+        write!(f, "]")?;
+    }
+    Ok(())
+}
+
+pub(crate) fn is_valid_identifier(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+fn is_composed(value_type: Option<ValueType>) -> bool {
+    matches!(value_type,
+        Some(ValueType::SimpleTextSimpleText) | Some(ValueType::ListOfPointSimpleText) |
+        Some(ValueType::NoneOrNumberSimpleText) | Some(ValueType::ListOfPointPoint) |
+        Some(ValueType::ListOfPoint) | Some(ValueType::EListOfPoint))
+}
+
+const DIGEST_MODULUS: u64 = (1 << 61) - 1;
+const DIGEST_BASE: u64 = 0x0123_4567_89ab_cdef;
+
+fn mul_mod(a: u64, b: u64) -> u64 {
+    (a as u128 * b as u128 % DIGEST_MODULUS as u128) as u64
+}
+
+/// A polynomial hash modulo 2^61 - 1 of written bytes, which is a fmt::Write sink.
+/// Unlike `DefaultHasher`, it is the same across processes and Rust versions,
+/// and the digest of a concatenation is computed from the digests of its parts by `append`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Digest {
+    hash: u64,
+    /// DIGEST_BASE to the power of the number of bytes
+    power: u64,
+}
+
+impl Digest {
+    pub(crate) fn new() -> Digest {
+        Digest { hash: 0, power: 1 }
+    }
+
+    #[cfg(feature = "track_changes")]
+    pub(crate) fn of(s: &str) -> Digest {
+        let mut digest = Digest::new();
+        let _ = digest.write_str(s);
+        digest
+    }
+
+    #[cfg(feature = "track_changes")]
+    /// Returns the digest of the bytes of self followed by those of other.
+    pub(crate) fn append(self, other: Digest) -> Digest {
+        Digest {
+            hash: (mul_mod(self.hash, other.power) + other.hash) % DIGEST_MODULUS,
+            power: mul_mod(self.power, other.power),
+        }
+    }
+
+    pub(crate) fn hash(self) -> u64 {
+        self.hash
+    }
+}
+
+impl fmt::Write for Digest {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            self.hash = (mul_mod(self.hash, DIGEST_BASE) + b as u64 + 1) % DIGEST_MODULUS;
+            self.power = mul_mod(self.power, DIGEST_BASE);
+        }
+        Ok(())
+    }
+}
+
+impl SgfNode {
+    /// Returns a hash of the tree written as a game tree by `write_sgf_canonical` without line breaks,
+    /// so trees with the same canonical form hash equally whatever the order of their properties
+    /// and however they were built or edited. The hash is stable across processes and versions of Rust.
+    ///
+    /// It is computed from scratch in linear time without building the serialization in memory.
+    /// Save it to tell whether the tree changed since then.
+    /// `TrackedTree` of the feature "track_changes" keeps the same hash up to date while the tree is edited.
+    pub fn content_hash(&self) -> u64 {
+        let mut digest = Digest::new();
+        let _ = digest.write_str("(")
+            .and_then(|_| write_canonical_node(&mut digest, self, false, true))
+            .and_then(|_| digest.write_str(")"));
+        digest.hash()
+    }
+}
+
+/// How to make text values ASCII only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsciiPolicy {
+    /// Removes non-ASCII characters.
+    Strip,
+    /// Replaces each non-ASCII character with '?'.
+    Replace,
+    /// Transliterates common accented Latin characters into basic Latin ones, such as 'ō' into 'o',
+    /// and replaces other non-ASCII characters with '?'.
+    Transliterate,
+}
+
+/// Options for `write_sgf_with_options`.
+///
+/// Output is always strict FF[4]. There are no profiles matching quirks of specific editors
+/// since no editor documents its deviations from FF[4].
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// If some, non-ASCII characters in text values are converted by the policy.
+    /// Text values are values of Text and SimpleText types including composed ones (AP, FG, LB)
+    /// and values of nonstandard properties. Other values are written as they are.
+    pub ascii_only: Option<AsciiPolicy>,
+    /// If true, each node starts a new line indented by two spaces per level of variations
+    /// and each game tree is terminated by a newline.
+    pub pretty: bool,
+    /// If some, values of Text and SimpleText types are broken by soft line breaks("\\" and a newline)
+    /// so that lines of them don't exceed the width in characters.
+    /// Other parts are never broken, so a line may still exceed the width.
+    pub max_line_width: Option<usize>,
+}
+
+/// A record of a value altered in output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alteration {
+    /// Index of the game in the collection
+    pub game: usize,
+    /// Child indices from the root of the game to the node
+    pub path: Vec<usize>,
+    pub id: String,
+    pub original: String,
+    pub altered: String,
+}
+
+/// Writes a collection in the same form as Display with options and returns alterations of values.
+/// The collection itself is not modified.
+///
+/// Values which would break the output are escaped and reported as alterations, whatever the options.
+/// They are a ']' without a preceding '\\', a '\\' at the end, and a ':' after the separator
+/// of a composed value(AP, FG, LB and so on). An empty list of values is written as "[]".
+/// So the output of any collection is parsed into an equal one unless soft line breaks are inserted,
+/// in which case decoded values are equal.
+/// Returns fmt::Error if a property identifier is not uppercase letters.
+///
+/// # Example
+///
+/// ```
+/// use sgf::*;
+///
+/// let c = SgfCollection::from_sgf("(;PB[Gō Seigen])").unwrap();
+/// let mut s = String::new();
+/// let options = WriteOptions { ascii_only: Some(AsciiPolicy::Transliterate), ..Default::default() };
+/// let alterations = write_sgf_with_options(&mut s, &c, &options).unwrap();
+/// assert_eq!(s, "(;PB[Go Seigen])");
+/// assert_eq!(alterations.len(), 1);
+/// ```
+pub fn write_sgf_with_options<W: fmt::Write>(f: &mut W, collection: &SgfCollection, options: &WriteOptions) -> Result<Vec<Alteration>, fmt::Error> {
+    let mut f = Columns { inner: f, column: 0 };
+    let mut alterations = Vec::new();
+    for (i, game) in collection.iter().enumerate() {
+        write!(f, "(")?;
+        write_node_with_options(&mut f, game, options, i, &mut vec![], &mut alterations, 0, true)?;
+        write!(f, ")")?;
+        if options.pretty {
+            writeln!(f)?;
+        }
+    }
+    Ok(alterations)
+}
+
+/// Same as `write_sgf_with_options` but writes to an `io::Write` such as a `File`.
+pub fn write_sgf_io<W: io::Write>(w: &mut W, collection: &SgfCollection, options: &WriteOptions) -> io::Result<Vec<Alteration>> {
+    let mut adapter = IoAdapter { inner: w, error: None };
+    match write_sgf_with_options(&mut adapter, collection, options) {
+        Ok(alterations) => Ok(alterations),
+        Err(_) => Err(adapter.error.unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "formatter error"))),
+    }
+}
+
+struct IoAdapter<'a, W: 'a> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: io::Write> fmt::Write for IoAdapter<'a, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// A writer tracking the column of the current line.
+struct Columns<'a, W: 'a> {
+    inner: &'a mut W,
+    column: usize,
+}
+
+impl<'a, W: fmt::Write> fmt::Write for Columns<'a, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match s.rfind('\n') {
+            Some(i) => self.column = s[i + 1..].chars().count(),
+            None => self.column += s.chars().count(),
+        }
+        self.inner.write_str(s)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_node_with_options<W: fmt::Write>(f: &mut Columns<W>, node: &SgfNode, options: &WriteOptions, game: usize, path: &mut Vec<usize>, alterations: &mut Vec<Alteration>, depth: usize, head: bool) -> fmt::Result {
+    if options.pretty && !head {
+        write!(f, "\n{}", "  ".repeat(depth))?;
+    }
+    write!(f, ";")?;
+    for (id, values) in node.iter_values() {
+        if !is_valid_identifier(id) {
+            return Err(fmt::Error);
+        }
+        let value_type = property_spec(id).map(|spec| spec.value_type);
+        write!(f, "{}", id)?;
+        if values.is_empty() {
+            write!(f, "[]")?;
+        }
+        let is_text = match value_type {
+            Some(value_type) => matches!(value_type,
+                ValueType::SimpleText | ValueType::Text | ValueType::SimpleTextSimpleText |
+                ValueType::ListOfPointSimpleText | ValueType::NoneOrNumberSimpleText),
+            None => true,
+        };
+        let is_composed = is_composed(value_type);
+        let wrap = options.max_line_width.filter(|_| matches!(value_type, Some(ValueType::SimpleText) | Some(ValueType::Text)));
+        for v in values {
+            let mut altered = repair_escapes(v, is_composed);
+            match options.ascii_only {
+                Some(policy) if is_text && !altered.is_ascii() => altered = to_ascii(&altered, policy),
+                _ => (),
+            }
+            if altered != *v {
+                alterations.push(Alteration {
+                    game,
+                    path: path.clone(),
+                    id: id.clone(),
+                    original: v.to_string(),
+                    altered: altered.clone(),
+                });
+            }
+            write!(f, "[")?;
+            match wrap {
+                Some(width) => write_wrapped(f, &altered, width)?,
+                None => write!(f, "{}", altered)?,
+            }
+            write!(f, "]")?;
+        }
+    }
+    if node.children.len() == 1 {
+        path.push(0);
+        write_node_with_options(f, &node.children[0], options, game, path, alterations, depth, false)?;
+        path.pop();
+    } else {
+        for (i, child) in node.children.iter().enumerate() {
+            if options.pretty {
+                write!(f, "\n{}", "  ".repeat(depth + 1))?;
+            }
+            write!(f, "(")?;
+            path.push(i);
+            write_node_with_options(f, child, options, game, path, alterations, depth + 1, true)?;
+            path.pop();
+            write!(f, ")")?;
+        }
+    }
+    Ok(())
+}
+
+/// Escapes ']' not escaped, a '\\' at the end and, if composed, ':' after the first one not escaped in a raw value.
+fn repair_escapes(v: &str, composed: bool) -> String {
+    let mut result = String::with_capacity(v.len());
+    let _ = write_repaired_escapes(&mut result, v, composed);
+    result
+}
+
+/// Writes v with escapes repaired as `repair_escapes` does without allocation.
+fn write_repaired_escapes<W: fmt::Write>(f: &mut W, v: &str, composed: bool) -> fmt::Result {
+    let mut separated = false;
+    let mut chars = v.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                f.write_char(c)?;
+                f.write_char(chars.next().unwrap_or('\\'))?;
+            },
+            ']' => f.write_str("\\]")?,
+            ':' if composed && separated => f.write_str("\\:")?,
+            ':' => {
+                separated = true;
+                f.write_char(c)?;
+            },
+            _ => f.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes a raw value inserting soft line breaks before escape sequences or characters
+/// which would make the line longer than width with a following '\\' or ']'.
+fn write_wrapped<W: fmt::Write>(f: &mut Columns<W>, v: &str, width: usize) -> fmt::Result {
+    let mut chars = v.chars();
+    while let Some(c) = chars.next() {
+        let mut unit = c.to_string();
+        if c == '\\' {
+            unit.extend(chars.next());
+        }
+        if c != '\n' && c != '\r' && f.column > 0 && f.column + unit.chars().count() >= width {
+            writeln!(f, "\\")?;
+        }
+        write!(f, "{}", unit)?;
+    }
+    Ok(())
+}
+
+fn to_ascii(s: &str, policy: AsciiPolicy) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii() {
+            result.push(c);
+            continue;
+        }
+        match policy {
+            AsciiPolicy::Strip => (),
+            AsciiPolicy::Replace => result.push('?'),
+            AsciiPolicy::Transliterate => result.push_str(transliterate(c).unwrap_or("?")),
+        }
+    }
+    result
+}
+
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'ç' | 'ć' | 'č' => "c",
+        'Ç' | 'Ć' | 'Č' => "C",
+        'ď' | 'đ' => "d",
+        'Ď' | 'Đ' => "D",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'Į' | 'İ' => "I",
+        'ł' => "l",
+        'Ł' => "L",
+        'ñ' | 'ń' | 'ň' => "n",
+        'Ñ' | 'Ń' | 'Ň' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ř' => "r",
+        'Ř' => "R",
+        'ś' | 'š' | 'ş' => "s",
+        'Ś' | 'Š' | 'Ş' => "S",
+        'ß' => "ss",
+        'ť' | 'ţ' => "t",
+        'Ť' | 'Ţ' => "T",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ý' | 'ÿ' => "y",
+        'Ý' | 'Ÿ' => "Y",
+        'ź' | 'ż' | 'ž' => "z",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_sgf_canonical() {
+        let c = SgfCollection::from_sgf("(;GN[test]CA[UTF-8]FF[4]XX[private]C[root](;W[bb]B[aa])(;B[cc]))").unwrap();
+        let mut s = String::new();
+        write_sgf_canonical(&mut s, &c, false).unwrap();
+        assert_eq!(s, "(;CA[UTF-8]FF[4]GN[test]C[root]XX[private](;B[aa]W[bb])(;B[cc]))\n");
+    }
+
+    #[test]
+    fn test_write_sgf_canonical_reparse() {
+        let c = SgfCollection::from_sgf("(;FF[4]C[root](;C[a];C[b](;C[c])
+            (;C[d];C[e]))
+            (;C[f](;C[g];C[h];C[i])
+            (;C[j])))(;FF[4]GC[line\\]\nbreak])").unwrap();
+        let mut s = String::new();
+        write_sgf_canonical(&mut s, &c, true).unwrap();
+        assert!(s.ends_with(")\n"));
+        assert_eq!(SgfCollection::from_sgf(&s).unwrap(), c);
+    }
+
+    #[test]
+    fn test_display_repairs_escapes() {
+        let mut node = SgfNode::new(vec![("LB".to_string(), vec!["aa:x:y".to_string()]), ("AB".to_string(), vec![])]);
+        node.set_property("C", vec!["a]b\\"]);
+        let s = format!("({})", node);
+        assert_eq!(s, "(;LB[aa:x\\:y]AB[]C[a\\]b\\\\])");
+        let parsed = SgfCollection::from_sgf(&s).unwrap();
+        assert_eq!(parsed[0].get_text("C").unwrap(), "a]b\\");
+        assert_eq!(parsed[0].get_point_simple_text("LB").unwrap(), ("aa".to_string(), "x:y".to_string()));
+        let mut canonical = String::new();
+        write_sgf_canonical(&mut canonical, &SgfCollection::new(vec![node.clone()]), false).unwrap();
+        assert_eq!(canonical, "(;AB[]C[a\\]b\\\\]LB[aa:x\\:y])\n");
+        // identifiers which can't be written are rejected except by Display
+        node.set_property("c]", vec!["x"]);
+        assert!(node.to_string().ends_with("c][x]"));
+        let c = SgfCollection::new(vec![node]);
+        match c.to_sgf() {
+            Err(SgfError::InvalidIdentifier(id)) => assert_eq!(id, "c]"),
+            result => panic!("{:?}", result),
+        }
+        let mut s = String::new();
+        assert!(write_sgf_with_options(&mut s, &c, &WriteOptions::default()).is_err());
+        assert!(write_sgf_canonical(&mut s, &c, false).is_err());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let mut c = SgfCollection::from_sgf("(;FF[4]SZ[19]C[root](;B[pd];W[dd])(;B[dp]))").unwrap();
+        let saved = c[0].content_hash();
+        c[0].set_simple_text("SZ", "19".to_string());
+        assert_eq!(c[0].content_hash(), saved);
+        c[0].children[0].set_point("B", "pp".to_string());
+        assert_ne!(c[0].content_hash(), saved);
+        c[0].children[0].set_point("B", "pd".to_string());
+        assert_eq!(c[0].content_hash(), saved);
+        // built in another order
+        let mut other = SgfNode::new(vec![("C".to_string(), vec!["root".to_string()]), ("SZ".to_string(), vec!["19".to_string()])]);
+        other.set_number("FF", 4);
+        other.add_child(SgfNode::new(vec![("B".to_string(), vec!["dp".to_string()])]));
+        let mut line = SgfNode::new(vec![("B".to_string(), vec!["pd".to_string()])]);
+        line.add_child(SgfNode::new(vec![("W".to_string(), vec!["dd".to_string()])]));
+        other.insert_child(0, line);
+        assert_eq!(other.content_hash(), saved);
+        // a sequence of edits agrees with the tree parsed from its canonical form
+        c[0].promote_variation(1);
+        c[0].remove_property("C");
+        c[0].children[1].children[0].set_text("C", "a ]comment[".to_string());
+        let mut s = String::new();
+        write_sgf_canonical(&mut s, &c, false).unwrap();
+        assert_eq!(SgfCollection::from_sgf(&s).unwrap()[0].content_hash(), c[0].content_hash());
+        assert_ne!(c[0].content_hash(), saved);
+    }
+
+    #[test]
+    fn test_write_sgf_ascii_only() {
+        let c = SgfCollection::from_sgf("(;PB[Gō Seigen]SZ[19];B[pd]C[良い])").unwrap();
+        let write = |policy| {
+            let mut s = String::new();
+            let alterations = write_sgf_with_options(&mut s, &c, &WriteOptions { ascii_only: policy, ..Default::default() }).unwrap();
+            (SgfCollection::from_sgf(&s).unwrap(), alterations)
+        };
+        let (stripped, alterations) = write(Some(AsciiPolicy::Strip));
+        assert_eq!(stripped[0].get_simple_text("PB").unwrap(), "G Seigen");
+        assert_eq!(stripped[0].get_number("SZ").unwrap(), 19);
+        assert_eq!(stripped[0].children[0].get_text("C").unwrap(), "");
+        assert_eq!(alterations[1], Alteration {
+            game: 0, path: vec![0], id: "C".to_string(), original: "良い".to_string(), altered: "".to_string()
+        });
+        let (replaced, _) = write(Some(AsciiPolicy::Replace));
+        assert_eq!(replaced[0].get_simple_text("PB").unwrap(), "G? Seigen");
+        let (transliterated, _) = write(Some(AsciiPolicy::Transliterate));
+        assert_eq!(transliterated[0].get_simple_text("PB").unwrap(), "Go Seigen");
+        assert_eq!(transliterated[0].children[0].get_text("C").unwrap(), "??");
+        let (intact, alterations) = write(None);
+        assert_eq!(intact, c);
+        assert!(alterations.is_empty());
+    }
+
+    #[test]
+    fn test_write_sgf_repairs_escapes() {
+        let mut root = SgfNode::new(vec![
+            ("FF".to_string(), vec!["4".to_string()]),
+            ("C".to_string(), vec!["a]b\\]c\\".to_string()]),
+            ("LB".to_string(), vec!["aa:1:2".to_string()]),
+            ("AB".to_string(), vec![]),
+        ]);
+        root.children.push(SgfNode::new(vec![("B".to_string(), vec!["aa".to_string()])]));
+        let c = SgfCollection::new(vec![root]);
+        let mut s = String::new();
+        let alterations = write_sgf_with_options(&mut s, &c, &Default::default()).unwrap();
+        assert_eq!(s, "(;FF[4]C[a\\]b\\]c\\\\]LB[aa:1\\:2]AB[];B[aa])");
+        assert_eq!(alterations.len(), 2);
+        let reparsed = SgfCollection::from_sgf(&s).unwrap();
+        assert_eq!(reparsed[0].get_text("C").unwrap(), "a]b]c\\");
+        assert_eq!(reparsed[0].get_point_simple_text("LB").unwrap(), ("aa".to_string(), "1:2".to_string()));
+        // valid trees are written as Display does
+        let c = SgfCollection::from_sgf("(;FF[4]C[a\\]b\\\\]LB[aa:1\\:2](;B[aa])(;B[bb]))").unwrap();
+        let mut s = String::new();
+        assert!(write_sgf_with_options(&mut s, &c, &Default::default()).unwrap().is_empty());
+        assert_eq!(s, c.to_string());
+    }
+
+    #[test]
+    fn test_write_sgf_pretty() {
+        let c = SgfCollection::from_sgf("(;FF[4]C[a long comment to be wrapped];B[aa](;W[bb];B[cc])(;W[dd](;B[ee])(;B[ff])))").unwrap();
+        let mut s = String::new();
+        write_sgf_with_options(&mut s, &c, &WriteOptions { pretty: true, max_line_width: Some(16), ..Default::default() }).unwrap();
+        assert_eq!(s, "(;FF[4]C[a long\\\n comment to be \\\nwrapped]\n;B[aa]\n  (;W[bb]\n  ;B[cc])\n  (;W[dd]\n    (;B[ee])\n    (;B[ff])))\n");
+        assert!(s.lines().all(|l| l.chars().count() <= 16));
+        let reparsed = SgfCollection::from_sgf(&s).unwrap();
+        assert_eq!(reparsed[0].get_text("C").unwrap(), "a long comment to be wrapped");
+        assert_eq!(reparsed[0].children, c[0].children);
+    }
+
+    #[test]
+    fn test_write_sgf_io() {
+        let c = SgfCollection::from_sgf("(;FF[4]PB[Gō Seigen];B[pd])").unwrap();
+        let mut bytes = Vec::new();
+        write_sgf_io(&mut bytes, &c, &Default::default()).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), c.to_string());
+        let mut full = [0u8; 8];
+        assert!(write_sgf_io(&mut &mut full[..], &c, &Default::default()).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Conformance suite running every fixture in tests/conformance through parse, validate and canonical write.
+//!
+//! A fixture is a pair of `<name>.sgf` and a sidecar `<name>.expected`, which has header lines,
+//! a line of `---` and a body.
+//!
+//! * `mode:` how the fixture is parsed. `strict`(default) for `SgfCollection::from_sgf`,
+//!   `lenient` for `ParseOptions::lenient` and `bytes` for `SgfCollection::from_bytes`.
+//! * `expect:` `ok` if the body is the output of `write_sgf_canonical` without line breaks,
+//!   `error` if the body is the description of the error, and `xfail` for input which the crate doesn't support yet,
+//!   whose body is the output expected once it is supported. An xfail fixture fails the suite when it passes.
+//! * `reason:` why the fixture is expected to fail. Required for xfail.
+//!
+//! Error descriptions are `parse error at <line>:<column>` for syntax errors, Display of `SgfError`
+//! for errors of `from_bytes`, and `invalid: <message>` for the first validation issue of Error severity.
+//! Trailing whitespaces of outputs and bodies are ignored. Adding a fixture needs no changes of this file.
+
+extern crate sgf;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use sgf::*;
+
+struct Expectation {
+    mode: String,
+    expect: String,
+    reason: Option<String>,
+    body: String,
+}
+
+fn read_expectation(path: &Path) -> Result<Expectation, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (header, body) = match text.find("\n---\n") {
+        Some(i) => (&text[..i], &text[i + 5..]),
+        None => return Err(format!("{}: no line of ---", path.display())),
+    };
+    let mut expectation = Expectation { mode: "strict".to_string(), expect: String::new(), reason: None, body: body.trim_end().to_string() };
+    for line in header.lines() {
+        let mut pair = line.splitn(2, ':');
+        let key = pair.next().unwrap_or("").trim();
+        let value = pair.next().unwrap_or("").trim().to_string();
+        match key {
+            "mode" => expectation.mode = value,
+            "expect" => expectation.expect = value,
+            "reason" => expectation.reason = Some(value),
+            _ => return Err(format!("{}: unknown header '{}'", path.display(), line)),
+        }
+    }
+    Ok(expectation)
+}
+
+/// Runs the pipeline and returns the canonical output or the description of the error.
+fn run(bytes: &[u8], mode: &str) -> Result<String, String> {
+    let collection = match mode {
+        "bytes" => SgfCollection::from_bytes(bytes).map_err(|e| e.to_string())?,
+        "strict" | "lenient" => {
+            let sgf = String::from_utf8(bytes.to_vec()).map_err(|_| "not UTF-8".to_string())?;
+            let options = ParseOptions { lenient: mode == "lenient", ..Default::default() };
+            SgfCollection::from_sgf_with_options(&sgf, &options)
+                .map_err(|e| format!("parse error at {}:{}", e.line, e.column))?.0
+        },
+        _ => return Err(format!("unknown mode '{}'", mode)),
+    };
+    for game in collection.iter() {
+        if let Some(issue) = game.validate().into_iter().find(|i| i.severity == Severity::Error) {
+            return Err(format!("invalid: {}", issue.message));
+        }
+    }
+    let mut s = String::new();
+    write_sgf_canonical(&mut s, &collection, false).unwrap();
+    Ok(s.trim_end().to_string())
+}
+
+fn check(sgf_path: &Path) -> Result<(), String> {
+    let expectation = read_expectation(&sgf_path.with_extension("expected"))?;
+    let bytes = fs::read(sgf_path).map_err(|e| format!("{}: {}", sgf_path.display(), e))?;
+    let result = run(&bytes, &expectation.mode);
+    let name = sgf_path.file_stem().unwrap().to_string_lossy();
+    match (&expectation.expect[..], &result) {
+        ("ok", Ok(output)) | ("error", Err(output)) if *output == expectation.body => Ok(()),
+        ("xfail", Ok(output)) if *output == expectation.body => Err(format!("{}: passes, remove xfail", name)),
+        ("xfail", _) if expectation.reason.is_some() => Ok(()),
+        ("xfail", _) => Err(format!("{}: xfail without reason", name)),
+        ("ok", _) | ("error", _) => Err(format!("{}: expected {}\n{}\nbut got\n{:?}", name, expectation.expect, expectation.body, result)),
+        (expect, _) => Err(format!("{}: unknown expect '{}'", name, expect)),
+    }
+}
+
+#[test]
+fn test_conformance() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("conformance");
+    let mut fixtures = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |e| e == "sgf"))
+        .collect::<Vec<PathBuf>>();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+    let failures = fixtures.iter().filter_map(|path| check(path).err()).collect::<Vec<_>>();
+    assert!(failures.is_empty(), "{} of {} fixtures failed\n{}", failures.len(), fixtures.len(), failures.join("\n"));
+}
@@ -0,0 +1,56 @@
+// Copyright 2016 ICHIKAWA, Yuji
+// License: MIT
+
+//! Runs the library functions which the programs in examples are built on
+//! with fixtures in tests/examples and tests/conformance.
+
+extern crate sgf;
+
+use std::fs;
+use std::path::PathBuf;
+use sgf::*;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join(name)
+}
+
+#[test]
+fn test_record() {
+    let moves = fs::read_to_string(fixture("examples/moves.txt")).unwrap();
+    let points = moves.lines().map(|line| line.trim()).filter(|point| !point.is_empty());
+    let game = SgfNode::from_points(9, points).unwrap();
+    assert_eq!(SgfCollection::new(vec![game]).to_string(), "(;FF[4]GM[1]SZ[9];B[ee];W[cc];B[];W[gg])");
+}
+
+#[test]
+fn test_record_out_of_range() {
+    match SgfNode::from_points(9, vec!["pd"]) {
+        Err(SgfError::OutOfRange(_)) => (),
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn test_convert() {
+    let input = fs::read(fixture("conformance/shift_jis.sgf")).unwrap();
+    let collection = SgfCollection::from_bytes(&input).unwrap();
+    let output = collection.to_bytes("UTF-8").unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "(;FF[4]CA[UTF-8]PB[本因坊秀策]C[黒番];B[pd])");
+    assert_eq!(collection.to_bytes("SJIS").unwrap(), input);
+    match collection.to_bytes("ISO-8859-1") {
+        Err(SgfError::Unencodable { path, .. }) => assert_eq!(path, vec![0]),
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn test_validate() {
+    let input = fs::read_to_string(fixture("conformance/move_in_root_with_game_info.sgf")).unwrap();
+    let mut collection = SgfCollection::from_sgf(&input).unwrap();
+    let reports = collection.validate_and_repair();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].issues.iter().map(|issue| issue.kind).collect::<Vec<_>>(), vec![IssueKind::MoveInRootWithGameInfo]);
+    assert!(reports[0].root_move_split);
+    assert_eq!(collection.to_string(), "(;FF[4]PB[Black];B[pd];W[dd])");
+    assert!(collection[0].validate().is_empty());
+}